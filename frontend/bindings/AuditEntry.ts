@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AuditRole } from "./AuditRole";
+
+export interface AuditEntry { room: string, timestamp: string, session: string | null, role: AuditRole, teacher: string | null, action: string, diff: unknown, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AuditEntry } from "./AuditEntry";
+
+export interface AuditTrail { entries: Array<AuditEntry>, }
\ No newline at end of file
@@ -1,13 +1,14 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CupAlert } from "./CupAlert";
+import type { CupTimeline } from "./CupTimeline";
+import type { PlaylistInfo } from "./PlaylistInfo";
 import type { QuestionId } from "./QuestionId";
 import type { QuestionInfo } from "./QuestionInfo";
 import type { QuestionPublication } from "./QuestionPublication";
+import type { QuestionResults } from "./QuestionResults";
+import type { RaisedHand } from "./RaisedHand";
+import type { RoomEventRecord } from "./RoomEventRecord";
 import type { RoomInfo } from "./RoomInfo";
+import type { StudentDetail } from "./StudentDetail";
 
-export type ClientMessage =
-  | { kind: "Ok" }
-  | { kind: "RoomInfo"; payload: RoomInfo }
-  | { kind: "QuestionsInfo"; payload: Array<QuestionInfo> }
-  | { kind: "QuestionPublication"; payload: QuestionPublication }
-  | { kind: "QuestionDelete"; payload: QuestionId }
-  | { kind: "Error"; payload: string };
+export type ClientMessage = { kind: "Ok" } | { kind: "RoomInfo", payload: RoomInfo } | { kind: "QuestionsInfo", payload: Array<QuestionInfo> } | { kind: "QuestionPublication", payload: QuestionPublication } | { kind: "QuestionDelete", payload: QuestionId } | { kind: "StudentsInfo", payload: Array<StudentDetail> } | { kind: "CupTimeline", payload: CupTimeline } | { kind: "HandsQueue", payload: Array<RaisedHand> } | { kind: "HandPosition", payload: number | null } | { kind: "CalledOn" } | { kind: "CupCleared" } | { kind: "CupAlert", payload: CupAlert } | { kind: "QuestionResults", payload: QuestionResults } | { kind: "Playlist", payload: PlaylistInfo } | { kind: "ServerShutdown", payload: { reconnect_after: number, } } | { kind: "Announcement", payload: string } | { kind: "Disconnected", payload: string } | { kind: "ReplayEvent", payload: RoomEventRecord } | { kind: "ReplayFinished" } | { kind: "Error", payload: string };
\ No newline at end of file
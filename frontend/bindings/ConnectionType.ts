@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type ConnectionType = "Student" | "Teacher";
+export type ConnectionType = "Student" | "Teacher" | "Assistant" | "Projector";
\ No newline at end of file
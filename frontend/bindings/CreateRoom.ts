@@ -1,5 +1,7 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CupAlertRule } from "./CupAlertRule";
+import type { CupResetOptions } from "./CupResetOptions";
+import type { CupSignal } from "./CupSignal";
+import type { ResultsMode } from "./ResultsMode";
 
-export interface CreateRoom {
-  new_room: string;
-}
+export interface CreateRoom { new_room: string, signals: Array<CupSignal> | null, cup_reset: CupResetOptions, alert_rules: Array<CupAlertRule>, auto_share_results: ResultsMode | null, template: string | null, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CupAlertCondition } from "./CupAlertCondition";
+
+export interface CupAlert { rule: string, condition: CupAlertCondition, ratio: number, connections: number, }
\ No newline at end of file
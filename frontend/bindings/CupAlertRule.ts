@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CupAlertCondition } from "./CupAlertCondition";
+
+export interface CupAlertRule { id: string, condition: CupAlertCondition, hold_secs: number, cooldown_secs: number | null, }
\ No newline at end of file
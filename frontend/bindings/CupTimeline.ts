@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CupBucket } from "./CupBucket";
+import type { PublicationMark } from "./PublicationMark";
+
+export interface CupTimeline { bucket_secs: number, buckets: Array<CupBucket>, publications: Array<PublicationMark>, }
\ No newline at end of file
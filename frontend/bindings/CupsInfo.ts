@@ -1,5 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RoomSummary } from "./RoomSummary";
 
-export interface CupsInfo {
-  rooms: Array<string>;
-}
+export interface CupsInfo { rooms: Array<RoomSummary>, }
\ No newline at end of file
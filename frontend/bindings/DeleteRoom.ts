@@ -1,5 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export interface DeleteRoom {
-  room: string;
-}
+export interface DeleteRoom { room: string, }
\ No newline at end of file
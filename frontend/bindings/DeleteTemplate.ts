@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type CupColor = "Green" | "Yellow" | "Red";
+export interface DeleteTemplate { name: string, }
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { QuestionId } from "./QuestionId";
+import type { ScheduledQuestion } from "./ScheduledQuestion";
+
+export interface PlaylistInfo { questions: Array<QuestionId>, position: number | null, scheduled: Array<ScheduledQuestion>, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { QuestionId } from "./QuestionId";
+
+export interface PublicationMark { timestamp: string, id: QuestionId, title: string, }
\ No newline at end of file
@@ -1,6 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export interface Question {
-  title: string;
-  options: Array<string>;
-}
+export interface Question { title: string, options: Array<string>, correct: number | null, }
\ No newline at end of file
@@ -1,6 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export interface QuestionAnswer {
-  id: string;
-  answer: number;
-}
+export interface QuestionAnswer { id: string, answer: number, }
\ No newline at end of file
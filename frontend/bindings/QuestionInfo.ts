@@ -1,9 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { QuestionId } from "./QuestionId";
 
-export interface QuestionInfo {
-  id: QuestionId;
-  title: string;
-  options: Array<string>;
-  answers: Array<number>;
-}
+export interface QuestionInfo { id: QuestionId, title: string, options: Array<string>, correct: number | null, answers: Array<number>, created_at: string, }
\ No newline at end of file
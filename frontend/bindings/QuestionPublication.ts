@@ -1,9 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { QuestionId } from "./QuestionId";
 
-export interface QuestionPublication {
-  id: QuestionId;
-  title: string;
-  options: Array<string>;
-  secs: number;
-}
+export interface QuestionPublication { id: QuestionId, title: string, options: Array<string>, secs: number, }
\ No newline at end of file
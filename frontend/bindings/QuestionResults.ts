@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { QuestionId } from "./QuestionId";
+
+export interface QuestionResults { id: QuestionId, title: string, options: Array<string>, counts: Array<number> | null, percentages: Array<number> | null, correct: number | null, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CupAlertRule } from "./CupAlertRule";
+
+export interface RoomAlertRules { room: string, rules: Array<CupAlertRule>, }
\ No newline at end of file
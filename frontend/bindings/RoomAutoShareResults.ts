@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ResultsMode } from "./ResultsMode";
+
+export interface RoomAutoShareResults { room: string, mode: ResultsMode | null, }
\ No newline at end of file
@@ -1,7 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { ConnectionType } from "./ConnectionType";
 
-export interface RoomConnectInfo {
-  room_name: string;
-  connection_type: ConnectionType;
-}
+export interface RoomConnectInfo { room_name: string, connection_type: ConnectionType, lti_launch: string | null, display_name: string | null, student_id: string | null, }
\ No newline at end of file
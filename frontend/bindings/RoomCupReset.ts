@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CupResetOptions } from "./CupResetOptions";
+
+export interface RoomCupReset { room: string, options: CupResetOptions, }
\ No newline at end of file
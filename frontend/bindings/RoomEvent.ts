@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ConnectionType } from "./ConnectionType";
+import type { SignalId } from "./SignalId";
+
+export type RoomEvent = { kind: "StudentJoined", payload: { id: string, } } | { kind: "TeacherJoined", payload: { id: string, connection_type: ConnectionType, } } | { kind: "Left", payload: { id: string, } } | { kind: "CupChanged", payload: { student: string, color: SignalId | null, } } | { kind: "QuestionCreated", payload: { id: string, title: string, options: Array<string>, correct: number | null, } } | { kind: "QuestionModified", payload: { id: string, title: string | null, options: Array<string> | null, } } | { kind: "QuestionDeleted", payload: { id: string, } } | { kind: "QuestionsReordered", payload: { ids: Array<string>, } } | { kind: "QuestionPublished", payload: { id: string, secs: number, } } | { kind: "QuestionAnswered", payload: { id: string, student: string, answer: number, } } | { kind: "QuestionClosed", payload: { id: string, } } | { kind: "PlaylistChanged", payload: { ids: Array<string>, } } | { kind: "Restored" };
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { IdentityMode } from "./IdentityMode";
+
+export interface RoomIdentityMode { room: string, mode: IdentityMode, }
\ No newline at end of file
@@ -1,9 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CupSignal } from "./CupSignal";
 
-export interface RoomInfo {
-  name: string;
-  connections: number;
-  green: number;
-  yellow: number;
-  red: number;
-}
+export interface RoomInfo { name: string, connections: number, signals: Array<CupSignal>, cups: Record<string, number>, }
\ No newline at end of file
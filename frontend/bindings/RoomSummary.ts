@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CupResetOptions } from "./CupResetOptions";
+import type { IdentityMode } from "./IdentityMode";
+import type { ResultsMode } from "./ResultsMode";
+
+export interface RoomSummary { name: string, owner: string | null, co_owners: Array<string>, created_at: string, identity_mode: IdentityMode, cup_reset: CupResetOptions, auto_share_results: ResultsMode | null, students: number, questions: number, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { QuestionId } from "./QuestionId";
+
+export interface ScheduleQuestion { id: QuestionId, secs: number, at: string | null, delay_secs: number | null, }
\ No newline at end of file
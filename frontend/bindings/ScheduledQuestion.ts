@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { QuestionId } from "./QuestionId";
+
+export interface ScheduledQuestion { id: QuestionId, at: string, secs: number, }
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { QuestionId } from "./QuestionId";
+import type { ResultsMode } from "./ResultsMode";
+
+export interface ShareResults { id: QuestionId, mode: ResultsMode, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SignalId } from "./SignalId";
+
+export interface StudentDetail { id: string, name: string, student_id: string | null, cup: SignalId | null, answers: Record<string, number>, }
\ No newline at end of file
@@ -1,17 +1,15 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
-import type { CupColor } from "./CupColor";
+import type { CallOnRequest } from "./CallOnRequest";
+import type { CupTimelineRequest } from "./CupTimelineRequest";
+import type { PlaylistStep } from "./PlaylistStep";
 import type { PublishQuestion } from "./PublishQuestion";
 import type { Question } from "./Question";
 import type { QuestionAnswer } from "./QuestionAnswer";
 import type { QuestionId } from "./QuestionId";
 import type { QuestionModification } from "./QuestionModification";
 import type { RoomConnectInfo } from "./RoomConnectInfo";
+import type { ScheduleQuestion } from "./ScheduleQuestion";
+import type { ShareResults } from "./ShareResults";
+import type { SignalId } from "./SignalId";
 
-export type WSMessage =
-  | { task: "RoomConnect"; payload: RoomConnectInfo }
-  | { task: "ChooseCup"; payload: CupColor }
-  | { task: "CreateQuestion"; payload: Question }
-  | { task: "PublishQuestion"; payload: PublishQuestion }
-  | { task: "DeleteQuestion"; payload: QuestionId }
-  | { task: "ModifyQuestion"; payload: QuestionModification }
-  | { task: "AnswerQuestion"; payload: QuestionAnswer };
+export type WSMessage = { task: "RoomConnect", payload: RoomConnectInfo } | { task: "ChooseCup", payload: SignalId } | { task: "CreateQuestion", payload: Question } | { task: "PublishQuestion", payload: PublishQuestion } | { task: "DeleteQuestion", payload: QuestionId } | { task: "ModifyQuestion", payload: QuestionModification } | { task: "AnswerQuestion", payload: QuestionAnswer } | { task: "SubmitGrades" } | { task: "GetCupTimeline", payload: CupTimelineRequest } | { task: "RaiseHand" } | { task: "LowerHand" } | { task: "CallOn", payload: CallOnRequest } | { task: "ClearHands" } | { task: "ResetCups" } | { task: "ShareResults", payload: ShareResults } | { task: "SetPlaylist", payload: Array<QuestionId> } | { task: "NextQuestion", payload: PlaylistStep } | { task: "PreviousQuestion", payload: PlaylistStep } | { task: "ScheduleQuestion", payload: ScheduleQuestion } | { task: "CancelSchedule", payload: QuestionId } | { task: "ReorderQuestions", payload: Array<QuestionId> };
\ No newline at end of file
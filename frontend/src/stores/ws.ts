@@ -2,21 +2,40 @@ import { writable } from "svelte/store";
 import type { WSMessage } from "bindings/WSMessage";
 import type { ClientMessage } from "bindings/ClientMessage";
 import type { ConnectionType } from "bindings/ConnectionType";
-import type { CupColor } from "bindings/CupColor";
+import type { SignalId } from "bindings/SignalId";
+import type { CupSignal } from "bindings/CupSignal";
 import type { Question } from "bindings/Question";
 import type { QuestionInfo } from "bindings/QuestionInfo";
 import type { QuestionPublication } from "bindings/QuestionPublication";
+import type { StudentDetail } from "bindings/StudentDetail";
+import type { CupTimeline } from "bindings/CupTimeline";
+import type { RaisedHand } from "bindings/RaisedHand";
+import type { CupAlert } from "bindings/CupAlert";
+import type { QuestionResults } from "bindings/QuestionResults";
+import type { ResultsMode } from "bindings/ResultsMode";
+import type { PlaylistInfo } from "bindings/PlaylistInfo";
 
 export interface WSData {
   room_name: string;
   status: "disconnected" | "connected" | "working" | "error";
   connections: number;
-  cups: {
-    green: number;
-    yellow: number;
-    red: number;
-  };
+  signals: CupSignal[];
+  /** Cup chosen by the student */
+  cup: SignalId | null;
+  cups: Record<string, number>;
   questions: QuestionInfo[];
+  students: StudentDetail[];
+  timeline: CupTimeline | null;
+  hands: RaisedHand[];
+  /** Alerts not dismissed yet, for teachers */
+  alerts: CupAlert[];
+  /** Position in the raised hands queue, for students */
+  hand_position: number | null;
+  called_on: boolean;
+  /** Playlist and scheduled publications, for teachers */
+  playlist: PlaylistInfo | null;
+  /** Last maintenance announcement from the administrators */
+  announcement: string | null;
   error_msg: string | null;
 }
 
@@ -30,7 +49,8 @@ export const getWSStore = (
     ws.send(JSON.stringify(msg));
   };
 
-  const chooseCup = (cup: CupColor) => {
+  const chooseCup = (cup: SignalId) => {
+    wsStore.update((d) => ({ ...d, cup }));
     sendWSMessage({
       task: "ChooseCup",
       payload: cup,
@@ -51,6 +71,13 @@ export const getWSStore = (
     });
   };
 
+  const reorderQuestions = (question_ids: string[]) => {
+    sendWSMessage({
+      task: "ReorderQuestions",
+      payload: question_ids,
+    });
+  };
+
   const publishQuestion = (question_id: string, secs: number) => {
     sendWSMessage({
       task: "PublishQuestion",
@@ -71,19 +98,114 @@ export const getWSStore = (
     });
   };
 
+  const shareResults = (question_id: string, mode: ResultsMode) => {
+    sendWSMessage({
+      task: "ShareResults",
+      payload: { id: question_id, mode },
+    });
+  };
+
+  const setPlaylist = (question_ids: string[]) => {
+    sendWSMessage({
+      task: "SetPlaylist",
+      payload: question_ids,
+    });
+  };
+
+  const nextQuestion = (secs: number) => {
+    sendWSMessage({
+      task: "NextQuestion",
+      payload: { secs },
+    });
+  };
+
+  const previousQuestion = (secs: number) => {
+    sendWSMessage({
+      task: "PreviousQuestion",
+      payload: { secs },
+    });
+  };
+
+  const scheduleQuestion = (
+    question_id: string,
+    secs: number,
+    at: string | null,
+    delay_secs: number | null = null
+  ) => {
+    sendWSMessage({
+      task: "ScheduleQuestion",
+      payload: { id: question_id, secs, at, delay_secs },
+    });
+  };
+
+  const cancelSchedule = (question_id: string) => {
+    sendWSMessage({
+      task: "CancelSchedule",
+      payload: question_id,
+    });
+  };
+
+  const submitGrades = () => {
+    sendWSMessage({
+      task: "SubmitGrades",
+    });
+  };
+
+  const getCupTimeline = (bucket_secs: number | null = null) => {
+    sendWSMessage({
+      task: "GetCupTimeline",
+      payload: { bucket_secs },
+    });
+  };
+
+  const dismissAlert = (rule: string) => {
+    wsStore.update((d) => ({
+      ...d,
+      alerts: d.alerts.filter((alert) => alert.rule !== rule),
+    }));
+  };
+
+  const resetCups = () => {
+    sendWSMessage({ task: "ResetCups" });
+  };
+
+  const raiseHand = () => {
+    wsStore.update((d) => ({ ...d, called_on: false }));
+    sendWSMessage({ task: "RaiseHand" });
+  };
+
+  const lowerHand = () => {
+    sendWSMessage({ task: "LowerHand" });
+  };
+
+  const callOn = (student: string | null = null) => {
+    sendWSMessage({
+      task: "CallOn",
+      payload: { student },
+    });
+  };
+
+  const clearHands = () => {
+    sendWSMessage({ task: "ClearHands" });
+  };
+
   const initWS = () => {
     const url = import.meta.env.PROD
-      ? `wss://${location.host}/ws`
+      ? `${location.protocol === "https:" ? "wss" : "ws"}://${location.host}/ws`
       : "ws://localhost:8000/ws";
     const ws = new WebSocket(url);
     ws.onopen = () => {
       console.log("Starting WebSocket...");
       wsStore.update((d) => ({ ...d, status: "connected" }));
+      const params = new URLSearchParams(location.search);
       sendWSMessage({
         task: "RoomConnect",
         payload: {
           room_name,
           connection_type,
+          lti_launch: params.get("launch"),
+          display_name: params.get("name"),
+          student_id: params.get("student_id"),
         },
       });
     };
@@ -96,11 +218,8 @@ export const getWSStore = (
           wsStore.update((d) => ({
             ...d,
             connections: msg.payload.connections,
-            cups: {
-              green: msg.payload.green,
-              yellow: msg.payload.yellow,
-              red: msg.payload.red,
-            },
+            signals: msg.payload.signals,
+            cups: msg.payload.cups,
           }));
           break;
 
@@ -111,14 +230,93 @@ export const getWSStore = (
           }));
           break;
 
+        case "StudentsInfo":
+          wsStore.update((d) => ({
+            ...d,
+            students: msg.payload,
+          }));
+          break;
+
+        case "CupTimeline":
+          wsStore.update((d) => ({
+            ...d,
+            timeline: msg.payload,
+          }));
+          break;
+
+        case "HandsQueue":
+          wsStore.update((d) => ({
+            ...d,
+            hands: msg.payload,
+          }));
+          break;
+
+        case "HandPosition":
+          wsStore.update((d) => ({
+            ...d,
+            hand_position: msg.payload,
+          }));
+          break;
+
+        case "CupAlert":
+          wsStore.update((d) => ({
+            ...d,
+            alerts: [
+              ...d.alerts.filter((alert) => alert.rule !== msg.payload.rule),
+              msg.payload,
+            ],
+          }));
+          break;
+
+        case "CupCleared":
+          wsStore.update((d) => ({
+            ...d,
+            cup: null,
+          }));
+          break;
+
+        case "CalledOn":
+          wsStore.update((d) => ({
+            ...d,
+            hand_position: null,
+            called_on: true,
+          }));
+          break;
+
+        case "Playlist":
+          wsStore.update((d) => ({
+            ...d,
+            playlist: msg.payload,
+          }));
+          break;
+
         case "QuestionPublication":
           questionsStore.set(msg.payload);
           break;
 
+        case "QuestionResults":
+          resultsStore.set(msg.payload);
+          break;
+
         case "QuestionDelete":
           questionsDeleteStore.set(msg.payload);
           break;
 
+        case "ServerShutdown":
+          ws.close();
+          setTimeout(() => {
+            ws = initWS();
+          }, msg.payload.reconnect_after);
+          break;
+
+        case "Announcement":
+          wsStore.update((d) => ({
+            ...d,
+            announcement: msg.payload,
+          }));
+          break;
+
+        case "Disconnected":
         case "Error":
           wsStore.update((d) => ({
             ...d,
@@ -147,12 +345,18 @@ export const getWSStore = (
       room_name,
       status: "disconnected",
       connections: 0,
-      cups: {
-        green: 0,
-        yellow: 0,
-        red: 0,
-      },
+      signals: [],
+      cup: null,
+      cups: {},
       questions: null,
+      students: [],
+      timeline: null,
+      hands: [],
+      alerts: [],
+      hand_position: null,
+      called_on: false,
+      playlist: null,
+      announcement: null,
       error_msg: null,
     },
     () => {
@@ -172,10 +376,26 @@ export const getWSStore = (
     chooseCup,
     createQuestion,
     deleteQuestion,
+    reorderQuestions,
     publishQuestion,
     answerQuestion,
+    submitGrades,
+    shareResults,
+    getCupTimeline,
+    resetCups,
+    dismissAlert,
+    raiseHand,
+    lowerHand,
+    callOn,
+    clearHands,
+    setPlaylist,
+    nextQuestion,
+    previousQuestion,
+    scheduleQuestion,
+    cancelSchedule,
   };
 };
 
 export const questionsStore = writable<QuestionPublication>(null);
 export const questionsDeleteStore = writable<string>(null);
+export const resultsStore = writable<QuestionResults>(null);
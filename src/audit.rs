@@ -0,0 +1,439 @@
+//! Append-only trail of the changes made to each room
+
+use crate::{
+    configuration::AuditSettings, error_chain_fmt, oidc::Teacher, routes::message::ConnectionType,
+    state::RoomState,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{File, OpenOptions},
+    io::{LineWriter, Write},
+    sync::Mutex,
+};
+use ts_rs::TS;
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum AuditError {
+    #[error("Failed to open the audit log {0:?}.")]
+    OpenError(String, #[source] std::io::Error),
+}
+
+impl std::fmt::Debug for AuditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub enum AuditRole {
+    Student,
+    Teacher,
+    Assistant,
+    Projector,
+    Admin,
+    /// Changes made by the server, like scheduled publications
+    System,
+}
+
+impl From<ConnectionType> for AuditRole {
+    fn from(connection_type: ConnectionType) -> Self {
+        match connection_type {
+            ConnectionType::Student => Self::Student,
+            ConnectionType::Teacher => Self::Teacher,
+            ConnectionType::Assistant => Self::Assistant,
+            ConnectionType::Projector => Self::Projector,
+        }
+    }
+}
+
+/// Who made a change
+#[derive(Clone, Debug)]
+pub struct AuditActor {
+    /// Websocket session, `None` for REST calls
+    pub session: Option<Uuid>,
+    pub role: AuditRole,
+    /// Id of the logged in teacher
+    pub teacher: Option<String>,
+}
+
+impl AuditActor {
+    /// Teacher calling the REST API
+    pub fn api(teacher: Option<&Teacher>) -> Self {
+        Self {
+            session: None,
+            role: AuditRole::Teacher,
+            teacher: teacher.map(|teacher| teacher.id.clone()),
+        }
+    }
+
+    pub fn admin() -> Self {
+        Self {
+            session: None,
+            role: AuditRole::Admin,
+            teacher: None,
+        }
+    }
+
+    pub fn system() -> Self {
+        Self {
+            session: None,
+            role: AuditRole::System,
+            teacher: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct AuditEntry {
+    pub room: String,
+    pub timestamp: DateTime<Utc>,
+    #[ts(type = "string | null")]
+    pub session: Option<Uuid>,
+    pub role: AuditRole,
+    pub teacher: Option<String>,
+    /// `WSMessage` task, REST action or system event that made the change
+    pub action: String,
+    /// Changed fields of the room, with their value `before` and `after` the change
+    /// or the `appended` entries for the room histories
+    #[ts(type = "unknown")]
+    pub diff: Value,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct AuditTrail {
+    /// Oldest first
+    pub entries: Vec<AuditEntry>,
+}
+
+/// Filters of the audit trail
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditFilter {
+    pub session: Option<Uuid>,
+    pub action: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl AuditFilter {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.session
+            .is_none_or(|session| entry.session == Some(session))
+            && self
+                .action
+                .as_ref()
+                .is_none_or(|action| &entry.action == action)
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+    }
+}
+
+/// State of a room before a change, to find out what the change did
+#[derive(Debug)]
+pub struct RoomCapture {
+    state: Value,
+    /// The histories are only appended to, so their lengths are enough
+    cup_history: usize,
+    publications: usize,
+}
+
+impl RoomCapture {
+    pub fn new(room_state: &RoomState) -> Self {
+        Self {
+            state: capture_state(room_state),
+            cup_history: room_state.cup_history.len(),
+            publications: room_state.publications.len(),
+        }
+    }
+
+    /// Capture of a room that doesn't exist yet
+    pub fn empty() -> Self {
+        Self {
+            state: Value::Null,
+            cup_history: 0,
+            publications: 0,
+        }
+    }
+
+    /// Changes from the capture to `room_state`, `None` if nothing changed
+    fn diff(&self, room_state: Option<&RoomState>) -> Option<Value> {
+        let room_state = match room_state {
+            Some(room_state) => room_state,
+            None => return diff_values(&self.state, &Value::Null),
+        };
+        let mut diff = match diff_values(&self.state, &capture_state(room_state)) {
+            Some(Value::Object(diff)) => diff,
+            Some(diff) => return Some(diff),
+            None => Map::new(),
+        };
+        let mut append = |field: &str, appended: Option<Value>| {
+            if let Some(appended) = appended.filter(|appended| appended != &Value::Array(vec![])) {
+                diff.insert(
+                    field.to_string(),
+                    serde_json::json!({ "appended": appended }),
+                );
+            }
+        };
+        append(
+            "cup_history",
+            room_state
+                .cup_history
+                .get(self.cup_history..)
+                .and_then(|appended| serde_json::to_value(appended).ok()),
+        );
+        append(
+            "publications",
+            room_state
+                .publications
+                .get(self.publications..)
+                .and_then(|appended| serde_json::to_value(appended).ok()),
+        );
+        (!diff.is_empty()).then_some(Value::Object(diff))
+    }
+}
+
+/// Room fields tracked by the audit trail, the histories are left out
+fn capture_state(room_state: &RoomState) -> Value {
+    let mut state = match serde_json::to_value(room_state.snapshot()) {
+        Ok(Value::Object(state)) => state,
+        _ => return Value::Null,
+    };
+    state.remove("cup_history");
+    state.remove("publications");
+    state.remove("events");
+    for (field, value) in [
+        (
+            "raised_hands",
+            serde_json::to_value(&room_state.raised_hands),
+        ),
+        ("scheduled", serde_json::to_value(&room_state.scheduled)),
+    ] {
+        if let Ok(value) = value {
+            state.insert(field.to_string(), value);
+        }
+    }
+    Value::Object(state)
+}
+
+/// Objects are compared field by field, other values as a whole
+fn diff_values(before: &Value, after: &Value) -> Option<Value> {
+    match (before, after) {
+        _ if before == after => None,
+        (Value::Object(before_fields), Value::Object(after_fields)) => {
+            let diff = before_fields
+                .keys()
+                .chain(
+                    after_fields
+                        .keys()
+                        .filter(|k| !before_fields.contains_key(*k)),
+                )
+                .filter_map(|key| {
+                    let before = before_fields.get(key).unwrap_or(&Value::Null);
+                    let after = after_fields.get(key).unwrap_or(&Value::Null);
+                    diff_values(before, after).map(|diff| (key.clone(), diff))
+                })
+                .collect::<Map<_, _>>();
+            Some(Value::Object(diff))
+        }
+        _ => Some(serde_json::json!({ "before": before, "after": after })),
+    }
+}
+
+/// Changes made to a room by a message, built by its handler while holding the room
+#[derive(Debug, Default)]
+pub struct RoomDiff(Map<String, Value>);
+
+impl RoomDiff {
+    /// Records the value at `path` going from `before` to `after`, `None` when missing
+    pub fn change<T: Serialize>(
+        mut self,
+        path: &[&str],
+        before: Option<&T>,
+        after: Option<&T>,
+    ) -> Self {
+        let to_value = |value: Option<&T>| {
+            value
+                .and_then(|value| serde_json::to_value(value).ok())
+                .unwrap_or(Value::Null)
+        };
+        if let Some(diff) = diff_values(&to_value(before), &to_value(after)) {
+            self.insert(path, diff);
+        }
+        self
+    }
+
+    /// Records the entries appended to the room history `field`
+    pub fn appended<T: Serialize>(mut self, field: &str, appended: &[T]) -> Self {
+        if let (false, Ok(appended)) = (appended.is_empty(), serde_json::to_value(appended)) {
+            self.insert(&[field], serde_json::json!({ "appended": appended }));
+        }
+        self
+    }
+
+    fn insert(&mut self, path: &[&str], diff: Value) {
+        let diff = path
+            .iter()
+            .rev()
+            .fold(diff, |diff, field| serde_json::json!({ *field: diff }));
+        if let Value::Object(diff) = diff {
+            merge_fields(&mut self.0, diff);
+        }
+    }
+}
+
+/// Adds the `from` fields to `into`, merging the objects found in both
+fn merge_fields(into: &mut Map<String, Value>, from: Map<String, Value>) {
+    for (field, value) in from {
+        match (into.get_mut(&field), value) {
+            (Some(Value::Object(into)), Value::Object(from)) => merge_fields(into, from),
+            (_, value) => {
+                into.insert(field, value);
+            }
+        }
+    }
+}
+
+/// Latest entries of a room, the students ones are kept apart so they don't push out the others
+#[derive(Debug, Default)]
+struct RoomEntries {
+    /// Oldest first
+    students: VecDeque<AuditEntry>,
+    /// Oldest first
+    others: VecDeque<AuditEntry>,
+}
+
+/// Keeps the latest entries of each room in memory and writes every entry
+/// as a JSON line to the file sink, if configured.
+#[derive(Debug)]
+pub struct AuditLog {
+    max_entries_per_room: usize,
+    max_student_entries_per_room: usize,
+    /// Room name -> Entries
+    entries: Mutex<HashMap<String, RoomEntries>>,
+    sink: Option<Mutex<LineWriter<File>>>,
+}
+
+impl AuditLog {
+    pub fn new(settings: &AuditSettings) -> Result<Self, AuditError> {
+        let sink = match &settings.path {
+            Some(path) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| AuditError::OpenError(path.clone(), e))?;
+                Some(Mutex::new(LineWriter::new(file)))
+            }
+            None => None,
+        };
+        Ok(Self {
+            max_entries_per_room: settings.max_entries_per_room,
+            max_student_entries_per_room: settings.max_student_entries_per_room,
+            entries: Mutex::new(HashMap::new()),
+            sink,
+        })
+    }
+
+    /// Records what `actor` changed in `room` since `before`, `room_state` is `None` if the
+    /// room was deleted. Nothing is recorded if there are no changes.
+    pub fn record(
+        &self,
+        room: &str,
+        actor: &AuditActor,
+        action: &str,
+        before: &RoomCapture,
+        room_state: Option<&RoomState>,
+    ) {
+        if let Some(diff) = before.diff(room_state) {
+            self.push(room, actor, action, diff);
+        }
+    }
+
+    /// Records the changes `actor` made in `room`, nothing if `diff` is empty
+    pub fn record_diff(&self, room: &str, actor: &AuditActor, action: &str, diff: RoomDiff) {
+        if !diff.0.is_empty() {
+            self.push(room, actor, action, Value::Object(diff.0));
+        }
+    }
+
+    fn push(&self, room: &str, actor: &AuditActor, action: &str, diff: Value) {
+        let entry = AuditEntry {
+            room: room.to_string(),
+            timestamp: Utc::now(),
+            session: actor.session,
+            role: actor.role,
+            teacher: actor.teacher.clone(),
+            action: action.to_string(),
+            diff,
+        };
+        if let Some(sink) = &self.sink {
+            let written = serde_json::to_vec(&entry)
+                .map_err(std::io::Error::from)
+                .and_then(|mut line| {
+                    line.push(b'\n');
+                    sink.lock().unwrap().write_all(&line)
+                });
+            if let Err(e) = written {
+                tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to write the audit log.");
+            }
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let room_entries = entries.entry(entry.room.clone()).or_default();
+        let (room_entries, max_entries) = match entry.role {
+            AuditRole::Student => (
+                &mut room_entries.students,
+                self.max_student_entries_per_room,
+            ),
+            _ => (&mut room_entries.others, self.max_entries_per_room),
+        };
+        if room_entries.len() >= max_entries {
+            room_entries.pop_front();
+        }
+        room_entries.push_back(entry);
+    }
+
+    /// Applies `change` to the room, recording what it changed
+    pub fn audited<T>(
+        &self,
+        room_state: &mut RoomState,
+        actor: &AuditActor,
+        action: &str,
+        change: impl FnOnce(&mut RoomState) -> T,
+    ) -> T {
+        let before = RoomCapture::new(room_state);
+        let result = change(room_state);
+        let room = room_state.name.clone();
+        self.record(&room, actor, action, &before, Some(room_state));
+        result
+    }
+
+    /// Entries of `room` kept in memory
+    pub fn trail(&self, room: &str, filter: &AuditFilter) -> AuditTrail {
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap()
+            .get(room)
+            .map(|entries| {
+                entries
+                    .students
+                    .iter()
+                    .chain(entries.others.iter())
+                    .filter(|entry| filter.matches(entry))
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        entries.sort_by_key(|entry| entry.timestamp);
+        AuditTrail { entries }
+    }
+
+    /// Drops the entries of a deleted room from memory, they are still in the file sink
+    pub fn remove_room(&self, room: &str) {
+        self.entries.lock().unwrap().remove(room);
+    }
+}
@@ -1,12 +1,25 @@
 use config::Config;
+use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 use serde_aux::field_attributes::deserialize_number_from_string;
 use serde_with::{serde_as, DurationMilliSeconds};
-use std::time::Duration;
+use std::{net::IpAddr, time::Duration};
+
 #[derive(Clone, Deserialize)]
 pub struct Settings {
     pub application: ApplicationSettings,
     pub websocket: WSSettings,
+    pub webhooks: WebhookSettings,
+    pub limits: LimitsSettings,
+    pub audit: AuditSettings,
+    /// Rooms are only kept in memory if missing
+    pub persistence: Option<PersistenceSettings>,
+    /// LTI 1.3 launches are disabled if missing
+    pub lti: Option<LtiSettings>,
+    /// Teachers don't need to log in if missing
+    pub oidc: Option<OidcSettings>,
+    /// The admin API is disabled if missing
+    pub admin: Option<AdminSettings>,
 }
 
 #[derive(Clone, Deserialize)]
@@ -14,6 +27,57 @@ pub struct ApplicationSettings {
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub port: u16,
     pub host: String,
+    /// Key used to encrypt the session cookies, at least 32 bytes, required with OIDC or LTI.
+    /// Set it with `APP__APPLICATION__SESSION_KEY`, a random key is used if missing
+    pub session_key: Option<Secret<String>>,
+    /// Only send the session cookies over https
+    pub secure_cookies: bool,
+    /// Origins besides the server itself that can open websockets and call the API
+    #[serde(default)]
+    pub allowed_origins: AllowedOrigins,
+    /// Serve https directly instead of behind a proxy
+    pub tls: Option<TlsSettings>,
+    pub shutdown: ShutdownSettings,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, Deserialize)]
+pub struct ShutdownSettings {
+    /// In milliseconds, time given to the clients to disconnect before stopping the server
+    #[serde_as(as = "DurationMilliSeconds<u64>")]
+    pub drain_timeout: Duration,
+    /// In milliseconds, sent to the clients so they know when to reconnect
+    #[serde_as(as = "DurationMilliSeconds<u64>")]
+    pub reconnect_after: Duration,
+}
+
+/// The certificate is reloaded from its files on SIGHUP
+#[derive(Clone, Debug, Deserialize)]
+pub struct TlsSettings {
+    /// PEM file with the certificate chain
+    pub cert_path: String,
+    /// PEM file with the private key
+    pub key_path: String,
+    /// Port of an http listener that redirects to https
+    pub redirect_port: Option<u16>,
+}
+
+/// Origins like `https://class.example.com`, `*` allows any origin
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct AllowedOrigins(pub Vec<String>);
+
+impl AllowedOrigins {
+    pub fn allows_any(&self) -> bool {
+        self.0.iter().any(|origin| origin == "*")
+    }
+
+    /// Same origin requests are always allowed, `host` is the `Host` header of the request
+    pub fn is_allowed(&self, origin: &str, host: Option<&str>) -> bool {
+        let same_origin =
+            host.is_some() && origin.split_once("://").map(|(_, origin_host)| origin_host) == host;
+        same_origin || self.allows_any() || self.0.iter().any(|allowed| allowed == origin)
+    }
 }
 
 #[serde_as]
@@ -25,6 +89,154 @@ pub struct WSSettings {
     /// In milliseconds
     #[serde_as(as = "DurationMilliSeconds<u64>")]
     pub client_timeout: Duration,
+    /// Messages allowed to each connection
+    pub session_rate_limit: RateLimitSettings,
+    /// Messages allowed to all the connections from the same IP
+    pub ip_rate_limit: RateLimitSettings,
+    /// Maximum size of text messages, in bytes
+    pub max_message_size: usize,
+    /// Rejected messages in a row before disconnecting the client
+    pub max_rejected_messages: u32,
+    /// Open connections allowed in the whole server
+    pub max_connections: usize,
+    pub max_connections_per_ip: usize,
+    /// Different rooms that the connections from the same browser session can join
+    pub max_rooms_per_client: usize,
+    /// Reverse proxies trusted to give the client address in `X-Forwarded-For`,
+    /// otherwise the address of the peer is used
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+/// Token bucket refilled over time
+#[derive(Clone, Debug, Deserialize)]
+pub struct RateLimitSettings {
+    /// Tokens added every second
+    pub per_second: f64,
+    /// Maximum tokens, also the initial amount
+    pub burst: u32,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookSettings {
+    /// Urls that receive the events of every room
+    #[serde(default)]
+    pub urls: Vec<String>,
+    /// Key used to sign the payloads (HMAC-SHA256), required to send any webhook
+    pub secret: Option<Secret<String>>,
+    /// Lets the room webhooks target loopback and private addresses, for local testing
+    #[serde(default)]
+    pub allow_private_urls: bool,
+    /// In milliseconds
+    #[serde_as(as = "DurationMilliSeconds<u64>")]
+    pub timeout: Duration,
+    /// Retries after the first failed delivery
+    pub max_retries: u32,
+    /// In milliseconds, doubled after every failed retry
+    #[serde_as(as = "DurationMilliSeconds<u64>")]
+    pub retry_interval: Duration,
+    /// Ratio of alert cups (over connected students) that triggers a `CupThresholdCrossed` event
+    pub red_cup_threshold: f64,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct AdminSettings {
+    /// Bearer token required by the `/admin` routes
+    pub token: Secret<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AuditSettings {
+    /// JSON-lines file where every audit entry is appended
+    pub path: Option<String>,
+    /// Entries kept in memory for each room, the oldest ones are dropped first
+    pub max_entries_per_room: usize,
+    /// Entries of students kept in memory for each room, apart from the other entries
+    pub max_student_entries_per_room: usize,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PersistenceSettings {
+    /// JSON file where the rooms are saved on shutdown and loaded on start
+    pub path: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LimitsSettings {
+    /// Maximum characters of room and template names
+    pub max_name_length: usize,
+    /// Characters allowed in names besides letters and digits
+    pub name_symbols: String,
+    /// Maximum characters of question titles
+    pub max_title_length: usize,
+    /// Maximum characters of each question option
+    pub max_option_length: usize,
+    /// Maximum options of a question, at least 2 are always required
+    pub max_options: usize,
+    pub max_questions_per_room: usize,
+    /// Maximum seconds a question can be published for
+    pub max_publication_secs: usize,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LtiSettings {
+    /// Platform issuer (`iss` claim of the id tokens)
+    pub issuer: String,
+    /// Client id assigned by the platform to this tool
+    pub client_id: String,
+    /// If set, only launches from this deployment are accepted
+    pub deployment_id: Option<String>,
+    /// Platform OIDC authorization endpoint
+    pub auth_url: String,
+    /// Platform public keys used to validate the id tokens
+    pub jwks_url: String,
+    /// Url where the platform posts the id tokens (`/lti/launch`)
+    pub launch_url: String,
+    /// Platform OAuth2 token endpoint, needed to send grades
+    pub token_url: Option<String>,
+    /// Tool private key (PEM), needed to send grades
+    pub private_key: Option<Secret<String>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OidcSettings {
+    /// The provider metadata is read from `{issuer_url}/.well-known/openid-configuration`
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: Option<Secret<String>>,
+    /// Url of the `/auth/callback` route
+    pub redirect_url: String,
+    /// Scopes requested besides `openid`
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl Settings {
+    /// Checks the settings that can't be left to their defaults
+    pub fn validate(&self) -> Result<(), config::ConfigError> {
+        match &self.application.session_key {
+            None if self.oidc.is_some() || self.lti.is_some() => {
+                return Err(config::ConfigError::Message(
+                    "application.session_key is required to log in with OIDC or LTI, set it with \
+                     APP__APPLICATION__SESSION_KEY"
+                        .to_string(),
+                ))
+            }
+            Some(key) if key.expose_secret().len() < 32 => {
+                return Err(config::ConfigError::Message(
+                    "application.session_key needs at least 32 bytes".to_string(),
+                ))
+            }
+            _ => {}
+        }
+        if !self.webhooks.urls.is_empty() && self.webhooks.secret.is_none() {
+            return Err(config::ConfigError::Message(
+                "webhooks.secret is required to send webhooks to webhooks.urls".to_string(),
+            ));
+        }
+        Ok(())
+    }
 }
 
 pub fn get_configuration() -> Result<Settings, config::ConfigError> {
@@ -0,0 +1,152 @@
+use crate::{configuration::WSSettings, error_chain_fmt};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(thiserror::Error)]
+pub enum ConnectionLimitError {
+    #[error("Too many connections from this address.")]
+    TooManyConnections,
+    #[error("The server is full, try again later.")]
+    ServerFull,
+}
+
+impl std::fmt::Debug for ConnectionLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Connections {
+    total: usize,
+    /// Ip -> Connections from that ip
+    ips: HashMap<String, usize>,
+    /// Client -> Room name -> Connections of the client to that room
+    clients: HashMap<String, HashMap<String, usize>>,
+}
+
+impl Connections {
+    fn leave_room(&mut self, client: &str, room: &str) {
+        if let Some(rooms) = self.clients.get_mut(client) {
+            if let Some(count) = rooms.get_mut(room) {
+                *count -= 1;
+                if *count == 0 {
+                    rooms.remove(room);
+                }
+            }
+            if rooms.is_empty() {
+                self.clients.remove(client);
+            }
+        }
+    }
+}
+
+/// Keeps track of the open websocket connections
+#[derive(Debug)]
+pub struct ConnectionLimiter {
+    max_connections: usize,
+    max_connections_per_ip: usize,
+    max_rooms_per_client: usize,
+    connections: Arc<Mutex<Connections>>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(settings: &WSSettings) -> Self {
+        Self {
+            max_connections: settings.max_connections,
+            max_connections_per_ip: settings.max_connections_per_ip,
+            max_rooms_per_client: settings.max_rooms_per_client,
+            connections: Arc::new(Mutex::new(Connections::default())),
+        }
+    }
+
+    /// The connection is counted until the permit is dropped, `client` identifies the
+    /// browser session to count its rooms
+    pub fn connect(
+        &self,
+        ip: &str,
+        client: &str,
+    ) -> Result<ConnectionPermit, ConnectionLimitError> {
+        let mut connections = self.connections.lock().unwrap();
+        if connections.total >= self.max_connections {
+            return Err(ConnectionLimitError::ServerFull);
+        }
+        let ip_connections = connections.ips.get(ip).copied().unwrap_or(0);
+        if ip_connections >= self.max_connections_per_ip {
+            return Err(ConnectionLimitError::TooManyConnections);
+        }
+        *connections.ips.entry(ip.to_string()).or_default() += 1;
+        connections.total += 1;
+        Ok(ConnectionPermit {
+            ip: ip.to_string(),
+            client: client.to_string(),
+            room: None,
+            max_rooms: self.max_rooms_per_client,
+            connections: self.connections.clone(),
+        })
+    }
+
+    /// Open connections
+    pub fn connections(&self) -> usize {
+        self.connections.lock().unwrap().total
+    }
+
+    /// Ips with open connections
+    pub fn connected_ips(&self) -> usize {
+        self.connections.lock().unwrap().ips.len()
+    }
+}
+
+#[derive(Debug)]
+pub struct ConnectionPermit {
+    ip: String,
+    client: String,
+    room: Option<String>,
+    max_rooms: usize,
+    connections: Arc<Mutex<Connections>>,
+}
+
+impl ConnectionPermit {
+    pub fn ip(&self) -> &str {
+        &self.ip
+    }
+
+    /// Moves the connection to `room`, returns false if the client is already
+    /// in too many rooms.
+    pub fn join_room(&mut self, room: &str) -> bool {
+        if self.room.as_deref() == Some(room) {
+            return true;
+        }
+        let mut connections = self.connections.lock().unwrap();
+        // The previous room doesn't count if the connection was the only one of the client in it
+        if let Some(previous) = &self.room {
+            connections.leave_room(&self.client, previous);
+        }
+        let rooms = connections.clients.entry(self.client.clone()).or_default();
+        if !rooms.contains_key(room) && rooms.len() >= self.max_rooms {
+            if let Some(previous) = &self.room {
+                *rooms.entry(previous.clone()).or_default() += 1;
+            }
+            return false;
+        }
+        *rooms.entry(room.to_string()).or_default() += 1;
+        self.room = Some(room.to_string());
+        true
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let mut connections = self.connections.lock().unwrap();
+        connections.total -= 1;
+        if let Some(room) = &self.room {
+            connections.leave_room(&self.client, room);
+        }
+        if let Some(count) = connections.ips.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                connections.ips.remove(&self.ip);
+            }
+        }
+    }
+}
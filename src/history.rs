@@ -0,0 +1,240 @@
+//! Ordered log of the events of each room, to replay it or derive a view of it.
+//! The live room is kept in `RoomState`, `RoomState::view` takes the same view from it to check
+//! the log against.
+
+use crate::{
+    routes::message::{ConnectionType, SignalId},
+    state::{move_to_front, QuestionState},
+};
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use ts_rs::TS;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, TS)]
+#[serde(tag = "kind", content = "payload")]
+#[ts(export, export_to = "frontend/bindings/")]
+pub enum RoomEvent {
+    StudentJoined {
+        #[ts(type = "string")]
+        id: Uuid,
+    },
+    /// Teachers, assistants and projectors
+    TeacherJoined {
+        #[ts(type = "string")]
+        id: Uuid,
+        connection_type: ConnectionType,
+    },
+    /// The cup and answers of a student leave with it
+    Left {
+        #[ts(type = "string")]
+        id: Uuid,
+    },
+    /// `color` is `null` when the cup is cleared
+    CupChanged {
+        #[ts(type = "string")]
+        student: Uuid,
+        color: Option<SignalId>,
+    },
+    QuestionCreated {
+        #[ts(type = "string")]
+        id: Uuid,
+        title: String,
+        options: Vec<String>,
+        correct: Option<usize>,
+    },
+    QuestionModified {
+        #[ts(type = "string")]
+        id: Uuid,
+        title: Option<String>,
+        options: Option<Vec<String>>,
+    },
+    QuestionDeleted {
+        #[ts(type = "string")]
+        id: Uuid,
+    },
+    /// The `ids` questions moved to the front in the given order
+    QuestionsReordered {
+        #[ts(type = "Array<string>")]
+        ids: Vec<Uuid>,
+    },
+    QuestionPublished {
+        #[ts(type = "string")]
+        id: Uuid,
+        secs: usize,
+    },
+    QuestionAnswered {
+        #[ts(type = "string")]
+        id: Uuid,
+        #[ts(type = "string")]
+        student: Uuid,
+        answer: usize,
+    },
+    /// The publication time of the question is over
+    QuestionClosed {
+        #[ts(type = "string")]
+        id: Uuid,
+    },
+    /// The playlist was replaced, starting again from its beginning
+    PlaylistChanged {
+        #[ts(type = "Array<string>")]
+        ids: Vec<Uuid>,
+    },
+    /// The room was loaded from a snapshot, its connections and publication are gone but not
+    /// their answers
+    Restored,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct RoomEventRecord {
+    pub timestamp: DateTime<Utc>,
+    pub event: RoomEvent,
+}
+
+impl RoomEventRecord {
+    pub fn new(event: RoomEvent) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            event,
+        }
+    }
+
+    /// Creation of the question, at its creation time
+    pub fn question_created(id: Uuid, question: &QuestionState) -> Self {
+        Self {
+            timestamp: question.created_at,
+            event: RoomEvent::QuestionCreated {
+                id,
+                title: question.title.clone(),
+                options: question.options.clone(),
+                correct: question.correct,
+            },
+        }
+    }
+}
+
+/// Room state derived by folding its events
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RoomView {
+    /// Connected students -> Chosen cup
+    pub students: HashMap<Uuid, Option<SignalId>>,
+    /// Teachers, assistants and projectors
+    pub teachers: HashMap<Uuid, ConnectionType>,
+    /// QuestionId -> QuestionState, in creation order
+    pub questions: IndexMap<Uuid, QuestionState>,
+    /// Last published question, until its publication time is over
+    pub published: Option<Uuid>,
+    pub playlist: Vec<Uuid>,
+}
+
+impl RoomView {
+    pub fn fold<'a>(records: impl IntoIterator<Item = &'a RoomEventRecord>) -> Self {
+        records
+            .into_iter()
+            .fold(Self::default(), |mut view, record| {
+                view.apply(record);
+                view
+            })
+    }
+
+    /// Events that don't apply to the current view (like answers to deleted questions) are ignored
+    pub fn apply(&mut self, record: &RoomEventRecord) {
+        match &record.event {
+            RoomEvent::StudentJoined { id } => {
+                self.students.insert(*id, None);
+            }
+            RoomEvent::TeacherJoined {
+                id,
+                connection_type,
+            } => {
+                self.teachers.insert(*id, *connection_type);
+            }
+            RoomEvent::Left { id } => {
+                self.students.remove(id);
+                self.teachers.remove(id);
+                self.questions.values_mut().for_each(|question| {
+                    question.answers.remove(id);
+                });
+            }
+            RoomEvent::CupChanged { student, color } => {
+                if let Some(cup) = self.students.get_mut(student) {
+                    *cup = color.clone();
+                }
+            }
+            RoomEvent::QuestionCreated {
+                id,
+                title,
+                options,
+                correct,
+            } => {
+                let mut question = QuestionState::new(title.clone(), options.clone(), *correct);
+                question.created_at = record.timestamp;
+                self.questions.insert(*id, question);
+            }
+            RoomEvent::QuestionModified { id, title, options } => {
+                if let Some(question) = self.questions.get_mut(id) {
+                    question.modify(title.clone(), options.clone());
+                }
+            }
+            RoomEvent::QuestionDeleted { id } => {
+                self.questions.shift_remove(id);
+                self.playlist.retain(|question| question != id);
+                if self.published == Some(*id) {
+                    self.published = None;
+                }
+            }
+            RoomEvent::QuestionsReordered { ids } => {
+                move_to_front(&mut self.questions, ids);
+            }
+            RoomEvent::QuestionPublished { id, .. } => {
+                self.published = Some(*id);
+            }
+            RoomEvent::QuestionAnswered {
+                id,
+                student,
+                answer,
+            } => {
+                if let Some(question) = self.questions.get_mut(id) {
+                    // Answers were validated when recorded
+                    let _ = question.answer(*student, *answer);
+                }
+            }
+            RoomEvent::QuestionClosed { id } => {
+                if self.published == Some(*id) {
+                    self.published = None;
+                }
+            }
+            RoomEvent::PlaylistChanged { ids } => {
+                self.playlist = ids.clone();
+            }
+            RoomEvent::Restored => {
+                self.students.clear();
+                self.teachers.clear();
+                self.published = None;
+            }
+        }
+    }
+}
+
+/// Questions are compared in order
+impl PartialEq for RoomView {
+    fn eq(&self, other: &Self) -> bool {
+        self.students == other.students
+            && self.teachers == other.teachers
+            && self.questions.iter().eq(other.questions.iter())
+            && self.published == other.published
+            && self.playlist == other.playlist
+    }
+}
+
+/// Events of a room with the state derived from them
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RoomHistory {
+    pub events: Vec<RoomEventRecord>,
+    pub state: RoomView,
+    /// The same view taken from the live room, it matches `state`
+    pub room: RoomView,
+}
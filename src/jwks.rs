@@ -0,0 +1,73 @@
+//! Public keys of the identity providers
+
+use crate::error_chain_fmt;
+use anyhow::Context;
+use jsonwebtoken::{jwk::JwkSet, DecodingKey};
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Unknown keys only fetch the JWKS again once this time passed since the last fetch
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(thiserror::Error)]
+pub enum JwksError {
+    #[error("Unknown signing key: {0:?}.")]
+    UnknownKey(Option<String>),
+    #[error("Invalid signing key.")]
+    InvalidKey(#[source] jsonwebtoken::errors::Error),
+    #[error("Something went wrong.")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for JwksError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Last fetched JWKS and when it was fetched
+#[derive(Debug, Default)]
+pub struct JwksCache(Mutex<Option<(JwkSet, Instant)>>);
+
+impl JwksCache {
+    /// Gets the key `kid` (or the first one if missing) from the JWKS at `url`,
+    /// which is fetched again if the key is unknown (at most once per `MIN_REFRESH_INTERVAL`)
+    pub async fn decoding_key(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+        kid: Option<String>,
+    ) -> Result<DecodingKey, JwksError> {
+        let find_key = |jwks: &JwkSet| match &kid {
+            Some(kid) => jwks.find(kid).cloned(),
+            None => jwks.keys.first().cloned(),
+        };
+        let (cached, refresh) = match self.0.lock().unwrap().as_ref() {
+            Some((jwks, fetched_at)) => {
+                (find_key(jwks), fetched_at.elapsed() >= MIN_REFRESH_INTERVAL)
+            }
+            None => (None, true),
+        };
+        let jwk = match cached {
+            Some(jwk) => jwk,
+            None if !refresh => return Err(JwksError::UnknownKey(kid)),
+            None => {
+                let jwks = client
+                    .get(url)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .context("Failed to fetch JWKS.")?
+                    .json::<JwkSet>()
+                    .await
+                    .context("Failed to parse JWKS.")?;
+                let jwk = find_key(&jwks);
+                *self.0.lock().unwrap() = Some((jwks, Instant::now()));
+                jwk.ok_or(JwksError::UnknownKey(kid))?
+            }
+        };
+        DecodingKey::from_jwk(&jwk).map_err(JwksError::InvalidKey)
+    }
+}
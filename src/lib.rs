@@ -1,9 +1,21 @@
+pub mod audit;
 pub mod configuration;
+pub mod connection_limit;
+pub mod history;
+pub mod jwks;
+pub mod lti;
+pub mod oidc;
+pub mod persistence;
+pub mod rate_limit;
 pub mod routes;
+pub mod session_state;
 pub mod startup;
 pub mod state;
 pub mod telemetry;
+pub mod tls;
 pub mod utils;
+pub mod validation;
+pub mod webhooks;
 
 pub use startup::*;
 
@@ -0,0 +1,358 @@
+//! LTI 1.3 tool provider
+
+use crate::{
+    configuration::LtiSettings,
+    error_chain_fmt,
+    jwks::{JwksCache, JwksError},
+    routes::message::ConnectionType,
+};
+use anyhow::Context;
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, EncodingKey, Header, Validation};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+/// Time given to the platform to post the id token of a started login
+const LOGIN_TTL: Duration = Duration::from_secs(10 * 60);
+/// Time given to the user to connect to the room after a launch
+const LAUNCH_TTL: Duration = Duration::from_secs(10 * 60);
+
+const ROLE_INSTRUCTOR: &str = "http://purl.imsglobal.org/vocab/lis/v2/membership#Instructor";
+const ROLE_ADMINISTRATOR: &str =
+    "http://purl.imsglobal.org/vocab/lis/v2/institution/person#Administrator";
+const ROLE_TEACHING_ASSISTANT: &str =
+    "http://purl.imsglobal.org/vocab/lis/v2/membership/Instructor#TeachingAssistant";
+const ROLE_LEARNER: &str = "http://purl.imsglobal.org/vocab/lis/v2/membership#Learner";
+const SCOPE_SCORE: &str = "https://purl.imsglobal.org/spec/lti-ags/scope/score";
+
+#[derive(thiserror::Error)]
+pub enum LtiError {
+    #[error("LTI is not configured.")]
+    NotConfigured,
+    #[error("Invalid LTI issuer: {0:?}.")]
+    InvalidIssuer(String),
+    #[error("Invalid LTI state.")]
+    InvalidState,
+    #[error("Invalid LTI id token.")]
+    InvalidToken(#[source] jsonwebtoken::errors::Error),
+    #[error("{0}")]
+    JwksError(#[from] JwksError),
+    #[error("Invalid LTI nonce.")]
+    InvalidNonce,
+    #[error("Invalid LTI deployment: {0:?}.")]
+    InvalidDeployment(String),
+    #[error("Unsupported LTI message: {0:?}.")]
+    UnsupportedMessage(String),
+    #[error("Missing LTI context.")]
+    MissingContext,
+    #[error("No supported LTI role.")]
+    InvalidRole,
+    #[error("Sending grades is not configured.")]
+    GradesNotConfigured,
+    #[error("Something went wrong.")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for LtiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Claims of the id token sent by the platform on a resource link launch
+#[derive(Debug, Deserialize)]
+pub struct LtiClaims {
+    pub sub: String,
+    pub nonce: String,
+    pub name: Option<String>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/message_type")]
+    pub message_type: String,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/version")]
+    pub version: String,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/deployment_id")]
+    pub deployment_id: String,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/roles")]
+    pub roles: Vec<String>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/context")]
+    pub context: Option<LtiContext>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti-ags/claim/endpoint")]
+    pub ags_endpoint: Option<AgsEndpoint>,
+}
+
+impl LtiClaims {
+    /// Instructors and administrators are teachers, learners are students
+    pub fn connection_type(&self) -> Option<ConnectionType> {
+        if self
+            .roles
+            .iter()
+            .any(|role| role == ROLE_TEACHING_ASSISTANT)
+        {
+            Some(ConnectionType::Assistant)
+        } else if self
+            .roles
+            .iter()
+            .any(|role| role == ROLE_INSTRUCTOR || role == ROLE_ADMINISTRATOR)
+        {
+            Some(ConnectionType::Teacher)
+        } else if self.roles.iter().any(|role| role == ROLE_LEARNER) {
+            Some(ConnectionType::Student)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LtiContext {
+    pub id: String,
+    pub title: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AgsEndpoint {
+    pub lineitem: Option<String>,
+    #[serde(default)]
+    pub scope: Vec<String>,
+}
+
+impl AgsEndpoint {
+    /// Line item where the tool is allowed to send scores
+    pub fn score_lineitem(self) -> Option<String> {
+        if self.scope.iter().any(|scope| scope == SCOPE_SCORE) {
+            self.lineitem
+        } else {
+            None
+        }
+    }
+}
+
+/// LTI information of a room
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct LtiRoom {
+    pub context_id: String,
+    /// AGS line item where the grades are sent
+    pub lineitem: Option<String>,
+}
+
+/// A validated launch, used by the web socket session to identify the user
+#[derive(Clone, Debug)]
+pub struct LtiLaunch {
+    pub user_id: String,
+    pub connection_type: ConnectionType,
+    pub room: String,
+}
+
+#[derive(Serialize)]
+struct ClientAssertion {
+    iss: String,
+    sub: String,
+    aud: String,
+    iat: i64,
+    exp: i64,
+    jti: String,
+}
+
+#[derive(Deserialize)]
+struct AccessToken {
+    access_token: String,
+}
+
+#[derive(Debug)]
+pub struct Lti {
+    settings: LtiSettings,
+    client: reqwest::Client,
+    jwks: JwksCache,
+    /// State -> nonce of the started logins and when they expire
+    nonces: Mutex<HashMap<String, (String, Instant)>>,
+    /// Launches not used yet to connect and when they expire
+    launches: Mutex<HashMap<Uuid, (LtiLaunch, Instant)>>,
+}
+
+impl Lti {
+    pub fn new(settings: LtiSettings) -> Self {
+        Self {
+            settings,
+            client: reqwest::Client::new(),
+            jwks: JwksCache::default(),
+            nonces: Mutex::new(HashMap::new()),
+            launches: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Starts an OIDC login, returns the url of the platform authorization endpoint
+    pub fn login(
+        &self,
+        issuer: &str,
+        login_hint: &str,
+        lti_message_hint: Option<&str>,
+    ) -> Result<String, LtiError> {
+        if issuer != self.settings.issuer {
+            return Err(LtiError::InvalidIssuer(issuer.to_string()));
+        }
+        let state = Uuid::new_v4().to_string();
+        let nonce = Uuid::new_v4().to_string();
+        let mut params = vec![
+            ("scope", "openid"),
+            ("response_type", "id_token"),
+            ("response_mode", "form_post"),
+            ("prompt", "none"),
+            ("client_id", &self.settings.client_id),
+            ("redirect_uri", &self.settings.launch_url),
+            ("login_hint", login_hint),
+            ("state", &state),
+            ("nonce", &nonce),
+        ];
+        if let Some(lti_message_hint) = lti_message_hint {
+            params.push(("lti_message_hint", lti_message_hint));
+        }
+        let url = reqwest::Url::parse_with_params(&self.settings.auth_url, params)
+            .context("Invalid LTI auth url.")?;
+        let now = Instant::now();
+        let mut nonces = self.nonces.lock().unwrap();
+        nonces.retain(|_, (_, expires_at)| *expires_at > now);
+        nonces.insert(state, (nonce, now + LOGIN_TTL));
+        Ok(url.into())
+    }
+
+    /// Validates the id token of a launch and its claims
+    #[tracing::instrument(skip(self, id_token))]
+    pub async fn validate(&self, state: &str, id_token: &str) -> Result<LtiClaims, LtiError> {
+        let nonce = self
+            .nonces
+            .lock()
+            .unwrap()
+            .remove(state)
+            .filter(|(_, expires_at)| *expires_at > Instant::now())
+            .ok_or(LtiError::InvalidState)?
+            .0;
+        let header = decode_header(id_token).map_err(LtiError::InvalidToken)?;
+        let key = self
+            .jwks
+            .decoding_key(&self.client, &self.settings.jwks_url, header.kid)
+            .await?;
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_issuer(&[&self.settings.issuer]);
+        validation.set_audience(&[&self.settings.client_id]);
+        let claims = decode::<LtiClaims>(id_token, &key, &validation)
+            .map_err(LtiError::InvalidToken)?
+            .claims;
+        if claims.nonce != nonce {
+            return Err(LtiError::InvalidNonce);
+        }
+        if let Some(deployment_id) = &self.settings.deployment_id {
+            if &claims.deployment_id != deployment_id {
+                return Err(LtiError::InvalidDeployment(claims.deployment_id));
+            }
+        }
+        if claims.message_type != "LtiResourceLinkRequest" {
+            return Err(LtiError::UnsupportedMessage(claims.message_type));
+        }
+        if claims.version != "1.3.0" {
+            return Err(LtiError::UnsupportedMessage(format!(
+                "version {}",
+                claims.version
+            )));
+        }
+        Ok(claims)
+    }
+
+    pub fn add_launch(&self, launch: LtiLaunch) -> Uuid {
+        let id = Uuid::new_v4();
+        let now = Instant::now();
+        let mut launches = self.launches.lock().unwrap();
+        launches.retain(|_, (_, expires_at)| *expires_at > now);
+        launches.insert(id, (launch, now + LAUNCH_TTL));
+        id
+    }
+
+    /// Launches can only be used once
+    pub fn take_launch(&self, id: &Uuid) -> Option<LtiLaunch> {
+        self.launches
+            .lock()
+            .unwrap()
+            .remove(id)
+            .filter(|(_, expires_at)| *expires_at > Instant::now())
+            .map(|(launch, _)| launch)
+    }
+
+    /// Sends the scores (between 0 and 1) of each user to an AGS line item
+    #[tracing::instrument(skip(self, scores))]
+    pub async fn send_scores(
+        &self,
+        lineitem: &str,
+        scores: Vec<(String, f64)>,
+    ) -> Result<(), LtiError> {
+        let access_token = self.access_token().await?;
+        let mut url = reqwest::Url::parse(lineitem).context("Invalid AGS line item.")?;
+        url.set_path(&format!("{}/scores", url.path().trim_end_matches('/')));
+        for (user_id, score) in scores {
+            self.client
+                .post(url.clone())
+                .bearer_auth(&access_token)
+                .header(
+                    reqwest::header::CONTENT_TYPE,
+                    "application/vnd.ims.lis.v1.score+json",
+                )
+                .json(&serde_json::json!({
+                    "userId": user_id,
+                    "scoreGiven": score,
+                    "scoreMaximum": 1.0,
+                    "activityProgress": "Completed",
+                    "gradingProgress": "FullyGraded",
+                    "timestamp": chrono::Utc::now().to_rfc3339(),
+                }))
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .context("Failed to send score.")?;
+        }
+        Ok(())
+    }
+
+    /// Requests an access token for the AGS score scope with the client credentials grant
+    async fn access_token(&self) -> Result<String, LtiError> {
+        let (token_url, private_key) = match (&self.settings.token_url, &self.settings.private_key)
+        {
+            (Some(token_url), Some(private_key)) => (token_url, private_key),
+            _ => return Err(LtiError::GradesNotConfigured),
+        };
+        let key = EncodingKey::from_rsa_pem(private_key.expose_secret().as_bytes())
+            .context("Invalid LTI private key.")?;
+        let now = chrono::Utc::now().timestamp();
+        let assertion = ClientAssertion {
+            iss: self.settings.client_id.clone(),
+            sub: self.settings.client_id.clone(),
+            aud: token_url.clone(),
+            iat: now,
+            exp: now + 300,
+            jti: Uuid::new_v4().to_string(),
+        };
+        let assertion = encode(&Header::new(Algorithm::RS256), &assertion, &key)
+            .context("Failed to sign client assertion.")?;
+        let token = self
+            .client
+            .post(token_url)
+            .form(&[
+                ("grant_type", "client_credentials"),
+                (
+                    "client_assertion_type",
+                    "urn:ietf:params:oauth:client-assertion-type:jwt-bearer",
+                ),
+                ("client_assertion", &assertion),
+                ("scope", SCOPE_SCORE),
+            ])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context("Failed to request access token.")?
+            .json::<AccessToken>()
+            .await
+            .context("Failed to parse access token.")?;
+        Ok(token.access_token)
+    }
+}
@@ -0,0 +1,219 @@
+//! OpenID Connect login for teachers (authorization code flow with PKCE)
+
+use crate::{
+    configuration::OidcSettings,
+    error_chain_fmt,
+    jwks::{JwksCache, JwksError},
+};
+use anyhow::Context;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode, decode_header, Algorithm, Validation};
+use rand::RngCore;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+use ts_rs::TS;
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum OidcError {
+    #[error("Login is not configured.")]
+    NotConfigured,
+    #[error("Invalid login state.")]
+    InvalidState,
+    #[error("Invalid id token.")]
+    InvalidToken(#[source] jsonwebtoken::errors::Error),
+    #[error("Invalid login nonce.")]
+    InvalidNonce,
+    #[error("{0}")]
+    JwksError(#[from] JwksError),
+    #[error("Something went wrong.")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for OidcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Teacher logged in with the OIDC provider
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct Teacher {
+    /// `sub` claim of the provider
+    pub id: String,
+    pub name: Option<String>,
+    pub email: Option<String>,
+}
+
+/// Values kept in the session between the login and the callback
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LoginAttempt {
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    /// Where to redirect after the login
+    pub return_to: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    name: Option<String>,
+    email: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Oidc {
+    settings: OidcSettings,
+    client: reqwest::Client,
+    metadata: Mutex<Option<ProviderMetadata>>,
+    jwks: JwksCache,
+}
+
+impl Oidc {
+    pub fn new(settings: OidcSettings) -> Self {
+        Self {
+            settings,
+            client: reqwest::Client::new(),
+            metadata: Mutex::new(None),
+            jwks: JwksCache::default(),
+        }
+    }
+
+    /// Provider metadata, discovered on the first use
+    async fn metadata(&self) -> Result<ProviderMetadata, OidcError> {
+        if let Some(metadata) = self.metadata.lock().unwrap().as_ref() {
+            return Ok(metadata.clone());
+        }
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.settings.issuer_url.trim_end_matches('/')
+        );
+        let metadata = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context("Failed to fetch the OIDC provider metadata.")?
+            .json::<ProviderMetadata>()
+            .await
+            .context("Failed to parse the OIDC provider metadata.")?;
+        *self.metadata.lock().unwrap() = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    /// Returns the url of the provider authorization endpoint and the values to keep until the callback
+    #[tracing::instrument(skip(self))]
+    pub async fn start_login(
+        &self,
+        return_to: String,
+    ) -> Result<(String, LoginAttempt), OidcError> {
+        let metadata = self.metadata().await?;
+        let attempt = LoginAttempt {
+            state: Uuid::new_v4().to_string(),
+            nonce: Uuid::new_v4().to_string(),
+            code_verifier: random_token(),
+            return_to,
+        };
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(&attempt.code_verifier));
+        let scope = std::iter::once("openid")
+            .chain(self.settings.scopes.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let url = reqwest::Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            [
+                ("response_type", "code"),
+                ("client_id", &self.settings.client_id),
+                ("redirect_uri", &self.settings.redirect_url),
+                ("scope", &scope),
+                ("state", &attempt.state),
+                ("nonce", &attempt.nonce),
+                ("code_challenge", &code_challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .context("Invalid OIDC authorization endpoint.")?;
+        Ok((url.into(), attempt))
+    }
+
+    /// Exchanges the authorization code and validates the received id token
+    #[tracing::instrument(skip(self, attempt, code))]
+    pub async fn finish_login(
+        &self,
+        attempt: LoginAttempt,
+        state: &str,
+        code: &str,
+    ) -> Result<Teacher, OidcError> {
+        if attempt.state != state {
+            return Err(OidcError::InvalidState);
+        }
+        let metadata = self.metadata().await?;
+        let mut params = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.settings.redirect_url),
+            ("client_id", &self.settings.client_id),
+            ("code_verifier", &attempt.code_verifier),
+        ];
+        if let Some(client_secret) = &self.settings.client_secret {
+            params.push(("client_secret", client_secret.expose_secret()));
+        }
+        let token = self
+            .client
+            .post(&metadata.token_endpoint)
+            .form(&params)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context("Failed to exchange the authorization code.")?
+            .json::<TokenResponse>()
+            .await
+            .context("Failed to parse the token response.")?;
+
+        let header = decode_header(&token.id_token).map_err(OidcError::InvalidToken)?;
+        let key = self
+            .jwks
+            .decoding_key(&self.client, &metadata.jwks_uri, header.kid)
+            .await?;
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&self.settings.client_id]);
+        let claims = decode::<IdTokenClaims>(&token.id_token, &key, &validation)
+            .map_err(OidcError::InvalidToken)?
+            .claims;
+        if claims.nonce.as_ref() != Some(&attempt.nonce) {
+            return Err(OidcError::InvalidNonce);
+        }
+        Ok(Teacher {
+            id: claims.sub,
+            name: claims.name,
+            email: claims.email,
+        })
+    }
+}
+
+/// 32 random bytes encoded as url safe base64
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
@@ -0,0 +1,64 @@
+use crate::{
+    configuration::PersistenceSettings,
+    error_chain_fmt,
+    state::{RoomSnapshot, RoomTemplate},
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(thiserror::Error)]
+pub enum PersistenceError {
+    #[error("Failed to access {0:?}.")]
+    IoError(PathBuf, #[source] std::io::Error),
+    #[error("Invalid rooms file {0:?}.")]
+    InvalidData(PathBuf, #[source] serde_json::Error),
+}
+
+impl std::fmt::Debug for PersistenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Everything saved across restarts
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct StoredState {
+    pub rooms: Vec<RoomSnapshot>,
+    pub templates: Vec<RoomTemplate>,
+}
+
+/// Saves the rooms in a JSON file
+#[derive(Debug)]
+pub struct RoomStore {
+    path: PathBuf,
+}
+
+impl RoomStore {
+    pub fn new(settings: &PersistenceSettings) -> Self {
+        Self {
+            path: PathBuf::from(&settings.path),
+        }
+    }
+
+    /// `None` if nothing was saved yet
+    pub fn load(&self) -> Result<Option<StoredState>, PersistenceError> {
+        let data = match std::fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(PersistenceError::IoError(self.path.clone(), e)),
+        };
+        serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|e| PersistenceError::InvalidData(self.path.clone(), e))
+    }
+
+    /// Writes to a temporary file first, so a failed save doesn't lose the previous one
+    pub fn save(&self, state: &StoredState) -> Result<(), PersistenceError> {
+        let data = serde_json::to_vec(state)
+            .map_err(|e| PersistenceError::InvalidData(self.path.clone(), e))?;
+        let tmp_path = self.path.with_extension("tmp");
+        std::fs::write(&tmp_path, data)
+            .and_then(|_| std::fs::rename(&tmp_path, &self.path))
+            .map_err(|e| PersistenceError::IoError(self.path.clone(), e))
+    }
+}
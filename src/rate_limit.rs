@@ -0,0 +1,77 @@
+use crate::configuration::RateLimitSettings;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Buckets are only removed once there are more than this number of them
+const MAX_IDLE_BUCKETS: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    per_second: f64,
+    burst: f64,
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(settings: &RateLimitSettings) -> Self {
+        Self {
+            per_second: settings.per_second,
+            burst: settings.burst as f64,
+            tokens: settings.burst as f64,
+            updated_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.burst);
+        self.updated_at = now;
+    }
+
+    /// Takes a token if there is any left
+    pub fn try_take(&mut self) -> bool {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// A full bucket behaves the same as a new one
+    fn is_full(&mut self) -> bool {
+        self.refill();
+        self.tokens >= self.burst
+    }
+}
+
+/// Token buckets shared by the connections of each IP
+#[derive(Debug)]
+pub struct IpRateLimiter {
+    settings: RateLimitSettings,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl IpRateLimiter {
+    pub fn new(settings: RateLimitSettings) -> Self {
+        Self {
+            settings,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn try_take(&self, ip: &str) -> bool {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > MAX_IDLE_BUCKETS {
+            buckets.retain(|_, bucket| !bucket.is_full());
+        }
+        buckets
+            .entry(ip.to_string())
+            .or_insert_with(|| TokenBucket::new(&self.settings))
+            .try_take()
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{
+    audit::{AuditActor, RoomCapture},
+    error_chain_fmt,
+    routes::message::{ClientMessage, ConnectionType},
+    state::{AppState, RoomSnapshot, RoomState},
+    utils::e400,
+    webhooks::WebhookEvent,
+};
+use actix_web::{dev::Payload, http::header, web, FromRequest, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    future::{ready, Ready},
+};
+use uuid::Uuid;
+
+#[derive(thiserror::Error)]
+pub enum AdminError {
+    #[error("Room {0:?} doesn't exists.")]
+    NoExistingRoom(String),
+    #[error("Session {0} doesn't exists.")]
+    NoExistingSession(Uuid),
+    #[error("The announcement can't be empty.")]
+    EmptyAnnouncement,
+}
+
+impl std::fmt::Debug for AdminError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Request authorized with the admin token.
+/// Rejects the request if the admin API is not configured or the token is invalid.
+#[derive(Debug)]
+pub struct AdminSession;
+
+impl FromRequest for AdminSession {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<AdminSession, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let admin_token = match req
+            .app_data::<web::Data<AppState>>()
+            .and_then(|state| state.admin_token.clone())
+        {
+            Some(admin_token) => admin_token,
+            None => return ready(Err(actix_web::error::ErrorNotFound("Admin API disabled."))),
+        };
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        ready(match token {
+            Some(token) if constant_time_eq(token, admin_token.expose_secret()) => Ok(AdminSession),
+            _ => Err(actix_web::error::ErrorUnauthorized("Invalid admin token.")),
+        })
+    }
+}
+
+/// Compares without leaking the length of the matching prefix
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AdminRoomsInfo {
+    /// Every room, sorted by creation time
+    pub rooms: Vec<AdminRoomSummary>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AdminRoomSummary {
+    pub name: String,
+    pub owner: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub students: usize,
+    /// Teachers, assistants and projectors
+    pub teachers: usize,
+    pub questions: usize,
+    /// Approximate bytes used by the room
+    pub memory: usize,
+}
+
+impl From<&RoomState> for AdminRoomSummary {
+    fn from(room_state: &RoomState) -> Self {
+        Self {
+            name: room_state.name.clone(),
+            owner: room_state.owner.clone(),
+            created_at: room_state.created_at,
+            students: room_state.student_connections.len(),
+            teachers: room_state.teacher_connections.len(),
+            questions: room_state.questions.len(),
+            memory: room_state.memory_footprint(),
+        }
+    }
+}
+
+impl AdminRoomsInfo {
+    fn from_rooms(rooms: &HashMap<String, RoomState>) -> Self {
+        let rooms = rooms
+            .values()
+            .map(AdminRoomSummary::from)
+            .sorted_by(|a, b| (a.created_at, &a.name).cmp(&(b.created_at, &b.name)))
+            .collect();
+        Self { rooms }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AdminRoomDetail {
+    pub summary: AdminRoomSummary,
+    /// Connected sessions, sorted by id
+    pub sessions: Vec<AdminSessionInfo>,
+    pub state: RoomSnapshot,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AdminSessionInfo {
+    pub id: Uuid,
+    pub connection_type: ConnectionType,
+}
+
+impl From<&RoomState> for AdminRoomDetail {
+    fn from(room_state: &RoomState) -> Self {
+        let sessions =
+            room_state
+                .student_connections
+                .keys()
+                .map(|&id| AdminSessionInfo {
+                    id,
+                    connection_type: ConnectionType::Student,
+                })
+                .chain(room_state.teacher_connections.iter().map(|(&id, teacher)| {
+                    AdminSessionInfo {
+                        id,
+                        connection_type: teacher.connection_type,
+                    }
+                }))
+                .sorted_by_key(|session| session.id)
+                .collect();
+        Self {
+            summary: room_state.into(),
+            sessions,
+            state: room_state.snapshot(),
+        }
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn admin_rooms(
+    state: web::Data<AppState>,
+    _admin: AdminSession,
+) -> web::Json<AdminRoomsInfo> {
+    let rooms = state.rooms.lock().unwrap();
+    web::Json(AdminRoomsInfo::from_rooms(&rooms))
+}
+
+#[tracing::instrument(skip(state, _admin))]
+pub async fn admin_room(
+    room: web::Path<String>,
+    state: web::Data<AppState>,
+    _admin: AdminSession,
+) -> Result<web::Json<AdminRoomDetail>, actix_web::Error> {
+    let room_name = room.into_inner();
+    let rooms = state.rooms.lock().unwrap();
+    match rooms.get(&room_name) {
+        Some(room_state) => Ok(web::Json(room_state.into())),
+        None => Err(e400(AdminError::NoExistingRoom(room_name))),
+    }
+}
+
+/// Deletes a room disconnecting its clients, returns the remaining rooms
+#[tracing::instrument(skip(state, _admin))]
+pub async fn admin_close_room(
+    room: web::Path<String>,
+    state: web::Data<AppState>,
+    _admin: AdminSession,
+) -> Result<web::Json<AdminRoomsInfo>, actix_web::Error> {
+    let room_name = room.into_inner();
+    let mut rooms = state.rooms.lock().unwrap();
+    let room_state = rooms
+        .remove(&room_name)
+        .ok_or_else(|| e400(AdminError::NoExistingRoom(room_name.clone())))?;
+    tracing::warn!(room = room_name, "Room closed by an administrator.");
+    state.audit.record(
+        &room_name,
+        &AuditActor::admin(),
+        "CloseRoom",
+        &RoomCapture::new(&room_state),
+        None,
+    );
+    state.audit.remove_room(&room_name);
+    room_state.broadcast(ClientMessage::Disconnected(
+        "The room was closed by an administrator.".to_string(),
+    ));
+    state.webhooks.dispatch(
+        &room_state.webhooks,
+        WebhookEvent::RoomDeleted { room: room_name },
+    );
+    Ok(web::Json(AdminRoomsInfo::from_rooms(&rooms)))
+}
+
+#[tracing::instrument(skip(state, _admin))]
+pub async fn admin_disconnect_session(
+    session: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    _admin: AdminSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let id = session.into_inner();
+    let sessions = state.sessions.lock().unwrap();
+    let connection = sessions
+        .get(&id)
+        .ok_or_else(|| e400(AdminError::NoExistingSession(id)))?;
+    tracing::warn!(session = %id, "Session disconnected by an administrator.");
+    connection.do_send(ClientMessage::Disconnected(
+        "Disconnected by an administrator.".to_string(),
+    ));
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Announcement {
+    message: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AnnouncementInfo {
+    /// Sessions the announcement was sent to
+    pub sessions: usize,
+}
+
+/// Sends a maintenance announcement to every session
+#[tracing::instrument(skip(state, _admin))]
+pub async fn admin_announcement(
+    form: web::Json<Announcement>,
+    state: web::Data<AppState>,
+    _admin: AdminSession,
+) -> Result<web::Json<AnnouncementInfo>, actix_web::Error> {
+    let message = form.into_inner().message.trim().to_string();
+    if message.is_empty() {
+        return Err(e400(AdminError::EmptyAnnouncement));
+    }
+    let sessions = state.broadcast_sessions(ClientMessage::Announcement(message));
+    Ok(web::Json(AnnouncementInfo { sessions }))
+}
@@ -0,0 +1,101 @@
+use crate::{
+    jwks::JwksError,
+    oidc::{Oidc, OidcError, Teacher},
+    session_state::{TeacherSession, TypedSession},
+    state::AppState,
+    utils::{e400, e500},
+};
+use actix_web::{http::header::LOCATION, web, HttpResponse};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct LoginQuery {
+    /// Path to go back after the login
+    return_to: Option<String>,
+}
+
+/// Redirects the teacher to the OIDC provider
+#[tracing::instrument(skip(session, state))]
+pub async fn login(
+    query: web::Query<LoginQuery>,
+    session: TypedSession,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // Only allow local paths to avoid open redirects
+    let return_to = query
+        .into_inner()
+        .return_to
+        .filter(|path| path.starts_with('/') && !path.starts_with("//"))
+        .unwrap_or_else(|| "/".to_string());
+    let (url, attempt) = get_oidc(&state)?
+        .start_login(return_to)
+        .await
+        .map_err(oidc_error)?;
+    session.insert_login_attempt(&attempt).map_err(e500)?;
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, url))
+        .finish())
+}
+
+#[derive(Deserialize)]
+pub struct CallbackQuery {
+    code: String,
+    state: String,
+}
+
+/// Finishes the login started on `login` and keeps the teacher in the session
+#[tracing::instrument(skip_all)]
+pub async fn login_callback(
+    query: web::Query<CallbackQuery>,
+    session: TypedSession,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let CallbackQuery {
+        code,
+        state: login_state,
+    } = query.into_inner();
+    let oidc = get_oidc(&state)?;
+    let attempt = session
+        .take_login_attempt()
+        .map_err(e500)?
+        .ok_or_else(|| oidc_error(OidcError::InvalidState))?;
+    let return_to = attempt.return_to.clone();
+    let teacher = oidc
+        .finish_login(attempt, &login_state, &code)
+        .await
+        .map_err(oidc_error)?;
+    tracing::info!(teacher.id = %teacher.id, "Teacher logged in.");
+    session.renew();
+    session.insert_teacher(&teacher).map_err(e500)?;
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, return_to))
+        .finish())
+}
+
+pub async fn logout(session: TypedSession) -> HttpResponse {
+    session.log_out();
+    HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/"))
+        .finish()
+}
+
+/// Returns the logged in teacher, `null` if the login is not configured
+pub async fn get_teacher(teacher: TeacherSession) -> web::Json<Option<Teacher>> {
+    web::Json(teacher.0)
+}
+
+fn get_oidc(state: &AppState) -> Result<&Oidc, actix_web::Error> {
+    state
+        .oidc
+        .as_ref()
+        .ok_or_else(|| oidc_error(OidcError::NotConfigured))
+}
+
+fn oidc_error(e: OidcError) -> actix_web::Error {
+    match e {
+        OidcError::UnexpectedError(_) | OidcError::JwksError(JwksError::UnexpectedError(_)) => {
+            e500(e).into()
+        }
+        e => e400(e),
+    }
+}
@@ -1,11 +1,23 @@
 use crate::{
+    audit::{AuditActor, AuditFilter, AuditTrail, RoomCapture},
     error_chain_fmt,
-    state::{AppState, RoomState},
-    utils::e400,
+    history::RoomHistory,
+    oidc::Teacher,
+    routes::message::{
+        ClientMessage, CupAlertCondition, CupAlertRule, CupResetOptions, CupSignal, CupTimeline,
+        IdentityMode, Question, ResultsMode,
+    },
+    session_state::TeacherSession,
+    state::{AppState, RoomSnapshot, RoomState, RoomTemplate, DEFAULT_TIMELINE_BUCKET_SECS},
+    utils::{e400, e403},
+    validation::ValidationError,
+    webhooks::WebhookEvent,
 };
-use actix_web::{web, HttpResponse};
+use actix_web::{http::header, web, HttpResponse};
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{hash_map::Entry, HashMap};
 use ts_rs::TS;
 
 #[derive(thiserror::Error)]
@@ -14,6 +26,32 @@ pub enum CupsError {
     RoomAlreadyExists(String),
     #[error("Room {0:?} doesn't exists.")]
     NoExistingRoom(String),
+    #[error("Not allowed to manage room {0:?}.")]
+    NotAllowed(String),
+    #[error("Invalid webhook url: {0:?}.")]
+    InvalidWebhookUrl(String),
+    #[error("Room webhooks are disabled, the server has no signing secret.")]
+    WebhooksDisabled,
+    #[error("Webhook {0:?} doesn't exists.")]
+    NoExistingWebhook(String),
+    #[error("Invalid roster: {0}.")]
+    InvalidRoster(#[source] csv::Error),
+    #[error("Invalid cup signals: {0}.")]
+    InvalidSignals(String),
+    #[error("Invalid alert rules: {0}.")]
+    InvalidAlertRules(String),
+    #[error("Invalid cup reset: {0}.")]
+    InvalidCupReset(String),
+    #[error("Co-owner {0:?} doesn't exists.")]
+    NoExistingCoOwner(String),
+    #[error("Template {0:?} doesn't exists.")]
+    NoExistingTemplate(String),
+    #[error("Not allowed to use template {0:?}.")]
+    TemplateNotAllowed(String),
+    #[error("Invalid snapshot: {0}.")]
+    InvalidSnapshot(String),
+    #[error("{0}")]
+    InvalidInput(#[from] ValidationError),
     #[error("Something went wrong.")]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -27,58 +65,1057 @@ impl std::fmt::Debug for CupsError {
 #[derive(Debug, Default, Deserialize, Serialize, PartialEq, Eq, TS)]
 #[ts(export, export_to = "frontend/bindings/")]
 pub struct CupsInfo {
-    pub rooms: HashSet<String>,
+    /// Rooms of the teacher, sorted by creation time
+    pub rooms: Vec<RoomSummary>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct RoomSummary {
+    pub name: String,
+    pub owner: Option<String>,
+    pub co_owners: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub identity_mode: IdentityMode,
+    pub cup_reset: CupResetOptions,
+    pub auto_share_results: Option<ResultsMode>,
+    /// Connected students
+    pub students: usize,
+    pub questions: usize,
+}
+
+impl From<&RoomState> for RoomSummary {
+    fn from(room_state: &RoomState) -> Self {
+        Self {
+            name: room_state.name.clone(),
+            owner: room_state.owner.clone(),
+            co_owners: room_state.co_owners.iter().cloned().sorted().collect(),
+            created_at: room_state.created_at,
+            identity_mode: room_state.identity_mode,
+            cup_reset: room_state.cup_reset,
+            auto_share_results: room_state.auto_share_results,
+            students: room_state.student_connections.len(),
+            questions: room_state.questions.len(),
+        }
+    }
+}
+
+impl CupsInfo {
+    /// Information of the rooms that `teacher` can manage
+    fn from_rooms(rooms: &HashMap<String, RoomState>, teacher: Option<&Teacher>) -> Self {
+        let rooms = rooms
+            .values()
+            .filter(|room_state| room_state.can_manage(teacher))
+            .map(RoomSummary::from)
+            .sorted_by(|a, b| (a.created_at, &a.name).cmp(&(b.created_at, &b.name)))
+            .collect();
+        Self { rooms }
+    }
 }
 
 #[tracing::instrument(skip_all)]
-pub async fn get_cups_info(state: web::Data<AppState>) -> web::Json<CupsInfo> {
-    let rooms = state.rooms.lock().unwrap().keys().cloned().collect();
-    let cups_info = CupsInfo { rooms };
-    web::Json(cups_info)
+pub async fn get_cups_info(
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> web::Json<CupsInfo> {
+    let rooms = state.rooms.lock().unwrap();
+    web::Json(CupsInfo::from_rooms(&rooms, teacher.0.as_ref()))
 }
 
 #[derive(Debug, Deserialize, TS)]
 #[ts(export, export_to = "frontend/bindings/")]
 pub struct CreateRoom {
     new_room: String,
+    /// Signals students can choose, defaults to green, yellow and red cups
+    #[serde(default)]
+    signals: Option<Vec<CupSignal>>,
+    #[serde(default)]
+    cup_reset: CupResetOptions,
+    #[serde(default)]
+    alert_rules: Vec<CupAlertRule>,
+    /// Results sent to the students when a question publication ends
+    #[serde(default)]
+    auto_share_results: Option<ResultsMode>,
+    /// Template to take the questions and settings from, the settings given here override
+    /// the template ones
+    #[serde(default)]
+    template: Option<String>,
+}
+
+/// Most signals a room can have
+const MAX_SIGNALS: usize = 10;
+
+fn validate_signals(signals: &[CupSignal]) -> Result<(), CupsError> {
+    if signals.is_empty() {
+        return Err(CupsError::InvalidSignals("no signals".to_string()));
+    }
+    if signals.len() > MAX_SIGNALS {
+        return Err(CupsError::InvalidSignals(format!(
+            "more than {MAX_SIGNALS} signals"
+        )));
+    }
+    if let Some(signal) = signals
+        .iter()
+        .find(|signal| signal.id.trim().is_empty() || signal.label.trim().is_empty())
+    {
+        return Err(CupsError::InvalidSignals(format!(
+            "empty id or label on {:?}",
+            signal.id
+        )));
+    }
+    if let Some(signal) = signals.iter().find(|signal| !is_css_color(&signal.color)) {
+        return Err(CupsError::InvalidSignals(format!(
+            "invalid color {:?}",
+            signal.color
+        )));
+    }
+    if let Some(id) = signals.iter().map(|signal| &signal.id).duplicates().next() {
+        return Err(CupsError::InvalidSignals(format!("duplicated id {id:?}")));
+    }
+    Ok(())
+}
+
+/// Hex colors (`#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`) or color names
+fn is_css_color(color: &str) -> bool {
+    match color.strip_prefix('#') {
+        Some(hex) => {
+            [3, 4, 6, 8].contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit())
+        }
+        None => {
+            !color.is_empty() && color.len() <= 32 && color.chars().all(|c| c.is_ascii_alphabetic())
+        }
+    }
+}
+
+/// Longest time a cup can take to decay, a day
+const MAX_DECAY_MINUTES: u64 = 24 * 60;
+
+fn validate_cup_reset(cup_reset: &CupResetOptions) -> Result<(), CupsError> {
+    match cup_reset.decay_minutes {
+        Some(minutes) if minutes == 0 || minutes > MAX_DECAY_MINUTES => {
+            Err(CupsError::InvalidCupReset(format!(
+                "decay_minutes should be between 1 and {MAX_DECAY_MINUTES}"
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Longest hold and cooldown of the alert rules, a day
+const MAX_ALERT_SECS: u64 = 24 * 60 * 60;
+
+fn validate_alert_rules(rules: &[CupAlertRule], signals: &[CupSignal]) -> Result<(), CupsError> {
+    for rule in rules {
+        if rule.id.trim().is_empty() {
+            return Err(CupsError::InvalidAlertRules("empty id".to_string()));
+        }
+        let ratio = match &rule.condition {
+            CupAlertCondition::SignalAtLeast { signal, ratio } => {
+                if !signals.iter().any(|s| &s.id == signal) {
+                    return Err(CupsError::InvalidAlertRules(format!(
+                        "unknown signal {signal:?}"
+                    )));
+                }
+                ratio
+            }
+            CupAlertCondition::ChosenBelow { ratio } => ratio,
+        };
+        if !(0.0..=1.0).contains(ratio) {
+            return Err(CupsError::InvalidAlertRules(format!(
+                "ratio of {:?} is not between 0 and 1",
+                rule.id
+            )));
+        }
+        let too_long = |secs: u64| secs > MAX_ALERT_SECS;
+        if too_long(rule.hold_secs) || rule.cooldown_secs.is_some_and(too_long) {
+            return Err(CupsError::InvalidAlertRules(format!(
+                "hold or cooldown of {:?} is longer than {MAX_ALERT_SECS} seconds",
+                rule.id
+            )));
+        }
+    }
+    if let Some(id) = rules.iter().map(|rule| &rule.id).duplicates().next() {
+        return Err(CupsError::InvalidAlertRules(format!(
+            "duplicated id {id:?}"
+        )));
+    }
+    Ok(())
 }
 
 #[tracing::instrument(skip(state))]
 pub async fn create_room(
     form: web::Json<CreateRoom>,
     state: web::Data<AppState>,
+    teacher: TeacherSession,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let room_name = form.into_inner().new_room;
+    let CreateRoom {
+        new_room: room_name,
+        signals,
+        cup_reset,
+        alert_rules,
+        auto_share_results,
+        template,
+    } = form.into_inner();
+    state
+        .validator
+        .name(&room_name)
+        .map_err(|e| e400(CupsError::from(e)))?;
+    if let Some(signals) = &signals {
+        validate_signals(signals).map_err(e400)?;
+    }
+    validate_cup_reset(&cup_reset).map_err(e400)?;
+    let template = match template {
+        Some(name) => Some(get_template(&state, &name, teacher.0.as_ref())?),
+        None => None,
+    };
+    let default_signals = CupSignal::defaults();
+    let room_signals = signals
+        .as_ref()
+        .or(template.as_ref().map(|template| &template.signals))
+        .unwrap_or(&default_signals);
+    let room_alert_rules = match &template {
+        Some(template) if alert_rules.is_empty() => &template.alert_rules,
+        _ => &alert_rules,
+    };
+    validate_alert_rules(room_alert_rules, room_signals).map_err(e400)?;
+    let mut rooms = state.rooms.lock().unwrap();
+    match rooms.entry(room_name.clone()) {
+        Entry::Occupied(_) => Err(e400(CupsError::RoomAlreadyExists(room_name))),
+        Entry::Vacant(entry) => {
+            let actor = AuditActor::api(teacher.0.as_ref());
+            let owner = teacher.0.map(|teacher| teacher.id);
+            let room_state = entry.insert(RoomState::new(room_name.clone(), owner));
+            if let Some(template) = &template {
+                room_state.apply_template(template);
+            }
+            if let Some(signals) = signals {
+                room_state.signals = signals;
+            }
+            if cup_reset != CupResetOptions::default() {
+                room_state.cup_reset = cup_reset;
+            }
+            if !alert_rules.is_empty() {
+                room_state.set_alert_rules(alert_rules);
+            }
+            if auto_share_results.is_some() {
+                room_state.auto_share_results = auto_share_results;
+            }
+            state.audit.record(
+                &room_name,
+                &actor,
+                "CreateRoom",
+                &RoomCapture::empty(),
+                Some(room_state),
+            );
+            state
+                .webhooks
+                .dispatch(&[], WebhookEvent::RoomCreated { room: room_name });
+            Ok(HttpResponse::Ok().finish())
+        }
+    }
+}
+
+/// Template that `teacher` can use
+fn get_template(
+    state: &AppState,
+    name: &str,
+    teacher: Option<&Teacher>,
+) -> Result<RoomTemplate, actix_web::Error> {
+    match state.templates.lock().unwrap().get(name) {
+        Some(template) if !template.is_owner(teacher) => {
+            Err(e403(CupsError::TemplateNotAllowed(name.to_string())))
+        }
+        Some(template) => Ok(template.clone()),
+        None => Err(e400(CupsError::NoExistingTemplate(name.to_string()))),
+    }
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct CloneRoom {
+    new_room: String,
+    /// Leaves out the students answers to the questions
+    #[serde(default)]
+    without_answers: bool,
+}
+
+/// Creates a room with the questions and configuration of `room`
+#[tracing::instrument(skip(state))]
+pub async fn clone_room(
+    room: web::Path<String>,
+    form: web::Json<CloneRoom>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let room = room.into_inner();
+    let CloneRoom {
+        new_room,
+        without_answers,
+    } = form.into_inner();
+    state
+        .validator
+        .name(&new_room)
+        .map_err(|e| e400(CupsError::from(e)))?;
+    let actor = AuditActor::api(teacher.0.as_ref());
+    let mut rooms = state.rooms.lock().unwrap();
+    let room_state = match rooms.get(&room) {
+        Some(room_state) if !room_state.can_manage(teacher.0.as_ref()) => {
+            return Err(e403(CupsError::NotAllowed(room)));
+        }
+        Some(room_state) => room_state.clone_as(
+            new_room.clone(),
+            teacher.0.map(|teacher| teacher.id),
+            !without_answers,
+        ),
+        None => return Err(e400(CupsError::NoExistingRoom(room))),
+    };
+    match rooms.entry(new_room.clone()) {
+        Entry::Occupied(_) => Err(e400(CupsError::RoomAlreadyExists(new_room))),
+        Entry::Vacant(entry) => {
+            let room_state = entry.insert(room_state);
+            state.audit.record(
+                &new_room,
+                &actor,
+                "CloneRoom",
+                &RoomCapture::empty(),
+                Some(room_state),
+            );
+            state
+                .webhooks
+                .dispatch(&[], WebhookEvent::RoomCreated { room: new_room });
+            Ok(HttpResponse::Ok().finish())
+        }
+    }
+}
+
+/// Questions, answers and configuration of the room as a JSON file
+#[tracing::instrument(skip(state))]
+pub async fn get_room_snapshot(
+    room: web::Path<String>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let room = room.into_inner();
+    let snapshot = match state.rooms.lock().unwrap().get(&room) {
+        Some(room_state) if !room_state.can_manage(teacher.0.as_ref()) => {
+            return Err(e403(CupsError::NotAllowed(room)));
+        }
+        Some(room_state) => room_state.snapshot(),
+        None => return Err(e400(CupsError::NoExistingRoom(room))),
+    };
+    Ok(HttpResponse::Ok()
+        .insert_header(header::ContentDisposition::attachment(format!(
+            "{room}.json"
+        )))
+        .json(snapshot))
+}
+
+/// What to do when restoring a snapshot of an existing room
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub enum RestoreConflict {
+    /// Fails without touching the existing room
+    #[default]
+    Reject,
+    /// Replaces the existing room, disconnecting its clients. Only for the room owner.
+    Replace,
+    /// Restores the room with the first free name of the form `{name}-{n}`
+    Rename,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RestoreRoom {
+    snapshot: RoomSnapshot,
+    #[serde(default)]
+    on_conflict: RestoreConflict,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct RestoredRoom {
+    /// Name of the restored room, different from the snapshot one when renamed
+    pub name: String,
+}
+
+/// Recreates a room from its snapshot, the teacher restoring it becomes the owner
+#[tracing::instrument(skip(state, form))]
+pub async fn restore_room(
+    form: web::Json<RestoreRoom>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<web::Json<RestoredRoom>, actix_web::Error> {
+    let RestoreRoom {
+        mut snapshot,
+        on_conflict,
+    } = form.into_inner();
+    validate_snapshot(&state, &snapshot).map_err(e400)?;
+    let teacher = teacher.0.as_ref();
     let mut rooms = state.rooms.lock().unwrap();
-    if rooms
-        .insert(room_name.clone(), RoomState::new(room_name.clone()))
-        .is_none()
+    let mut replaced = None;
+    if let Some(existing) = rooms.get(&snapshot.name) {
+        match on_conflict {
+            RestoreConflict::Reject => {
+                return Err(e400(CupsError::RoomAlreadyExists(snapshot.name)));
+            }
+            RestoreConflict::Replace if !existing.is_owner(teacher) => {
+                return Err(e403(CupsError::NotAllowed(snapshot.name)));
+            }
+            RestoreConflict::Replace => replaced = rooms.remove(&snapshot.name),
+            RestoreConflict::Rename => {
+                let name = (1..)
+                    .map(|n| format!("{}-{n}", snapshot.name))
+                    .find(|name| !rooms.contains_key(name))
+                    .unwrap_or_default();
+                state
+                    .validator
+                    .name(&name)
+                    .map_err(|e| e400(CupsError::from(e)))?;
+                snapshot.name = name;
+            }
+        }
+    }
+    // Snapshots can't link a room to an LTI context, a replaced room keeps its own
+    let replaced_lti = replaced
+        .as_ref()
+        .and_then(|room_state| room_state.lti.as_ref());
+    if snapshot.lti.as_ref() != replaced_lti {
+        snapshot.lti = None;
+    }
+    snapshot.owner = teacher.map(|teacher| teacher.id.clone());
+    if let Some(owner) = &snapshot.owner {
+        snapshot.co_owners.remove(owner);
+    }
+    let name = snapshot.name.clone();
+    let before = match &replaced {
+        Some(room_state) => {
+            room_state.broadcast(ClientMessage::Disconnected(
+                "The room was replaced by a restored snapshot.".to_string(),
+            ));
+            RoomCapture::new(room_state)
+        }
+        None => RoomCapture::empty(),
+    };
+    let room_state = rooms.entry(name.clone()).or_insert(snapshot.into());
+    state.audit.record(
+        &name,
+        &AuditActor::api(teacher),
+        "RestoreRoom",
+        &before,
+        Some(room_state),
+    );
+    if replaced.is_none() {
+        state.webhooks.dispatch(
+            &room_state.webhooks,
+            WebhookEvent::RoomCreated { room: name.clone() },
+        );
+    }
+    Ok(web::Json(RestoredRoom { name }))
+}
+
+/// Checks the snapshot like the requests that build a room
+fn validate_snapshot(state: &AppState, snapshot: &RoomSnapshot) -> Result<(), CupsError> {
+    let validator = &state.validator;
+    validator.name(&snapshot.name)?;
+    validate_signals(&snapshot.signals)?;
+    validate_alert_rules(&snapshot.alert_rules, &snapshot.signals)?;
+    validate_cup_reset(&snapshot.cup_reset)?;
+    if let Some(url) = snapshot
+        .webhooks
+        .iter()
+        .find(|url| !state.webhooks.allows_url(url))
     {
-        Ok(HttpResponse::Ok().finish())
-    } else {
-        Err(e400(CupsError::RoomAlreadyExists(room_name)))
+        return Err(CupsError::InvalidWebhookUrl(url.clone()));
+    }
+    if let Some(last) = snapshot.questions.len().checked_sub(1) {
+        validator.new_question(last)?;
+    }
+    for (id, question) in &snapshot.questions {
+        validator.question(&Question {
+            title: question.title.clone(),
+            options: question.options.clone(),
+            correct: question.correct,
+        })?;
+        if let Some(answer) = question
+            .answers
+            .values()
+            .find(|&&answer| answer >= question.options.len())
+        {
+            return Err(CupsError::InvalidSnapshot(format!(
+                "answer {answer} to question {id}"
+            )));
+        }
+    }
+    if let Some(id) = snapshot
+        .playlist
+        .iter()
+        .find(|id| !snapshot.questions.contains_key(*id))
+    {
+        return Err(CupsError::InvalidSnapshot(format!(
+            "question {id} of the playlist doesn't exists"
+        )));
+    }
+    if !snapshot.playlist.iter().all_unique() {
+        return Err(CupsError::InvalidSnapshot(
+            "repeated questions in the playlist".to_string(),
+        ));
+    }
+    if snapshot
+        .playlist_position
+        .is_some_and(|position| position >= snapshot.playlist.len())
+    {
+        return Err(CupsError::InvalidSnapshot(
+            "playlist position out of the playlist".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct TemplatesInfo {
+    /// Templates of the teacher, sorted by name
+    pub templates: Vec<TemplateSummary>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct TemplateSummary {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub questions: usize,
+}
+
+impl TemplatesInfo {
+    /// Information of the templates that `teacher` can use
+    fn from_templates(
+        templates: &HashMap<String, RoomTemplate>,
+        teacher: Option<&Teacher>,
+    ) -> Self {
+        let templates = templates
+            .values()
+            .filter(|template| template.is_owner(teacher))
+            .map(|template| TemplateSummary {
+                name: template.name.clone(),
+                created_at: template.created_at,
+                questions: template.questions.len(),
+            })
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+            .collect();
+        Self { templates }
     }
 }
 
+#[tracing::instrument(skip_all)]
+pub async fn get_templates(
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> web::Json<TemplatesInfo> {
+    let templates = state.templates.lock().unwrap();
+    web::Json(TemplatesInfo::from_templates(
+        &templates,
+        teacher.0.as_ref(),
+    ))
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct SaveTemplate {
+    room: String,
+    /// Name of the template, replaces the teacher template with the same name
+    name: String,
+}
+
+/// Saves the questions and configuration of a room as a template
+#[tracing::instrument(skip(state))]
+pub async fn save_template(
+    form: web::Json<SaveTemplate>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let SaveTemplate { room, name } = form.into_inner();
+    state
+        .validator
+        .name(&name)
+        .map_err(|e| e400(CupsError::from(e)))?;
+    let teacher = teacher.0.as_ref();
+    let template = match state.rooms.lock().unwrap().get(&room) {
+        Some(room_state) if !room_state.can_manage(teacher) => {
+            return Err(e403(CupsError::NotAllowed(room)));
+        }
+        Some(room_state) => {
+            room_state.to_template(name.clone(), teacher.map(|teacher| teacher.id.clone()))
+        }
+        None => return Err(e400(CupsError::NoExistingRoom(room))),
+    };
+    let mut templates = state.templates.lock().unwrap();
+    match templates.get(&name) {
+        Some(existing) if !existing.is_owner(teacher) => {
+            Err(e403(CupsError::TemplateNotAllowed(name)))
+        }
+        _ => {
+            templates.insert(name, template);
+            Ok(HttpResponse::Ok().finish())
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct DeleteTemplate {
+    name: String,
+}
+
+/// Deletes a template and returns the remaining ones
+#[tracing::instrument(skip(state))]
+pub async fn delete_template(
+    form: web::Json<DeleteTemplate>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<web::Json<TemplatesInfo>, actix_web::Error> {
+    let name = form.into_inner().name;
+    let teacher = teacher.0.as_ref();
+    let mut templates = state.templates.lock().unwrap();
+    match templates.get(&name) {
+        Some(template) if !template.is_owner(teacher) => {
+            return Err(e403(CupsError::TemplateNotAllowed(name)));
+        }
+        Some(_) => {
+            templates.remove(&name);
+        }
+        None => return Err(e400(CupsError::NoExistingTemplate(name))),
+    }
+    Ok(web::Json(TemplatesInfo::from_templates(
+        &templates, teacher,
+    )))
+}
+
 #[derive(Debug, Deserialize, TS)]
 #[ts(export, export_to = "frontend/bindings/")]
 pub struct DeleteRoom {
     room: String,
 }
 
-/// Deletes a room and returns the new room information
+/// Deletes a room and returns the new room information, only the owner can delete it
 #[tracing::instrument(skip(state))]
 pub async fn delete_room(
     form: web::Json<DeleteRoom>,
     state: web::Data<AppState>,
+    teacher: TeacherSession,
 ) -> Result<web::Json<CupsInfo>, actix_web::Error> {
     let room_name = form.into_inner().room;
+    let teacher = teacher.0.as_ref();
+    let mut rooms = state.rooms.lock().unwrap();
+    match rooms.get(&room_name) {
+        Some(room_state) if !room_state.is_owner(teacher) => {
+            return Err(e403(CupsError::NotAllowed(room_name)));
+        }
+        Some(_) => {}
+        None => return Err(e400(CupsError::NoExistingRoom(room_name))),
+    }
+    if let Some(room_state) = rooms.remove(&room_name) {
+        state.audit.record(
+            &room_name,
+            &AuditActor::api(teacher),
+            "DeleteRoom",
+            &RoomCapture::new(&room_state),
+            None,
+        );
+        state.audit.remove_room(&room_name);
+        state.webhooks.dispatch(
+            &room_state.webhooks,
+            WebhookEvent::RoomDeleted { room: room_name },
+        );
+    }
+    Ok(web::Json(CupsInfo::from_rooms(&rooms, teacher)))
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct RoomWebhook {
+    room: String,
+    url: String,
+}
+
+/// Registers a webhook url that will receive the events of a room
+#[tracing::instrument(skip(state))]
+pub async fn create_webhook(
+    form: web::Json<RoomWebhook>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let RoomWebhook { room, url } = form.into_inner();
+    if !state.webhooks.enabled() {
+        return Err(e400(CupsError::WebhooksDisabled));
+    }
+    if !state.webhooks.allows_url(&url) {
+        return Err(e400(CupsError::InvalidWebhookUrl(url)));
+    }
+    let mut rooms = state.rooms.lock().unwrap();
+    match rooms.get_mut(&room) {
+        Some(room_state) if !room_state.can_manage(teacher.0.as_ref()) => {
+            Err(e403(CupsError::NotAllowed(room)))
+        }
+        Some(room_state) => {
+            let actor = AuditActor::api(teacher.0.as_ref());
+            state
+                .audit
+                .audited(room_state, &actor, "CreateWebhook", |room_state| {
+                    if !room_state.webhooks.contains(&url) {
+                        room_state.webhooks.push(url);
+                    }
+                });
+            Ok(HttpResponse::Ok().finish())
+        }
+        None => Err(e400(CupsError::NoExistingRoom(room))),
+    }
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn delete_webhook(
+    form: web::Json<RoomWebhook>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let RoomWebhook { room, url } = form.into_inner();
+    let mut rooms = state.rooms.lock().unwrap();
+    match rooms.get_mut(&room) {
+        Some(room_state) if !room_state.can_manage(teacher.0.as_ref()) => {
+            Err(e403(CupsError::NotAllowed(room)))
+        }
+        Some(room_state) => match room_state.webhooks.iter().position(|o| o == &url) {
+            Some(idx) => {
+                let actor = AuditActor::api(teacher.0.as_ref());
+                state
+                    .audit
+                    .audited(room_state, &actor, "DeleteWebhook", |room_state| {
+                        room_state.webhooks.remove(idx);
+                    });
+                Ok(HttpResponse::Ok().finish())
+            }
+            None => Err(e400(CupsError::NoExistingWebhook(url))),
+        },
+        None => Err(e400(CupsError::NoExistingRoom(room))),
+    }
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct RoomCoOwner {
+    room: String,
+    /// Teacher id given by the login provider
+    teacher: String,
+}
+
+/// Lets another teacher manage the room, only the owner can add co-owners
+#[tracing::instrument(skip(state))]
+pub async fn add_co_owner(
+    form: web::Json<RoomCoOwner>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let RoomCoOwner {
+        room,
+        teacher: co_owner,
+    } = form.into_inner();
+    let mut rooms = state.rooms.lock().unwrap();
+    match rooms.get_mut(&room) {
+        Some(room_state) if !room_state.is_owner(teacher.0.as_ref()) => {
+            Err(e403(CupsError::NotAllowed(room)))
+        }
+        Some(room_state) => {
+            let actor = AuditActor::api(teacher.0.as_ref());
+            state
+                .audit
+                .audited(room_state, &actor, "AddCoOwner", |room_state| {
+                    room_state.co_owners.insert(co_owner);
+                });
+            Ok(HttpResponse::Ok().finish())
+        }
+        None => Err(e400(CupsError::NoExistingRoom(room))),
+    }
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn remove_co_owner(
+    form: web::Json<RoomCoOwner>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let RoomCoOwner {
+        room,
+        teacher: co_owner,
+    } = form.into_inner();
+    let mut rooms = state.rooms.lock().unwrap();
+    match rooms.get_mut(&room) {
+        Some(room_state) if !room_state.is_owner(teacher.0.as_ref()) => {
+            Err(e403(CupsError::NotAllowed(room)))
+        }
+        Some(room_state) => {
+            let actor = AuditActor::api(teacher.0.as_ref());
+            let removed = state
+                .audit
+                .audited(room_state, &actor, "RemoveCoOwner", |room_state| {
+                    room_state.co_owners.remove(&co_owner)
+                });
+            if removed {
+                Ok(HttpResponse::Ok().finish())
+            } else {
+                Err(e400(CupsError::NoExistingCoOwner(co_owner)))
+            }
+        }
+        None => Err(e400(CupsError::NoExistingRoom(room))),
+    }
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct RoomIdentityMode {
+    room: String,
+    mode: IdentityMode,
+}
+
+/// Sets how students identify themselves, applied to the next connections
+#[tracing::instrument(skip(state))]
+pub async fn set_identity_mode(
+    form: web::Json<RoomIdentityMode>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let RoomIdentityMode { room, mode } = form.into_inner();
+    let mut rooms = state.rooms.lock().unwrap();
+    match rooms.get_mut(&room) {
+        Some(room_state) if !room_state.can_manage(teacher.0.as_ref()) => {
+            Err(e403(CupsError::NotAllowed(room)))
+        }
+        Some(room_state) => {
+            let actor = AuditActor::api(teacher.0.as_ref());
+            state
+                .audit
+                .audited(room_state, &actor, "SetIdentityMode", |room_state| {
+                    room_state.identity_mode = mode;
+                });
+            Ok(HttpResponse::Ok().finish())
+        }
+        None => Err(e400(CupsError::NoExistingRoom(room))),
+    }
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct RoomCupReset {
+    room: String,
+    options: CupResetOptions,
+}
+
+/// Sets when the cups are reset, the decay applies to the next cup choices
+#[tracing::instrument(skip(state))]
+pub async fn set_cup_reset(
+    form: web::Json<RoomCupReset>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let RoomCupReset { room, options } = form.into_inner();
+    validate_cup_reset(&options).map_err(e400)?;
+    let mut rooms = state.rooms.lock().unwrap();
+    match rooms.get_mut(&room) {
+        Some(room_state) if !room_state.can_manage(teacher.0.as_ref()) => {
+            Err(e403(CupsError::NotAllowed(room)))
+        }
+        Some(room_state) => {
+            let actor = AuditActor::api(teacher.0.as_ref());
+            state
+                .audit
+                .audited(room_state, &actor, "SetCupReset", |room_state| {
+                    room_state.cup_reset = options;
+                });
+            Ok(HttpResponse::Ok().finish())
+        }
+        None => Err(e400(CupsError::NoExistingRoom(room))),
+    }
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct RoomAutoShareResults {
+    room: String,
+    /// `null` to stop sharing the results automatically
+    mode: Option<ResultsMode>,
+}
+
+/// Sets the results sent to the students when a question publication ends
+#[tracing::instrument(skip(state))]
+pub async fn set_auto_share_results(
+    form: web::Json<RoomAutoShareResults>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let RoomAutoShareResults { room, mode } = form.into_inner();
+    let mut rooms = state.rooms.lock().unwrap();
+    match rooms.get_mut(&room) {
+        Some(room_state) if !room_state.can_manage(teacher.0.as_ref()) => {
+            Err(e403(CupsError::NotAllowed(room)))
+        }
+        Some(room_state) => {
+            let actor = AuditActor::api(teacher.0.as_ref());
+            state
+                .audit
+                .audited(room_state, &actor, "SetAutoShareResults", |room_state| {
+                    room_state.auto_share_results = mode;
+                });
+            Ok(HttpResponse::Ok().finish())
+        }
+        None => Err(e400(CupsError::NoExistingRoom(room))),
+    }
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct RoomAlertRules {
+    room: String,
+    rules: Vec<CupAlertRule>,
+}
+
+/// Replaces the rules that alert the teachers about the room cups
+#[tracing::instrument(skip(state))]
+pub async fn set_alert_rules(
+    form: web::Json<RoomAlertRules>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let RoomAlertRules { room, rules } = form.into_inner();
+    let mut rooms = state.rooms.lock().unwrap();
+    match rooms.get_mut(&room) {
+        Some(room_state) if !room_state.can_manage(teacher.0.as_ref()) => {
+            Err(e403(CupsError::NotAllowed(room)))
+        }
+        Some(room_state) => {
+            validate_alert_rules(&rules, &room_state.signals).map_err(e400)?;
+            let actor = AuditActor::api(teacher.0.as_ref());
+            state
+                .audit
+                .audited(room_state, &actor, "SetAlertRules", |room_state| {
+                    room_state.set_alert_rules(rules);
+                });
+            Ok(HttpResponse::Ok().finish())
+        }
+        None => Err(e400(CupsError::NoExistingRoom(room))),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RosterQuery {
+    room: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RosterEntry {
+    id: String,
+    name: Option<String>,
+}
+
+/// Replaces the room roster with a CSV with the columns `id` and `name` (optional)
+#[tracing::instrument(skip(body, state))]
+pub async fn upload_roster(
+    query: web::Query<RosterQuery>,
+    body: String,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let room = query.into_inner().room;
+    let roster = parse_roster(&body).map_err(|e| e400(CupsError::InvalidRoster(e)))?;
     let mut rooms = state.rooms.lock().unwrap();
-    if rooms.remove(&room_name).is_some() {
-        let rooms = rooms.keys().cloned().collect();
-        let cups_info = CupsInfo { rooms };
-        Ok(web::Json(cups_info))
-    } else {
-        Err(e400(CupsError::NoExistingRoom(room_name)))
+    match rooms.get_mut(&room) {
+        Some(room_state) if !room_state.can_manage(teacher.0.as_ref()) => {
+            Err(e403(CupsError::NotAllowed(room)))
+        }
+        Some(room_state) => {
+            let actor = AuditActor::api(teacher.0.as_ref());
+            state
+                .audit
+                .audited(room_state, &actor, "UploadRoster", |room_state| {
+                    room_state.roster = roster;
+                });
+            Ok(HttpResponse::Ok().finish())
+        }
+        None => Err(e400(CupsError::NoExistingRoom(room))),
+    }
+}
+
+/// Student id -> Name, the id is used when the name is missing
+fn parse_roster(csv: &str) -> Result<HashMap<String, String>, csv::Error> {
+    csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(csv.as_bytes())
+        .deserialize::<RosterEntry>()
+        .filter(|entry| !matches!(entry, Ok(entry) if entry.id.is_empty()))
+        .map_ok(|RosterEntry { id, name }| {
+            let name = name
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| id.clone());
+            (id, name)
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimelineQuery {
+    room: String,
+    bucket_secs: Option<usize>,
+}
+
+/// Cup counts over the room session with the question publications
+#[tracing::instrument(skip(state))]
+pub async fn get_cup_timeline(
+    query: web::Query<TimelineQuery>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<web::Json<CupTimeline>, actix_web::Error> {
+    let TimelineQuery { room, bucket_secs } = query.into_inner();
+    let rooms = state.rooms.lock().unwrap();
+    match rooms.get(&room) {
+        Some(room_state) if !room_state.can_manage(teacher.0.as_ref()) => {
+            Err(e403(CupsError::NotAllowed(room)))
+        }
+        Some(room_state) => Ok(web::Json(
+            room_state.cup_timeline(bucket_secs.unwrap_or(DEFAULT_TIMELINE_BUCKET_SECS)),
+        )),
+        None => Err(e400(CupsError::NoExistingRoom(room))),
+    }
+}
+
+/// Audit trail of the room, filtered by session, action or time
+#[tracing::instrument(skip(state))]
+pub async fn get_audit_trail(
+    room: web::Path<String>,
+    filter: web::Query<AuditFilter>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<web::Json<AuditTrail>, actix_web::Error> {
+    let room = room.into_inner();
+    match state.rooms.lock().unwrap().get(&room) {
+        Some(room_state) if !room_state.can_manage(teacher.0.as_ref()) => {
+            return Err(e403(CupsError::NotAllowed(room)));
+        }
+        Some(_) => {}
+        None => return Err(e400(CupsError::NoExistingRoom(room))),
+    }
+    Ok(web::Json(state.audit.trail(&room, &filter)))
+}
+
+/// Events of the room with the state derived from them
+#[tracing::instrument(skip(state))]
+pub async fn get_room_history(
+    room: web::Path<String>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+) -> Result<web::Json<RoomHistory>, actix_web::Error> {
+    let room = room.into_inner();
+    match state.rooms.lock().unwrap().get(&room) {
+        Some(room_state) if !room_state.can_manage(teacher.0.as_ref()) => {
+            Err(e403(CupsError::NotAllowed(room)))
+        }
+        Some(room_state) => Ok(web::Json(room_state.history())),
+        None => Err(e400(CupsError::NoExistingRoom(room))),
     }
 }
@@ -0,0 +1,166 @@
+use crate::{
+    jwks::JwksError,
+    lti::{Lti, LtiError, LtiLaunch, LtiRoom},
+    routes::message::ConnectionType,
+    state::{AppState, RoomState},
+    utils::{e400, e500},
+    webhooks::WebhookEvent,
+};
+use actix_web::{http::header::LOCATION, web, HttpResponse};
+use anyhow::anyhow;
+use serde::Deserialize;
+
+/// Third party initiated login parameters
+#[derive(Debug, Deserialize)]
+pub struct LtiLogin {
+    iss: String,
+    login_hint: String,
+    lti_message_hint: Option<String>,
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn lti_login_query(
+    query: web::Query<LtiLogin>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    lti_login(query.into_inner(), &state)
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn lti_login_form(
+    form: web::Form<LtiLogin>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    lti_login(form.into_inner(), &state)
+}
+
+/// Redirects to the platform to get the id token
+fn lti_login(login: LtiLogin, state: &AppState) -> Result<HttpResponse, actix_web::Error> {
+    let url = get_lti(state)?
+        .login(
+            &login.iss,
+            &login.login_hint,
+            login.lti_message_hint.as_deref(),
+        )
+        .map_err(lti_error)?;
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, url))
+        .finish())
+}
+
+#[derive(Deserialize)]
+pub struct LtiLaunchForm {
+    id_token: String,
+    state: String,
+}
+
+/// Validates the launch, creates the room of the LTI context if needed and redirects to it
+#[tracing::instrument(skip_all)]
+pub async fn lti_launch(
+    form: web::Form<LtiLaunchForm>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let LtiLaunchForm {
+        id_token,
+        state: lti_state,
+    } = form.into_inner();
+    let lti = get_lti(&state)?;
+    let claims = lti
+        .validate(&lti_state, &id_token)
+        .await
+        .map_err(lti_error)?;
+    let connection_type = claims
+        .connection_type()
+        .ok_or_else(|| lti_error(LtiError::InvalidRole))?;
+    let context = claims
+        .context
+        .ok_or_else(|| lti_error(LtiError::MissingContext))?;
+    let lineitem = claims
+        .ags_endpoint
+        .and_then(|ags_endpoint| ags_endpoint.score_lineitem());
+
+    let room_name = {
+        let mut rooms = state.rooms.lock().unwrap();
+        let existing = rooms.values_mut().find(|room_state| {
+            room_state
+                .lti
+                .as_ref()
+                .map(|lti_room| lti_room.context_id == context.id)
+                .unwrap_or(false)
+        });
+        match existing {
+            Some(room_state) => {
+                if let (Some(lti_room), Some(lineitem)) = (room_state.lti.as_mut(), lineitem) {
+                    lti_room.lineitem = Some(lineitem);
+                }
+                room_state.name.clone()
+            }
+            None => {
+                let mut room_name = context.title.unwrap_or_else(|| context.id.clone());
+                if rooms.contains_key(&room_name) {
+                    room_name = format!("{} ({})", room_name, context.id);
+                }
+                if rooms.contains_key(&room_name) {
+                    return Err(lti_error(LtiError::UnexpectedError(anyhow!(
+                        "Room {room_name:?} already exists."
+                    ))));
+                }
+                let mut room_state = RoomState::new(room_name.clone(), None);
+                room_state.lti = Some(LtiRoom {
+                    context_id: context.id,
+                    lineitem,
+                });
+                rooms.insert(room_name.clone(), room_state);
+                state.webhooks.dispatch(
+                    &[],
+                    WebhookEvent::RoomCreated {
+                        room: room_name.clone(),
+                    },
+                );
+                room_name
+            }
+        }
+    };
+
+    let launch_id = lti.add_launch(LtiLaunch {
+        user_id: claims.sub,
+        connection_type,
+        room: room_name.clone(),
+    });
+    let mut url = reqwest::Url::parse("http://localhost/room").unwrap();
+    url.path_segments_mut().unwrap().push(&room_name);
+    match connection_type {
+        ConnectionType::Student => {}
+        ConnectionType::Teacher => {
+            url.path_segments_mut().unwrap().push("teacher");
+        }
+        ConnectionType::Assistant => {
+            url.path_segments_mut().unwrap().push("assistant");
+        }
+        ConnectionType::Projector => {
+            url.path_segments_mut().unwrap().push("projector");
+        }
+    }
+    url.query_pairs_mut()
+        .append_pair("launch", &launch_id.to_string());
+    let location = format!("{}?{}", url.path(), url.query().unwrap_or_default());
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, location))
+        .finish())
+}
+
+fn get_lti(state: &AppState) -> Result<&Lti, actix_web::Error> {
+    state
+        .lti
+        .as_ref()
+        .ok_or_else(|| lti_error(LtiError::NotConfigured))
+}
+
+fn lti_error(e: LtiError) -> actix_web::Error {
+    match e {
+        LtiError::UnexpectedError(_) | LtiError::JwksError(JwksError::UnexpectedError(_)) => {
+            e500(e).into()
+        }
+        e => e400(e),
+    }
+}
@@ -0,0 +1,6 @@
+use crate::{state::AppState, telemetry::MetricsInfo};
+use actix_web::web;
+
+pub async fn get_metrics(state: web::Data<AppState>) -> web::Json<MetricsInfo> {
+    web::Json(state.metrics.snapshot(&state.connection_limiter))
+}
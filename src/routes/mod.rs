@@ -1,7 +1,15 @@
+mod admin;
+mod auth;
 mod cups;
 mod health_check;
+mod lti;
+mod metrics;
 mod ws;
 
+pub use admin::*;
+pub use auth::*;
 pub use cups::*;
 pub use health_check::*;
+pub use lti::*;
+pub use metrics::*;
 pub use ws::*;
@@ -1,4 +1,5 @@
-use crate::{error_chain_fmt, state::StateError};
+use super::message::ConnectionType;
+use crate::{error_chain_fmt, lti::LtiError, state::StateError, validation::ValidationError};
 use uuid::Uuid;
 
 #[derive(thiserror::Error)]
@@ -9,6 +10,10 @@ pub enum WSError {
     NoRoom,
     #[error("Invalid room: {0:?}.")]
     InvalidRoom(String),
+    #[error("Too many messages, slow down.")]
+    RateLimited,
+    #[error("Messages can't be larger than {0} bytes.")]
+    MessageTooLarge(usize),
     #[error("Failed to parse websocket message.")]
     ParseError(#[source] anyhow::Error),
     #[error("{0}")]
@@ -17,6 +22,40 @@ pub enum WSError {
     InvalidQuestionId(Uuid),
     #[error("{0}")]
     InvalidAnswer(#[source] StateError),
+    #[error("{0}")]
+    InvalidCup(#[source] StateError),
+    #[error("{0}")]
+    InvalidHand(#[source] StateError),
+    #[error("{0}")]
+    InvalidPlaylist(#[source] StateError),
+    #[error("{0}")]
+    InvalidSchedule(#[source] StateError),
+    #[error("{0}")]
+    InvalidQuestionOrder(#[source] StateError),
+    #[error("{0}")]
+    InvalidIdentity(#[source] StateError),
+    #[error("{0}")]
+    InvalidInput(#[from] ValidationError),
+    #[error("A client can't join more than {0} rooms.")]
+    TooManyRooms(usize),
+    #[error("Origin {0:?} is not allowed.")]
+    OriginNotAllowed(String),
+    #[error("Teacher login required.")]
+    TeacherLoginRequired,
+    #[error("Not allowed to manage room {0:?}.")]
+    NotAllowed(String),
+    #[error("{0:?} connections can't do this.")]
+    TaskNotAllowed(ConnectionType),
+    #[error("Invalid LTI launch.")]
+    InvalidLtiLaunch,
+    #[error("The room has no LTI line item to send grades.")]
+    NoLtiLineItem,
+    #[error("No question has a correct answer.")]
+    NoGradableQuestions,
+    #[error("Invalid replay speed: {0}, it must be from 0.1 to 100.")]
+    InvalidReplaySpeed(f64),
+    #[error("{0}")]
+    LtiError(#[from] LtiError),
 }
 
 impl std::fmt::Debug for WSError {
@@ -30,6 +69,17 @@ impl From<StateError> for WSError {
         match e {
             StateError::InvalidId => Self::InvalidClientId(e),
             StateError::InvalidAnswer(_) => Self::InvalidAnswer(e),
+            StateError::InvalidCup(_) => Self::InvalidCup(e),
+            StateError::NoRaisedHands | StateError::HandNotRaised(_) => Self::InvalidHand(e),
+            StateError::InvalidPlaylist(_)
+            | StateError::PlaylistEnd
+            | StateError::PlaylistStart => Self::InvalidPlaylist(e),
+            StateError::InvalidSchedule(_) => Self::InvalidSchedule(e),
+            StateError::InvalidQuestionOrder(_) => Self::InvalidQuestionOrder(e),
+            StateError::DisplayNameRequired
+            | StateError::StudentIdRequired
+            | StateError::NotInRoster(_)
+            | StateError::StudentAlreadyConnected(_) => Self::InvalidIdentity(e),
         }
     }
 }
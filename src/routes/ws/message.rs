@@ -1,9 +1,14 @@
 //! Web socket messages
 
 use super::error::WSError;
-use crate::state::{QuestionState, RoomState};
+use crate::{
+    history::RoomEventRecord,
+    state::{QuestionState, RoomState},
+};
 use actix::Message;
 use anyhow::Context;
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, str::FromStr};
 use ts_rs::TS;
@@ -16,12 +21,92 @@ use uuid::Uuid;
 #[ts(export, export_to = "frontend/bindings/")]
 pub enum WSMessage {
     RoomConnect(RoomConnectInfo),
-    ChooseCup(CupColor),
+    ChooseCup(SignalId),
     CreateQuestion(Question),
     PublishQuestion(PublishQuestion),
     DeleteQuestion(QuestionId),
     ModifyQuestion(QuestionModification),
     AnswerQuestion(QuestionAnswer),
+    /// Sends the students scores to the LTI platform
+    SubmitGrades,
+    GetCupTimeline(CupTimelineRequest),
+    /// Student joins the end of the raised hands queue
+    RaiseHand,
+    /// Student leaves the raised hands queue
+    LowerHand,
+    /// Gives the word to a student in the raised hands queue
+    CallOn(CallOnRequest),
+    ClearHands,
+    /// Clears the cup of every student
+    ResetCups,
+    /// Sends the results of a question to the students and projectors
+    ShareResults(ShareResults),
+    /// Replaces the room playlist with the given questions, in order
+    SetPlaylist(Vec<QuestionId>),
+    /// Publishes the next question of the playlist
+    NextQuestion(PlaylistStep),
+    /// Publishes the previous question of the playlist
+    PreviousQuestion(PlaylistStep),
+    /// Publishes a question at a given time or after a delay
+    ScheduleQuestion(ScheduleQuestion),
+    /// Cancels the scheduled publication of a question
+    CancelSchedule(QuestionId),
+    /// Moves the given questions to the front, in order
+    ReorderQuestions(Vec<QuestionId>),
+}
+
+impl WSMessage {
+    /// Whether a `connection_type` connection can send this message
+    pub fn allowed_for(&self, connection_type: ConnectionType) -> bool {
+        use ConnectionType::*;
+        match self {
+            Self::RoomConnect(_) => true,
+            Self::ChooseCup(_) | Self::AnswerQuestion(_) | Self::RaiseHand | Self::LowerHand => {
+                connection_type == Student
+            }
+            Self::CreateQuestion(_)
+            | Self::PublishQuestion(_)
+            | Self::ModifyQuestion(_)
+            | Self::CallOn(_)
+            | Self::ClearHands
+            | Self::ResetCups
+            | Self::ShareResults(_)
+            | Self::SetPlaylist(_)
+            | Self::NextQuestion(_)
+            | Self::PreviousQuestion(_)
+            | Self::ScheduleQuestion(_)
+            | Self::CancelSchedule(_)
+            | Self::ReorderQuestions(_) => matches!(connection_type, Teacher | Assistant),
+            Self::DeleteQuestion(_) | Self::SubmitGrades => connection_type == Teacher,
+            Self::GetCupTimeline(_) => connection_type != Student,
+        }
+    }
+
+    pub fn task(&self) -> &'static str {
+        match self {
+            Self::RoomConnect(_) => "RoomConnect",
+            Self::ChooseCup(_) => "ChooseCup",
+            Self::CreateQuestion(_) => "CreateQuestion",
+            Self::PublishQuestion(_) => "PublishQuestion",
+            Self::DeleteQuestion(_) => "DeleteQuestion",
+            Self::ModifyQuestion(_) => "ModifyQuestion",
+            Self::AnswerQuestion(_) => "AnswerQuestion",
+            Self::SubmitGrades => "SubmitGrades",
+            Self::GetCupTimeline(_) => "GetCupTimeline",
+            Self::RaiseHand => "RaiseHand",
+            Self::LowerHand => "LowerHand",
+            Self::CallOn(_) => "CallOn",
+            Self::ClearHands => "ClearHands",
+            Self::ResetCups => "ResetCups",
+            Self::ShareResults(_) => "ShareResults",
+            Self::SetPlaylist(_) => "SetPlaylist",
+            Self::NextQuestion(_) => "NextQuestion",
+            Self::PreviousQuestion(_) => "PreviousQuestion",
+            Self::ScheduleQuestion(_) => "ScheduleQuestion",
+            Self::CancelSchedule(_) => "CancelSchedule",
+            Self::ReorderQuestions(_) => "ReorderQuestions",
+        }
+    }
 }
 
 impl FromStr for WSMessage {
@@ -39,28 +124,151 @@ impl FromStr for WSMessage {
 pub struct RoomConnectInfo {
     pub room_name: String,
     pub connection_type: ConnectionType,
+    /// Id received when redirected from an LTI launch
+    #[ts(type = "string | null")]
+    #[serde(default)]
+    pub lti_launch: Option<Uuid>,
+    /// Student nickname, used when the room identity mode is `Nickname`
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// Student id in the room roster, used when the room identity mode is `Roster`
+    #[serde(default)]
+    pub student_id: Option<String>,
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, TS)]
+/// How students identify themselves in a room
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub enum IdentityMode {
+    /// Students are not identified
+    #[default]
+    Anonymous,
+    /// Students choose a display name
+    Nickname,
+    /// Only students on the room roster can connect
+    Roster,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, TS)]
 #[ts(export, export_to = "frontend/bindings/")]
 pub enum ConnectionType {
     Student,
     Teacher,
+    /// Manages the questions with the teacher, but can't delete them or send grades
+    Assistant,
+    /// Read-only view for the classroom screen, opened by someone allowed to manage the room,
+    /// only gets aggregated information
+    Projector,
+}
+
+impl ConnectionType {
+    /// Teachers and assistants, which need to be allowed to manage the room
+    pub fn manages_room(&self) -> bool {
+        matches!(self, Self::Teacher | Self::Assistant)
+    }
+
+    /// Every connection but students, which need a teacher allowed to manage the room
+    pub fn needs_teacher(&self) -> bool {
+        !matches!(self, Self::Student)
+    }
 }
 
-#[derive(Clone, Debug, Deserialize, TS)]
+/// Id of one of the room cup signals
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash, TS)]
 #[ts(export, export_to = "frontend/bindings/")]
-pub enum CupColor {
-    Green,
-    Yellow,
-    Red,
+pub struct SignalId(pub String);
+
+/// When the students cups go back to neutral, besides the `ResetCups` task
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct CupResetOptions {
+    /// Clear every cup when a question is published
+    #[serde(default)]
+    pub on_publish: bool,
+    /// Clear a cup this many minutes after it was chosen, from 1 to 1440 (a day)
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub decay_minutes: Option<u64>,
 }
 
-#[derive(Debug, Deserialize, TS)]
+/// Condition on the room cups that alerts the teachers
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct CupAlertRule {
+    pub id: String,
+    pub condition: CupAlertCondition,
+    /// Seconds the condition needs to hold before alerting, at most a day
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub hold_secs: u64,
+    /// Minimum seconds between two alerts of the rule, 60 if missing, at most a day
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub cooldown_secs: Option<u64>,
+}
+
+/// Ratios are over the connected students
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, TS)]
+#[serde(tag = "kind")]
+#[ts(export, export_to = "frontend/bindings/")]
+pub enum CupAlertCondition {
+    /// At least `ratio` of the students chose `signal`
+    SignalAtLeast { signal: String, ratio: f64 },
+    /// Less than `ratio` of the students chose any cup
+    ChosenBelow { ratio: f64 },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct CupAlert {
+    pub rule: String,
+    pub condition: CupAlertCondition,
+    /// Ratio of students that triggered the alert
+    pub ratio: f64,
+    pub connections: usize,
+}
+
+/// Signal that students can choose in a room
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct CupSignal {
+    pub id: String,
+    pub label: String,
+    /// CSS color
+    pub color: String,
+    /// Whether choosing it counts towards the webhook cups threshold
+    #[serde(default)]
+    pub alert: bool,
+}
+
+impl CupSignal {
+    pub fn new(id: &str, label: &str, color: &str, alert: bool) -> Self {
+        Self {
+            id: id.to_string(),
+            label: label.to_string(),
+            color: color.to_string(),
+            alert,
+        }
+    }
+
+    /// The classic green, yellow and red cups
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::new("Green", "I am following the lesson", "#22c55e", false),
+            Self::new("Yellow", "Please slow down", "#fde047", false),
+            Self::new("Red", "Stop, I have a question", "#ef4444", true),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, TS)]
 #[ts(export, export_to = "frontend/bindings/")]
 pub struct Question {
     pub title: String,
     pub options: Vec<String>,
+    /// Index of the correct option, used to grade the students
+    #[serde(default)]
+    pub correct: Option<usize>,
 }
 
 #[derive(Debug, Deserialize, TS)]
@@ -83,6 +291,36 @@ pub enum ClientMessage {
     QuestionsInfo(Vec<QuestionInfo>),
     QuestionPublication(QuestionPublication),
     QuestionDelete(QuestionId),
+    /// Per-student detail for teachers and assistants, unless the room is anonymous
+    StudentsInfo(Vec<StudentDetail>),
+    CupTimeline(CupTimeline),
+    /// Raised hands queue for teachers and assistants
+    HandsQueue(Vec<RaisedHand>),
+    /// Position of the student in the raised hands queue (starting at 1), `null` once lowered
+    HandPosition(Option<usize>),
+    /// The student was given the word
+    CalledOn,
+    /// The cup of the student was reset
+    CupCleared,
+    /// A room alert rule was triggered, for teachers and assistants
+    CupAlert(CupAlert),
+    /// Results of a question shared with the students and projectors
+    QuestionResults(QuestionResults),
+    /// Playlist and scheduled publications, for teachers and assistants
+    Playlist(PlaylistInfo),
+    /// The server is going down, clients can reconnect after `reconnect_after` milliseconds
+    ServerShutdown {
+        #[ts(type = "number")]
+        reconnect_after: u64,
+    },
+    /// Maintenance announcement from the administrators
+    Announcement(String),
+    /// The session was closed by an administrator, with the reason
+    Disconnected(String),
+    /// Event of a room replay
+    ReplayEvent(RoomEventRecord),
+    /// Every event of the replay was sent
+    ReplayFinished,
     Error(String),
 }
 
@@ -91,7 +329,8 @@ impl ClientMessage {
         Self::Error("Internal server error".to_string())
     }
 
-    pub fn from_questions_map(questions: HashMap<Uuid, QuestionState>) -> Self {
+    /// Questions information, in the room order
+    pub fn from_questions_map(questions: IndexMap<Uuid, QuestionState>) -> Self {
         let all_info = questions
             .into_iter()
             .map(|(id, question_state)| {
@@ -100,12 +339,38 @@ impl ClientMessage {
                     id: QuestionId(id),
                     title: question_state.title,
                     options: question_state.options,
+                    correct: question_state.correct,
                     answers,
+                    created_at: question_state.created_at,
                 }
             })
             .collect();
         Self::QuestionsInfo(all_info)
     }
+
+    /// Message as sent to `connection_type`, only teachers and assistants get the correct
+    /// options of the questions and only them and projectors get the answer counts
+    pub fn visible_to(&self, connection_type: ConnectionType) -> Self {
+        match self {
+            Self::QuestionsInfo(questions) if !connection_type.manages_room() => {
+                let counts = connection_type == ConnectionType::Projector;
+                Self::QuestionsInfo(
+                    questions
+                        .iter()
+                        .map(|question| QuestionInfo {
+                            correct: None,
+                            answers: match counts {
+                                true => question.answers.clone(),
+                                false => Vec::new(),
+                            },
+                            ..question.clone()
+                        })
+                        .collect(),
+                )
+            }
+            msg => msg.clone(),
+        }
+    }
 }
 
 impl From<WSError> for ClientMessage {
@@ -119,49 +384,104 @@ impl From<WSError> for ClientMessage {
 pub struct RoomInfo {
     pub name: String,
     pub connections: usize,
-    pub green: usize,
-    pub yellow: usize,
-    pub red: usize,
+    /// Signals of the room, in display order
+    pub signals: Vec<CupSignal>,
+    /// Signal id -> Students that chose it
+    pub cups: HashMap<String, usize>,
 }
 
-impl From<RoomState> for RoomInfo {
-    fn from(state: RoomState) -> Self {
-        let (green, yellow, red) =
+impl From<&RoomState> for RoomInfo {
+    fn from(state: &RoomState) -> Self {
+        let cups = state.cup_counts(
             state
                 .student_connections
                 .values()
-                .fold((0, 0, 0), |mut acc, d| {
-                    match d.cup_selection {
-                        Some(CupColor::Green) => {
-                            acc.0 += 1;
-                        }
-                        Some(CupColor::Yellow) => {
-                            acc.1 += 1;
-                        }
-                        Some(CupColor::Red) => {
-                            acc.2 += 1;
-                        }
-                        _ => {}
-                    }
-                    acc
-                });
+                .filter_map(|student| student.cup_selection.as_ref()),
+        );
         Self {
-            name: state.name,
+            name: state.name.clone(),
             connections: state.student_connections.len(),
-            green,
-            yellow,
-            red,
+            signals: state.signals.clone(),
+            cups,
         }
     }
 }
 
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct CupTimelineRequest {
+    /// Size of the time buckets, 60 seconds by default
+    pub bucket_secs: Option<usize>,
+}
+
+/// Cup counts over the room session
+#[derive(Clone, Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct CupTimeline {
+    /// Size of the buckets, can be bigger than requested to limit the number of buckets
+    pub bucket_secs: usize,
+    pub buckets: Vec<CupBucket>,
+    pub publications: Vec<PublicationMark>,
+}
+
+/// Cup counts at the end of the bucket
+#[derive(Clone, Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct CupBucket {
+    pub start: DateTime<Utc>,
+    /// Signal id -> Students that chose it
+    pub cups: HashMap<String, usize>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct PublicationMark {
+    pub timestamp: DateTime<Utc>,
+    pub id: QuestionId,
+    pub title: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct StudentDetail {
+    #[ts(type = "string")]
+    pub id: Uuid,
+    pub name: String,
+    pub student_id: Option<String>,
+    pub cup: Option<SignalId>,
+    /// QuestionId -> Answer
+    #[ts(type = "Record<string, number>")]
+    pub answers: HashMap<Uuid, usize>,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct CallOnRequest {
+    /// Student to call on, the first in the queue if `null`
+    #[ts(type = "string | null")]
+    #[serde(default)]
+    pub student: Option<Uuid>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct RaisedHand {
+    #[ts(type = "string")]
+    pub id: Uuid,
+    /// Student name, `None` for anonymous rooms
+    pub name: Option<String>,
+    pub raised_at: DateTime<Utc>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, TS)]
 #[ts(export, export_to = "frontend/bindings/")]
 pub struct QuestionInfo {
     pub id: QuestionId,
     pub title: String,
     pub options: Vec<String>,
+    pub correct: Option<usize>,
     pub answers: Vec<usize>,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, TS)]
@@ -174,6 +494,77 @@ pub struct QuestionPublication {
     pub secs: usize,
 }
 
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct PlaylistStep {
+    /// Seconds for the question to be available to students
+    pub secs: usize,
+}
+
+/// Either `at` or `delay_secs` needs to be given
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct ScheduleQuestion {
+    pub id: QuestionId,
+    /// Seconds for the question to be available to students
+    pub secs: usize,
+    /// Time of the publication
+    #[serde(default)]
+    pub at: Option<DateTime<Utc>>,
+    /// Seconds from now until the publication
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub delay_secs: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct ScheduledQuestion {
+    pub id: QuestionId,
+    pub at: DateTime<Utc>,
+    pub secs: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct PlaylistInfo {
+    pub questions: Vec<QuestionId>,
+    /// Index of the last published question of the playlist
+    pub position: Option<usize>,
+    /// Upcoming publications, sorted by time
+    pub scheduled: Vec<ScheduledQuestion>,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct ShareResults {
+    pub id: QuestionId,
+    pub mode: ResultsMode,
+}
+
+/// What students see of the answers
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub enum ResultsMode {
+    Counts,
+    Percentages,
+    Both,
+}
+
+/// Answers summary without the students answers
+#[derive(Clone, Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "frontend/bindings/")]
+pub struct QuestionResults {
+    pub id: QuestionId,
+    pub title: String,
+    pub options: Vec<String>,
+    /// Answers per option
+    pub counts: Option<Vec<usize>>,
+    /// Percentage of the answers per option
+    pub percentages: Option<Vec<f64>>,
+    pub correct: Option<usize>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, TS, PartialEq, Eq)]
 #[ts(export, export_to = "frontend/bindings/")]
 pub struct QuestionId(#[ts(type = "string")] pub Uuid);
@@ -1,23 +1,158 @@
 //! Inspired by cups.fast.ai
 mod error;
 pub mod message;
+mod replay;
 mod session;
+mod timer;
 
-use self::session::WSSession;
-use crate::{configuration::WSSettings, state::AppState};
-use actix_web::{web, Error, HttpRequest, HttpResponse};
+use self::{error::WSError, replay::ReplaySession, session::WSSession};
+use crate::{
+    configuration::{AllowedOrigins, WSSettings},
+    connection_limit::{ConnectionLimitError, ConnectionPermit},
+    session_state::{TeacherSession, TypedSession},
+    state::AppState,
+    utils::{e400, e403, e429, e500, e503},
+};
+use actix_web::{http::header, web, Error, HttpRequest, HttpResponse};
 use actix_web_actors::ws;
+use serde::Deserialize;
+use std::net::IpAddr;
+use uuid::Uuid;
 
 #[tracing::instrument(name = "Starting web socket", skip_all)]
 pub async fn ws(
     req: HttpRequest,
     stream: web::Payload,
     settings: web::Data<WSSettings>,
+    allowed_origins: web::Data<AllowedOrigins>,
     state: web::Data<AppState>,
+    session: TypedSession,
 ) -> Result<HttpResponse, Error> {
+    check_origin(&req, &allowed_origins)?;
+    let teacher = session.get_teacher().map_err(e500)?;
+    let permit = connection_permit(&req, &settings, &state, &session)?;
     ws::start(
-        WSSession::new(state, settings.as_ref().clone()),
+        WSSession::new(state, settings.as_ref().clone(), teacher, permit),
         &req,
         stream,
     )
 }
+
+/// Bounds of the replay speed, so the gaps between events stay in range
+const MIN_REPLAY_SPEED: f64 = 0.1;
+const MAX_REPLAY_SPEED: f64 = 100.0;
+
+#[derive(Debug, Deserialize)]
+pub struct ReplayParameters {
+    /// How many times faster than real time, 1 by default
+    speed: Option<f64>,
+}
+
+/// Read-only websocket that replays the events of the room
+#[tracing::instrument(name = "Starting replay web socket", skip_all)]
+#[allow(clippy::too_many_arguments)]
+pub async fn replay(
+    req: HttpRequest,
+    stream: web::Payload,
+    room: web::Path<String>,
+    parameters: web::Query<ReplayParameters>,
+    settings: web::Data<WSSettings>,
+    allowed_origins: web::Data<AllowedOrigins>,
+    state: web::Data<AppState>,
+    teacher: TeacherSession,
+    session: TypedSession,
+) -> Result<HttpResponse, Error> {
+    check_origin(&req, &allowed_origins)?;
+    let speed = parameters.speed.unwrap_or(1.0);
+    if !(MIN_REPLAY_SPEED..=MAX_REPLAY_SPEED).contains(&speed) {
+        return Err(e400(WSError::InvalidReplaySpeed(speed)));
+    }
+    let room = room.into_inner();
+    let events = match state.rooms.lock().unwrap().get(&room) {
+        Some(room_state) if !room_state.can_manage(teacher.0.as_ref()) => {
+            return Err(e403(WSError::NotAllowed(room)));
+        }
+        Some(room_state) => room_state.events.clone(),
+        None => return Err(e400(WSError::InvalidRoom(room))),
+    };
+    let permit = connection_permit(&req, &settings, &state, &session)?;
+    ws::start(
+        ReplaySession::new(state, settings.as_ref().clone(), events, speed, permit),
+        &req,
+        stream,
+    )
+}
+
+fn check_origin(req: &HttpRequest, allowed_origins: &AllowedOrigins) -> Result<(), Error> {
+    if let Some(origin) = req.headers().get(header::ORIGIN) {
+        let origin = origin.to_str().unwrap_or_default();
+        let host = req
+            .headers()
+            .get(header::HOST)
+            .and_then(|host| host.to_str().ok());
+        if !allowed_origins.is_allowed(origin, host) {
+            tracing::warn!(origin, "Websocket from a not allowed origin.");
+            return Err(e403(WSError::OriginNotAllowed(origin.to_string())));
+        }
+    }
+    Ok(())
+}
+
+/// Address of the client, forwarded addresses are only followed from trusted proxies
+fn client_ip(req: &HttpRequest, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer);
+    }
+    // Each proxy appends the address it got the request from
+    let forwarded = req
+        .headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|ip| ip.trim().parse::<IpAddr>().ok())
+        .collect::<Vec<_>>();
+    let mut client = peer;
+    for ip in forwarded.into_iter().rev() {
+        match ip {
+            Some(ip) if trusted_proxies.contains(&client) => client = ip,
+            _ => break,
+        }
+    }
+    Some(client)
+}
+
+/// Id of the browser session, created on its first connection
+fn client_id(session: &TypedSession) -> Result<Uuid, Error> {
+    match session.get_client_id().map_err(e500)? {
+        Some(client_id) => Ok(client_id),
+        None => {
+            let client_id = Uuid::new_v4();
+            session.insert_client_id(client_id).map_err(e500)?;
+            Ok(client_id)
+        }
+    }
+}
+
+fn connection_permit(
+    req: &HttpRequest,
+    settings: &WSSettings,
+    state: &web::Data<AppState>,
+    session: &TypedSession,
+) -> Result<ConnectionPermit, Error> {
+    let ip = client_ip(req, &settings.trusted_proxies)
+        .map(|ip| ip.to_string())
+        .unwrap_or_default();
+    let client = client_id(session)?;
+    state
+        .connection_limiter
+        .connect(&ip, &client.to_string())
+        .map_err(|e| {
+            tracing::warn!(ip, error.message = %e, "Connection refused.");
+            state.metrics.rejected_connection();
+            match e {
+                ConnectionLimitError::TooManyConnections => e429(e),
+                ConnectionLimitError::ServerFull => e503(e),
+            }
+        })
+}
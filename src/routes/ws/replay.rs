@@ -0,0 +1,151 @@
+use super::{message::ClientMessage, ws};
+use crate::{
+    configuration::WSSettings, connection_limit::ConnectionPermit, history::RoomEventRecord,
+    state::AppState,
+};
+use actix::{Actor, ActorContext, AsyncContext, Handler, StreamHandler};
+use actix_web::web;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Read-only session that streams the events of a room as they happened,
+/// `speed` times faster
+pub struct ReplaySession {
+    id: Uuid,
+    hb: Instant,
+    state: web::Data<AppState>,
+    settings: WSSettings,
+    events: Vec<RoomEventRecord>,
+    speed: f64,
+    /// Counts the connection in the connection limits while the session is alive
+    _connection: ConnectionPermit,
+}
+
+impl ReplaySession {
+    pub fn new(
+        state: web::Data<AppState>,
+        settings: WSSettings,
+        events: Vec<RoomEventRecord>,
+        speed: f64,
+        connection: ConnectionPermit,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            hb: Instant::now(),
+            state,
+            settings,
+            events,
+            speed,
+            _connection: connection,
+        }
+    }
+
+    /// Sends ping to client every x seconds.
+    /// Also checks heartbeats from client.
+    fn hb(&self, ctx: &mut <Self as Actor>::Context) {
+        ctx.run_interval(self.settings.heartbeat_interval, |act, ctx| {
+            if Instant::now().duration_since(act.hb) > act.settings.client_timeout {
+                tracing::info!("Websocket client heartbeat failed, disconnecting.");
+                ctx.stop();
+            }
+            ctx.ping(b"");
+        });
+    }
+
+    /// Sends the event `idx` after its gap with the previous one, closes the session
+    /// once every event was sent
+    fn schedule(&self, idx: usize, ctx: &mut <Self as Actor>::Context) {
+        let gap = match (
+            idx.checked_sub(1).and_then(|i| self.events.get(i)),
+            self.events.get(idx),
+        ) {
+            (Some(previous), Some(next)) => (next.timestamp - previous.timestamp)
+                .to_std()
+                .unwrap_or_default()
+                .div_f64(self.speed),
+            _ => Duration::ZERO,
+        };
+        ctx.run_later(gap, move |act, ctx| match act.events.get(idx) {
+            Some(record) => {
+                act.send(ClientMessage::ReplayEvent(record.clone()), ctx);
+                act.schedule(idx + 1, ctx);
+            }
+            None => {
+                act.send(ClientMessage::ReplayFinished, ctx);
+                ctx.close(Some(ws::CloseCode::Normal.into()));
+                ctx.stop();
+            }
+        });
+    }
+
+    fn send(&self, msg: ClientMessage, ctx: &mut <Self as Actor>::Context) {
+        match serde_json::to_string(&msg) {
+            Ok(msg) => ctx.text(msg),
+            Err(e) => {
+                tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to send ClientMessage.")
+            }
+        }
+    }
+}
+
+impl Actor for ReplaySession {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.hb(ctx);
+        self.state
+            .sessions
+            .lock()
+            .unwrap()
+            .insert(self.id, ctx.address().recipient());
+        self.schedule(0, ctx);
+    }
+
+    fn stopped(&mut self, _ctx: &mut Self::Context) {
+        self.state.sessions.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ReplaySession {
+    fn handle(&mut self, item: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match item {
+            Ok(ws::Message::Ping(msg)) => {
+                self.hb = Instant::now();
+                ctx.pong(&msg);
+            }
+            Ok(ws::Message::Pong(_)) => {
+                self.hb = Instant::now();
+            }
+            Ok(ws::Message::Text(_)) => {
+                self.send(
+                    ClientMessage::Error("Replay sessions are read-only.".to_string()),
+                    ctx,
+                );
+            }
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            Ok(_) => {
+                tracing::info!("Invalid message");
+                ctx.stop();
+            }
+            Err(e) => {
+                tracing::error!(error.cause_chain =? e, error.message = %e, "Unexpected error.");
+                ctx.stop();
+            }
+        }
+    }
+}
+
+impl Handler<ClientMessage> for ReplaySession {
+    type Result = ();
+
+    fn handle(&mut self, msg: ClientMessage, ctx: &mut Self::Context) -> Self::Result {
+        self.send(msg.clone(), ctx);
+        if let ClientMessage::Disconnected(_) = msg {
+            ctx.close(Some(ws::CloseCode::Normal.into()));
+            ctx.stop();
+        }
+    }
+}
@@ -1,18 +1,35 @@
 use super::{
     error::WSError,
     message::{
-        ClientMessage, ConnectionType, CupColor, PublishQuestion, Question, QuestionAnswer,
-        QuestionId, QuestionModification, QuestionPublication, RoomConnectInfo, WSMessage,
+        ClientMessage, ConnectionType, CupTimelineRequest, PublicationMark, PublishQuestion,
+        Question, QuestionAnswer, QuestionId, QuestionModification, QuestionPublication,
+        RoomConnectInfo, ScheduleQuestion, ShareResults, SignalId, WSMessage,
     },
+    timer::QuestionTimer,
     ws,
 };
 use crate::{
+    audit::{AuditActor, AuditRole, RoomDiff},
     configuration::WSSettings,
-    state::{AppState, StudentInfo},
+    connection_limit::ConnectionPermit,
+    history::RoomEvent,
+    lti::LtiError,
+    oidc::Teacher,
+    rate_limit::TokenBucket,
+    state::{
+        AppState, RoomState, StateError, StudentInfo, TeacherInfo, DEFAULT_TIMELINE_BUCKET_SECS,
+    },
+    validation::ValidationError,
+    webhooks::WebhookEvent,
 };
 use actix::{Actor, ActorContext, Addr, AsyncContext, Handler, StreamHandler};
 use actix_web::web;
-use std::{str::FromStr, time::Instant};
+use chrono::{DateTime, Utc};
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 use uuid::Uuid;
 
 pub struct WSSession {
@@ -22,17 +39,73 @@ pub struct WSSession {
     state: web::Data<AppState>,
     settings: WSSettings,
     connection_type: Option<ConnectionType>,
+    /// Logged in teacher that opened the connection
+    teacher: Option<Teacher>,
+    /// Counts the connection in the connection limits while the session is alive
+    connection: ConnectionPermit,
+    rate_limit: TokenBucket,
+    /// Messages rejected in a row
+    rejected_messages: u32,
+}
+
+/// Room state a publication changes, to record it in the audit trail
+pub(super) struct PublishCapture {
+    publications: usize,
+    cup_history: usize,
+    playlist_position: Option<usize>,
+}
+
+impl PublishCapture {
+    pub(super) fn new(room_state: &RoomState) -> Self {
+        Self {
+            publications: room_state.publications.len(),
+            cup_history: room_state.cup_history.len(),
+            playlist_position: room_state.playlist_position,
+        }
+    }
+
+    pub(super) fn diff(&self, room_state: &RoomState) -> RoomDiff {
+        RoomDiff::default()
+            .appended(
+                "publications",
+                room_state
+                    .publications
+                    .get(self.publications..)
+                    .unwrap_or_default(),
+            )
+            .appended(
+                "cup_history",
+                room_state
+                    .cup_history
+                    .get(self.cup_history..)
+                    .unwrap_or_default(),
+            )
+            .change(
+                &["playlist_position"],
+                Some(&self.playlist_position),
+                Some(&room_state.playlist_position),
+            )
+    }
 }
 
 impl WSSession {
-    pub fn new(state: web::Data<AppState>, settings: WSSettings) -> Self {
+    pub fn new(
+        state: web::Data<AppState>,
+        settings: WSSettings,
+        teacher: Option<Teacher>,
+        connection: ConnectionPermit,
+    ) -> Self {
         Self {
             id: Uuid::new_v4(),
             hb: Instant::now(),
             room: None,
+            rate_limit: TokenBucket::new(&settings.session_rate_limit),
             state,
             settings,
             connection_type: None,
+            teacher,
+            connection,
+            rejected_messages: 0,
         }
     }
 
@@ -51,33 +124,113 @@ impl WSSession {
         });
     }
 
+    /// Checks the message size and rate limits, disconnects the client after
+    /// too many rejected messages in a row.
+    fn check_limits(&mut self, message: &str, ctx: &mut ws::WebsocketContext<WSSession>) -> bool {
+        let error = if message.len() > self.settings.max_message_size {
+            self.state.metrics.oversized_message();
+            WSError::MessageTooLarge(self.settings.max_message_size)
+        } else if !self.rate_limit.try_take()
+            || !self.state.ip_rate_limiter.try_take(self.connection.ip())
+        {
+            self.state.metrics.throttled_message();
+            WSError::RateLimited
+        } else {
+            self.rejected_messages = 0;
+            return true;
+        };
+        self.rejected_messages += 1;
+        if self.rejected_messages > self.settings.max_rejected_messages {
+            tracing::warn!(
+                ip = self.connection.ip(),
+                "Too many rejected messages, disconnecting."
+            );
+            self.state.metrics.throttled_disconnection();
+            ctx.close(Some(ws::CloseCode::Policy.into()));
+            ctx.stop();
+        } else {
+            tracing::info!(error.message = %error, "Message rejected.");
+            ctx.address().do_send(error.into());
+        }
+        false
+    }
+
     #[tracing::instrument(skip(self, ctx))]
     fn process_message(&mut self, message: &str, ctx: &mut ws::WebsocketContext<WSSession>) {
         let addr = ctx.address();
         match WSMessage::from_str(message) {
-            Ok(msg) => match msg {
-                WSMessage::RoomConnect(room_info) => {
-                    self.room_connect(room_info, addr);
-                }
-                WSMessage::ChooseCup(color) => {
-                    self.choose_cup(color, addr);
-                }
-                WSMessage::CreateQuestion(question) => {
-                    self.create_question(question, addr);
-                }
-                WSMessage::PublishQuestion(publish_question) => {
-                    self.publish_question(publish_question, addr);
-                }
-                WSMessage::DeleteQuestion(question_id) => {
-                    self.delete_question(question_id, addr);
-                }
-                WSMessage::ModifyQuestion(question_modification) => {
-                    self.modify_question(question_modification, addr);
+            Ok(msg) => {
+                if let Some(connection_type) = self.connection_type.filter(|&c| !msg.allowed_for(c))
+                {
+                    addr.do_send(WSError::TaskNotAllowed(connection_type).into());
+                    return;
                 }
-                WSMessage::AnswerQuestion(answer) => {
-                    self.answer_question(answer, addr);
+                match msg {
+                    WSMessage::RoomConnect(room_info) => {
+                        self.room_connect(room_info, addr);
+                    }
+                    WSMessage::ChooseCup(color) => {
+                        self.choose_cup(color, addr);
+                    }
+                    WSMessage::CreateQuestion(question) => {
+                        self.create_question(question, addr);
+                    }
+                    WSMessage::PublishQuestion(publish_question) => {
+                        self.publish_question(publish_question, addr);
+                    }
+                    WSMessage::DeleteQuestion(question_id) => {
+                        self.delete_question(question_id, addr);
+                    }
+                    WSMessage::ModifyQuestion(question_modification) => {
+                        self.modify_question(question_modification, addr);
+                    }
+                    WSMessage::AnswerQuestion(answer) => {
+                        self.answer_question(answer, addr);
+                    }
+                    WSMessage::SubmitGrades => {
+                        self.submit_grades(addr);
+                    }
+                    WSMessage::GetCupTimeline(request) => {
+                        self.get_cup_timeline(request, addr);
+                    }
+                    WSMessage::RaiseHand => {
+                        self.raise_hand(addr);
+                    }
+                    WSMessage::LowerHand => {
+                        self.lower_hand(addr);
+                    }
+                    WSMessage::CallOn(request) => {
+                        self.call_on(request.student, addr);
+                    }
+                    WSMessage::ClearHands => {
+                        self.clear_hands(addr);
+                    }
+                    WSMessage::ResetCups => {
+                        self.reset_cups(addr);
+                    }
+                    WSMessage::ShareResults(share_results) => {
+                        self.share_results(share_results, addr);
+                    }
+                    WSMessage::SetPlaylist(ids) => {
+                        self.set_playlist(ids, addr);
+                    }
+                    WSMessage::NextQuestion(step) => {
+                        self.playlist_step(true, step.secs, addr);
+                    }
+                    WSMessage::PreviousQuestion(step) => {
+                        self.playlist_step(false, step.secs, addr);
+                    }
+                    WSMessage::ScheduleQuestion(request) => {
+                        self.schedule_question(request, addr);
+                    }
+                    WSMessage::CancelSchedule(id) => {
+                        self.cancel_schedule(id, addr);
+                    }
+                    WSMessage::ReorderQuestions(ids) => {
+                        self.reorder_questions(ids, addr);
+                    }
                 }
-            },
+            }
             Err(e) => {
                 tracing::error!(error.cause_chain =? e, error.message = %e, "Failed to parse message.");
                 addr.do_send(e.into());
@@ -85,6 +238,24 @@ impl WSSession {
         }
     }
 
+    /// Records in the audit trail the changes that `task` of the session made to the room
+    fn audit(&self, room_state: &RoomState, task: &str, diff: RoomDiff) {
+        self.state
+            .audit
+            .record_diff(&room_state.name, &self.audit_actor(), task, diff);
+    }
+
+    fn audit_actor(&self) -> AuditActor {
+        AuditActor {
+            session: Some(self.id),
+            role: self
+                .connection_type
+                .map(AuditRole::from)
+                .unwrap_or(AuditRole::Student),
+            teacher: self.teacher.as_ref().map(|teacher| teacher.id.clone()),
+        }
+    }
+
     #[tracing::instrument(skip(self))]
     fn broadcast_message(&self, message: ClientMessage, connection_type: ConnectionType) {
         match &self.room {
@@ -94,16 +265,21 @@ impl WSSession {
                         ConnectionType::Student => room_state
                             .student_connections
                             .iter()
-                            .map(|(a, b)| (a, &b.connection))
+                            .map(|(a, b)| (a, &b.connection, ConnectionType::Student))
                             .collect::<Vec<_>>(),
-                        ConnectionType::Teacher => room_state.teacher_connections.iter().collect(),
+                        // Teachers, assistants and projectors get the same updates
+                        _ => room_state
+                            .teacher_connections
+                            .iter()
+                            .map(|(a, b)| (a, &b.connection, b.connection_type))
+                            .collect(),
                     };
 
                     connections
                         .into_iter()
-                        .filter(|&(id, _)| id != &self.id)
-                        .for_each(|(_, addr)| {
-                            addr.do_send(message.clone());
+                        .filter(|&(id, _, _)| id != &self.id)
+                        .for_each(|(_, addr, connection_type)| {
+                            addr.do_send(message.visible_to(connection_type));
                         })
                 }
                 None => {
@@ -122,10 +298,50 @@ impl WSSession {
         self.broadcast_message(message, ConnectionType::Student);
     }
 
+    /// Sends the students detail to teachers and assistants, if the room identity mode allows it
+    fn broadcast_students_detail(&self) {
+        if let Some(name) = &self.room {
+            if let Some(room_state) = self.state.rooms.lock().unwrap().get(name) {
+                if let Some(msg) = room_state.students_detail() {
+                    room_state
+                        .teacher_connections
+                        .values()
+                        .filter(|teacher| teacher.connection_type.manages_room())
+                        .for_each(|teacher| teacher.connection.do_send(msg.clone()));
+                }
+            }
+        }
+    }
+
+    /// Sends the raised hands queue to teachers and assistants, the position to the students on it
+    /// and `HandPosition(None)` to the `lowered` students
+    fn broadcast_hands(room_state: &RoomState, lowered: &[Uuid]) {
+        let msg = room_state.hands_queue();
+        room_state
+            .teacher_connections
+            .values()
+            .filter(|teacher| teacher.connection_type.manages_room())
+            .for_each(|teacher| teacher.connection.do_send(msg.clone()));
+        room_state
+            .raised_hands
+            .iter()
+            .map(|hand| hand.id)
+            .enumerate()
+            .map(|(i, id)| (id, Some(i + 1)))
+            .chain(lowered.iter().map(|&id| (id, None)))
+            .for_each(|(id, position)| {
+                if let Some(student) = room_state.student_connections.get(&id) {
+                    student
+                        .connection
+                        .do_send(ClientMessage::HandPosition(position));
+                }
+            });
+    }
+
     fn get_room_info(&self) -> ClientMessage {
         match &self.room {
             Some(name) => match self.state.rooms.lock().unwrap().get(name) {
-                Some(room_state) => ClientMessage::RoomInfo(room_state.clone().into()),
+                Some(room_state) => ClientMessage::RoomInfo(room_state.into()),
                 None => {
                     let e = WSError::InvalidRoom(name.clone());
                     tracing::warn!(error.message = %e);
@@ -141,35 +357,144 @@ impl WSSession {
     #[tracing::instrument(skip(self, addr))]
     fn room_connect(&mut self, room_info: RoomConnectInfo, addr: Addr<Self>) {
         let room_name = room_info.room_name;
-        self.room = Some(room_name.clone());
-        self.connection_type = Some(room_info.connection_type);
+        let lti_user = match room_info.lti_launch {
+            Some(launch_id) => {
+                let launch = self
+                    .state
+                    .lti
+                    .as_ref()
+                    .and_then(|lti| lti.take_launch(&launch_id))
+                    .filter(|launch| {
+                        launch.room == room_name
+                            && launch.connection_type == room_info.connection_type
+                    });
+                match launch {
+                    Some(launch) => Some(launch.user_id),
+                    None => {
+                        addr.do_send(WSError::InvalidLtiLaunch.into());
+                        return;
+                    }
+                }
+            }
+            None => {
+                // Teachers, assistants and projectors need to log in unless they come from an
+                // LTI launch
+                if room_info.connection_type.needs_teacher()
+                    && self.state.oidc.is_some()
+                    && self.teacher.is_none()
+                {
+                    addr.do_send(WSError::TeacherLoginRequired.into());
+                    return;
+                }
+                let allowed = !room_info.connection_type.needs_teacher()
+                    || self
+                        .state
+                        .rooms
+                        .lock()
+                        .unwrap()
+                        .get(&room_name)
+                        .map(|room_state| room_state.can_manage(self.teacher.as_ref()))
+                        .unwrap_or(true);
+                if !allowed {
+                    addr.do_send(WSError::NotAllowed(room_name).into());
+                    return;
+                }
+                None
+            }
+        };
+        if self.room.as_ref() == Some(&room_name) {
+            addr.do_send(WSError::AlreadyConnected.into());
+            return;
+        }
+        // Room the session moves from, left once it joins the new one
+        let mut previous = None;
         let msg = match self.state.rooms.lock().unwrap().get_mut(&room_name) {
             Some(room_state) => {
                 let added = match room_info.connection_type {
-                    ConnectionType::Student => room_state
-                        .student_connections
-                        .insert(self.id, StudentInfo::new(addr.clone().recipient()))
-                        .is_none(),
-                    ConnectionType::Teacher => room_state
+                    ConnectionType::Student => {
+                        let identity = match room_state
+                            .student_identity(room_info.display_name, room_info.student_id)
+                        {
+                            Ok(identity) => identity,
+                            Err(e) => {
+                                addr.do_send(WSError::from(e).into());
+                                return;
+                            }
+                        };
+                        room_state
+                            .student_connections
+                            .insert(
+                                self.id,
+                                StudentInfo::new(addr.clone().recipient(), lti_user, identity),
+                            )
+                            .is_none()
+                    }
+                    connection_type => room_state
                         .teacher_connections
-                        .insert(self.id, addr.clone().recipient())
+                        .insert(
+                            self.id,
+                            TeacherInfo::new(addr.clone().recipient(), connection_type),
+                        )
                         .is_none(),
                 };
-                if added {
+                if !added {
+                    Err(WSError::AlreadyConnected)
+                } else if !self.connection.join_room(&room_name) {
+                    room_state.student_connections.remove(&self.id);
+                    room_state.teacher_connections.remove(&self.id);
+                    Err(WSError::TooManyRooms(self.settings.max_rooms_per_client))
+                } else {
+                    previous = self
+                        .room
+                        .replace(room_name.clone())
+                        .zip(self.connection_type.replace(room_info.connection_type));
+                    room_state.record(match room_info.connection_type {
+                        ConnectionType::Student => RoomEvent::StudentJoined { id: self.id },
+                        connection_type => RoomEvent::TeacherJoined {
+                            id: self.id,
+                            connection_type,
+                        },
+                    });
                     match room_info.connection_type {
-                        ConnectionType::Student => Ok(None),
-                        ConnectionType::Teacher => Ok(Some(ClientMessage::from_questions_map(
-                            room_state.questions.clone(),
-                        ))),
+                        ConnectionType::Student => {
+                            self.state.webhooks.dispatch(
+                                &room_state.webhooks,
+                                WebhookEvent::StudentJoined {
+                                    room: room_name,
+                                    connections: room_state.student_connections.len(),
+                                },
+                            );
+                            Ok(None)
+                        }
+                        connection_type => {
+                            if connection_type.manages_room() {
+                                if let Some(students_detail) = room_state.students_detail() {
+                                    addr.do_send(students_detail);
+                                }
+                                if !room_state.raised_hands.is_empty() {
+                                    addr.do_send(room_state.hands_queue());
+                                }
+                                if !room_state.playlist.is_empty()
+                                    || !room_state.scheduled.is_empty()
+                                {
+                                    addr.do_send(room_state.playlist_info());
+                                }
+                            }
+                            Ok(Some(
+                                ClientMessage::from_questions_map(room_state.questions.clone())
+                                    .visible_to(connection_type),
+                            ))
+                        }
                     }
-                } else {
-                    Err(WSError::AlreadyConnected)
                 }
             }
             None => Err(WSError::InvalidRoom(room_name)),
         };
         match msg {
             Ok(question_info) => {
+                if let Some((previous_room, connection_type)) = previous {
+                    self.leave_room(&previous_room, connection_type);
+                }
                 // Send room info
                 let msg = self.get_room_info();
                 addr.do_send(msg.clone());
@@ -180,6 +505,7 @@ impl WSSession {
                 // Broadcast room info about new connection
                 if let ConnectionType::Student = room_info.connection_type {
                     self.broadcast_message(msg, ConnectionType::Teacher);
+                    self.broadcast_students_detail();
                 }
             }
             Err(msg) => {
@@ -188,13 +514,88 @@ impl WSSession {
         }
     }
 
+    /// Removes the session from the room, sending the room and question information to the rest of it
+    fn leave_room(&self, name: &str, connection_type: ConnectionType) {
+        let mut rooms = self.state.rooms.lock().unwrap();
+        let room_state = match rooms.get_mut(name) {
+            Some(room_state) => room_state,
+            None => {
+                tracing::warn!(error.message = %WSError::InvalidRoom(name.to_string()));
+                return;
+            }
+        };
+        // Removing connection
+        let removed = match connection_type {
+            ConnectionType::Student => {
+                let had_hand = room_state.hand_position(&self.id).is_some();
+                let removed = room_state.remove_student(&self.id).is_some();
+                if had_hand {
+                    Self::broadcast_hands(room_state, &[]);
+                }
+                removed
+            }
+            _ => {
+                let removed = room_state.teacher_connections.remove(&self.id).is_some();
+                if removed {
+                    room_state.record(RoomEvent::Left { id: self.id });
+                }
+                removed
+            }
+        };
+        // The room could have been replaced by another one with the same name
+        if !removed {
+            tracing::warn!(error.message = %WSError::InvalidRoom(name.to_string()), "Couldn't remove session.");
+            return;
+        }
+        // Removing answers
+        if let ConnectionType::Student = connection_type {
+            room_state
+                .questions
+                .values_mut()
+                .for_each(|question_state| {
+                    question_state.answers.remove(&self.id);
+                });
+            self.state.webhooks.dispatch(
+                &room_state.webhooks,
+                WebhookEvent::StudentLeft {
+                    room: name.to_string(),
+                    connections: room_state.student_connections.len(),
+                },
+            );
+            Self::cups_changed(&self.state, room_state);
+        }
+        // Send room information
+        room_state.broadcast(ClientMessage::RoomInfo((&*room_state).into()));
+        // Send question information
+        let question_info = ClientMessage::from_questions_map(room_state.questions.clone());
+        room_state.teacher_connections.values().for_each(|teacher| {
+            teacher
+                .connection
+                .do_send(question_info.visible_to(teacher.connection_type))
+        });
+        if let ConnectionType::Student = connection_type {
+            if let Some(students_detail) = room_state.students_detail() {
+                room_state.broadcast_managers(students_detail);
+            }
+        }
+    }
+
     /// Student chooses a cup color and broadcast information to teachers
     #[tracing::instrument(skip(self, addr))]
-    fn choose_cup(&mut self, color: CupColor, addr: Addr<Self>) {
+    fn choose_cup(&mut self, color: SignalId, addr: Addr<Self>) {
         let msg = match &self.room {
             Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
                 Some(room_state) => match room_state.choose_cup(&self.id, color) {
-                    Ok(_) => ClientMessage::Ok,
+                    Ok(selected_at) => {
+                        let diff = RoomDiff::default()
+                            .appended("cup_history", room_state.cup_history.last().as_slice());
+                        self.audit(room_state, "ChooseCup", diff);
+                        Self::cups_changed(&self.state, room_state);
+                        if let Some(minutes) = room_state.cup_reset.decay_minutes {
+                            self.schedule_cup_decay(room.clone(), minutes, selected_at);
+                        }
+                        ClientMessage::Ok
+                    }
                     Err(e) => WSError::from(e).into(),
                 },
                 None => WSError::InvalidRoom(room.clone()).into(),
@@ -203,6 +604,7 @@ impl WSSession {
         };
         addr.do_send(msg);
         self.broadcast_message(self.get_room_info(), ConnectionType::Teacher);
+        self.broadcast_students_detail();
     }
 
     /// Create a new question for the room
@@ -211,7 +613,24 @@ impl WSSession {
         let msg = match &self.room {
             Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
                 Some(room_state) => {
+                    let validation = self
+                        .state
+                        .validator
+                        .new_question(room_state.questions.len())
+                        .and_then(|_| self.state.validator.question(&question));
+                    if let Err(e) = validation {
+                        addr.do_send(WSError::from(e).into());
+                        return;
+                    }
                     room_state.add_question(question);
+                    if let Some((id, question)) = room_state.questions.last() {
+                        let diff = RoomDiff::default().change(
+                            &["questions", &id.to_string()],
+                            None,
+                            Some(question),
+                        );
+                        self.audit(room_state, "CreateQuestion", diff);
+                    }
                     ClientMessage::from_questions_map(room_state.questions.clone())
                 }
                 None => WSError::InvalidRoom(room.clone()).into(),
@@ -223,35 +642,249 @@ impl WSSession {
     }
 
     #[tracing::instrument(skip(self, addr))]
-    fn publish_question(&mut self, publish_question: PublishQuestion, addr: Addr<Self>) {
-        let id = publish_question.id;
+    fn reorder_questions(&mut self, ids: Vec<QuestionId>, addr: Addr<Self>) {
         let msg = match &self.room {
-            Some(room) => match self.state.rooms.lock().unwrap().get(room) {
-                Some(room_state) => match room_state.questions.get(&id.0) {
-                    Some(question) => ClientMessage::QuestionPublication(QuestionPublication {
-                        id,
-                        title: question.title.clone(),
-                        options: question.options.clone(),
-                        secs: publish_question.secs,
-                    }),
-                    None => WSError::InvalidQuestionId(id.0).into(),
-                },
+            Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
+                Some(room_state) => {
+                    let before = room_state.questions.keys().copied().collect::<Vec<_>>();
+                    match room_state.reorder_questions(ids.into_iter().map(|id| id.0).collect()) {
+                        Ok(()) => {
+                            let after = room_state.questions.keys().copied().collect::<Vec<_>>();
+                            let diff = RoomDiff::default().change(
+                                &["question_order"],
+                                Some(&before),
+                                Some(&after),
+                            );
+                            self.audit(room_state, "ReorderQuestions", diff);
+                            ClientMessage::from_questions_map(room_state.questions.clone())
+                        }
+                        Err(e) => {
+                            addr.do_send(WSError::from(e).into());
+                            return;
+                        }
+                    }
+                }
                 None => WSError::InvalidRoom(room.clone()).into(),
             },
             None => WSError::NoRoom.into(),
         };
         addr.do_send(msg.clone());
-        self.broadcast_all(msg);
+        self.broadcast_message(msg, ConnectionType::Teacher);
+    }
+
+    #[tracing::instrument(skip(self, addr))]
+    fn publish_question(&mut self, publish_question: PublishQuestion, addr: Addr<Self>) {
+        if let Err(e) = self.state.validator.publication_secs(publish_question.secs) {
+            addr.do_send(WSError::from(e).into());
+            return;
+        }
+        let result = match &self.room {
+            Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
+                Some(room_state) => {
+                    let before = PublishCapture::new(room_state);
+                    Self::publish(
+                        &self.state,
+                        room_state,
+                        publish_question.id.0,
+                        publish_question.secs,
+                    )
+                    .map(|_| self.audit(room_state, "PublishQuestion", before.diff(room_state)))
+                }
+                None => Err(WSError::InvalidRoom(room.clone())),
+            },
+            None => Err(WSError::NoRoom),
+        };
+        if let Err(e) = result {
+            addr.do_send(e.into());
+        }
+    }
+
+    /// Publishes the question to the whole room, moving the playlist to it if listed
+    pub(super) fn publish(
+        state: &web::Data<AppState>,
+        room_state: &mut RoomState,
+        id: Uuid,
+        secs: usize,
+    ) -> Result<(), WSError> {
+        let question = room_state
+            .questions
+            .get_mut(&id)
+            .ok_or(WSError::InvalidQuestionId(id))?;
+        question.publish(secs);
+        let publication = QuestionPublication {
+            id: QuestionId(id),
+            title: question.title.clone(),
+            options: question.options.clone(),
+            secs,
+        };
+        room_state.record(RoomEvent::QuestionPublished { id, secs });
+        room_state.publications.push(PublicationMark {
+            timestamp: Utc::now(),
+            id: publication.id.clone(),
+            title: publication.title.clone(),
+        });
+        state.webhooks.dispatch(
+            &room_state.webhooks,
+            WebhookEvent::QuestionPublished {
+                room: room_state.name.clone(),
+                id,
+                title: publication.title.clone(),
+                options: publication.options.clone(),
+                secs,
+            },
+        );
+        Self::schedule_question_close(state, room_state.name.clone(), id, secs);
+        room_state.broadcast(ClientMessage::QuestionPublication(publication));
+        // Cups are reset after the publication so clients get it first
+        if room_state.cup_reset.on_publish {
+            let cleared = room_state.reset_cups();
+            Self::cups_cleared(state, room_state, &cleared);
+        }
+        if room_state.mark_published(&id) {
+            room_state.broadcast_managers(room_state.playlist_info());
+        }
+        Ok(())
+    }
+
+    /// Closes the question once its publication time is over and sends the final answers to the webhooks
+    fn schedule_question_close(state: &web::Data<AppState>, room: String, id: Uuid, secs: usize) {
+        let state = state.clone();
+        actix_web::rt::spawn(async move {
+            actix_web::rt::time::sleep(Duration::from_secs(secs as u64)).await;
+            if let Some(room_state) = state.rooms.lock().unwrap().get_mut(&room) {
+                let question = match room_state.questions.get_mut(&id) {
+                    Some(question) => question,
+                    None => return,
+                };
+                if !question.close() {
+                    return;
+                }
+                let question = question.clone();
+                if let Some(mode) = room_state.auto_share_results {
+                    room_state
+                        .share_results(ClientMessage::QuestionResults(question.results(id, mode)));
+                }
+                state.webhooks.dispatch(
+                    &room_state.webhooks,
+                    WebhookEvent::QuestionClosed {
+                        room,
+                        id,
+                        title: question.title.clone(),
+                        options: question.options.clone(),
+                        summary: question.summary(),
+                    },
+                );
+                room_state.record(RoomEvent::QuestionClosed { id });
+            }
+        });
+    }
+
+    /// Clears the student cup once it is `minutes` old, unless it changed since `selected_at`
+    fn schedule_cup_decay(&self, room: String, minutes: u64, selected_at: DateTime<Utc>) {
+        let state = self.state.clone();
+        let id = self.id;
+        actix_web::rt::spawn(async move {
+            actix_web::rt::time::sleep(Duration::from_secs(minutes.saturating_mul(60))).await;
+            if let Some(room_state) = state.rooms.lock().unwrap().get_mut(&room) {
+                let unchanged = room_state
+                    .student_connections
+                    .get(&id)
+                    .map(|student| student.cup_selected_at == Some(selected_at))
+                    .unwrap_or(false);
+                if unchanged && room_state.clear_cup(&id) {
+                    Self::cups_cleared(&state, room_state, &[id]);
+                }
+            }
+        });
+    }
+
+    /// Lets the `cleared` students know their cup is gone and sends the new room information
+    fn cups_cleared(state: &web::Data<AppState>, room_state: &mut RoomState, cleared: &[Uuid]) {
+        cleared
+            .iter()
+            .filter_map(|id| room_state.student_connections.get(id))
+            .for_each(|student| student.connection.do_send(ClientMessage::CupCleared));
+        room_state.broadcast(ClientMessage::RoomInfo((&*room_state).into()));
+        if let Some(msg) = room_state.students_detail() {
+            room_state
+                .teacher_connections
+                .values()
+                .filter(|teacher| teacher.connection_type.manages_room())
+                .for_each(|teacher| teacher.connection.do_send(msg.clone()));
+        }
+        Self::cups_changed(state, room_state);
+    }
+
+    /// Checks the webhook threshold and the room alert rules after the cups changed
+    fn cups_changed(state: &web::Data<AppState>, room_state: &mut RoomState) {
+        Self::check_alert_cups(state, room_state);
+        Self::check_alert_rules(state, room_state);
+    }
+
+    /// Sends the triggered alerts to teachers and assistants, scheduling the next evaluation if needed
+    fn check_alert_rules(state: &web::Data<AppState>, room_state: &mut RoomState) {
+        let (alerts, recheck) = room_state.evaluate_alert_rules();
+        for alert in alerts {
+            let msg = ClientMessage::CupAlert(alert);
+            room_state
+                .teacher_connections
+                .values()
+                .filter(|teacher| teacher.connection_type.manages_room())
+                .for_each(|teacher| teacher.connection.do_send(msg.clone()));
+        }
+        if let Some(recheck) = recheck {
+            let state = state.clone();
+            let room = room_state.name.clone();
+            actix_web::rt::spawn(async move {
+                actix_web::rt::time::sleep(recheck).await;
+                if let Some(room_state) = state.rooms.lock().unwrap().get_mut(&room) {
+                    Self::check_alert_rules(&state, room_state);
+                }
+            });
+        }
+    }
+
+    /// Notifies the webhooks if the alert cups ratio crossed the configured threshold
+    fn check_alert_cups(state: &AppState, room_state: &mut RoomState) {
+        let threshold = state.webhooks.settings().red_cup_threshold;
+        if room_state.update_cups_alert(threshold) {
+            state.webhooks.dispatch(
+                &room_state.webhooks,
+                WebhookEvent::CupThresholdCrossed {
+                    room: room_state.name.clone(),
+                    connections: room_state.student_connections.len(),
+                    red: room_state.alert_cups(),
+                    threshold,
+                },
+            );
+        }
     }
 
     #[tracing::instrument(skip(self, addr))]
     fn delete_question(&mut self, question_id: QuestionId, addr: Addr<Self>) {
         let msg = match &self.room {
             Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
-                Some(room_state) => match room_state.questions.remove(&question_id.0) {
-                    Some(_) => ClientMessage::from_questions_map(room_state.questions.clone()),
-                    None => WSError::InvalidQuestionId(question_id.0).into(),
-                },
+                Some(room_state) => {
+                    let planned = room_state.is_planned(&question_id.0);
+                    let playlist = room_state.playlist.clone();
+                    match room_state.remove_question(&question_id.0) {
+                        Some(question) => {
+                            let diff = RoomDiff::default()
+                                .change(
+                                    &["questions", &question_id.0.to_string()],
+                                    Some(&question),
+                                    None,
+                                )
+                                .change(&["playlist"], Some(&playlist), Some(&room_state.playlist));
+                            self.audit(room_state, "DeleteQuestion", diff);
+                            if planned {
+                                room_state.broadcast_managers(room_state.playlist_info());
+                            }
+                            ClientMessage::from_questions_map(room_state.questions.clone())
+                        }
+                        None => WSError::InvalidQuestionId(question_id.0).into(),
+                    }
+                }
                 None => WSError::InvalidRoom(room.clone()).into(),
             },
             None => WSError::NoRoom.into(),
@@ -270,7 +903,26 @@ impl WSSession {
             Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
                 Some(room_state) => match room_state.questions.get_mut(&question_modification.id) {
                     Some(question) => {
-                        question.modify(question_modification.title, question_modification.options);
+                        if let Err(e) = self.validate_modification(&question_modification) {
+                            addr.do_send(WSError::from(e).into());
+                            return;
+                        }
+                        let before = question.clone();
+                        question.modify(
+                            question_modification.title.clone(),
+                            question_modification.options.clone(),
+                        );
+                        let diff = RoomDiff::default().change(
+                            &["questions", &question_modification.id.to_string()],
+                            Some(&before),
+                            Some(&*question),
+                        );
+                        room_state.record(RoomEvent::QuestionModified {
+                            id: question_modification.id,
+                            title: question_modification.title,
+                            options: question_modification.options,
+                        });
+                        self.audit(room_state, "ModifyQuestion", diff);
                         ClientMessage::from_questions_map(room_state.questions.clone())
                     }
                     None => WSError::InvalidQuestionId(question_modification.id).into(),
@@ -283,13 +935,44 @@ impl WSSession {
         self.broadcast_all(msg);
     }
 
+    fn validate_modification(
+        &self,
+        modification: &QuestionModification,
+    ) -> Result<(), ValidationError> {
+        if let Some(title) = &modification.title {
+            self.state.validator.title(title)?;
+        }
+        if let Some(options) = &modification.options {
+            self.state.validator.options(options)?;
+        }
+        Ok(())
+    }
+
     #[tracing::instrument(skip(self, addr))]
     fn answer_question(&mut self, answer: QuestionAnswer, addr: Addr<Self>) {
         let msg = match &self.room {
             Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
                 Some(room_state) => match room_state.questions.get_mut(&answer.id) {
                     Some(question) => match question.answer(self.id, answer.answer) {
-                        Ok(_) => ClientMessage::from_questions_map(room_state.questions.clone()),
+                        Ok(before) => {
+                            let diff = RoomDiff::default().change(
+                                &[
+                                    "questions",
+                                    &answer.id.to_string(),
+                                    "answers",
+                                    &self.id.to_string(),
+                                ],
+                                before.as_ref(),
+                                Some(&answer.answer),
+                            );
+                            self.audit(room_state, "AnswerQuestion", diff);
+                            room_state.record(RoomEvent::QuestionAnswered {
+                                id: answer.id,
+                                student: self.id,
+                                answer: answer.answer,
+                            });
+                            ClientMessage::from_questions_map(room_state.questions.clone())
+                        }
                         Err(e) => WSError::from(e).into(),
                     },
                     None => WSError::InvalidQuestionId(answer.id).into(),
@@ -300,53 +983,392 @@ impl WSSession {
         };
         addr.do_send(ClientMessage::Ok);
         self.broadcast_message(msg, ConnectionType::Teacher);
+        self.broadcast_students_detail();
     }
-}
 
-impl Actor for WSSession {
-    type Context = ws::WebsocketContext<Self>;
+    #[tracing::instrument(skip(self, addr))]
+    fn get_cup_timeline(&self, request: CupTimelineRequest, addr: Addr<Self>) {
+        let bucket_secs = request.bucket_secs.unwrap_or(DEFAULT_TIMELINE_BUCKET_SECS);
+        let msg = match &self.room {
+            Some(room) => match self.state.rooms.lock().unwrap().get(room) {
+                Some(room_state) => {
+                    ClientMessage::CupTimeline(room_state.cup_timeline(bucket_secs))
+                }
+                None => WSError::InvalidRoom(room.clone()).into(),
+            },
+            None => WSError::NoRoom.into(),
+        };
+        addr.do_send(msg);
+    }
 
-    fn started(&mut self, ctx: &mut Self::Context) {
-        self.hb(ctx);
+    #[tracing::instrument(skip(self, addr))]
+    fn raise_hand(&mut self, addr: Addr<Self>) {
+        let msg = match &self.room {
+            Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
+                Some(room_state) => match room_state.raise_hand(&self.id) {
+                    Ok(_) => {
+                        let diff = RoomDiff::default()
+                            .appended("raised_hands", room_state.raised_hands.back().as_slice());
+                        self.audit(room_state, "RaiseHand", diff);
+                        Self::broadcast_hands(room_state, &[]);
+                        return;
+                    }
+                    Err(e) => WSError::from(e).into(),
+                },
+                None => WSError::InvalidRoom(room.clone()).into(),
+            },
+            None => WSError::NoRoom.into(),
+        };
+        addr.do_send(msg);
     }
 
-    fn stopped(&mut self, _ctx: &mut Self::Context) {
-        let question_info = match &self.room {
-            Some(name) => match self.state.rooms.lock().unwrap().get_mut(name) {
+    #[tracing::instrument(skip(self, addr))]
+    fn lower_hand(&mut self, addr: Addr<Self>) {
+        let msg = match &self.room {
+            Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
                 Some(room_state) => {
-                    // Removing connection
-                    if room_state.student_connections.remove(&self.id).is_none() {
-                        tracing::warn!(error.message = %WSError::InvalidRoom(name.clone()), "Couldn't remove session.");
+                    let before = room_state.raised_hands.clone();
+                    if room_state.lower_hand(&self.id) {
+                        let diff = RoomDiff::default().change(
+                            &["raised_hands"],
+                            Some(&before),
+                            Some(&room_state.raised_hands),
+                        );
+                        self.audit(room_state, "LowerHand", diff);
+                        Self::broadcast_hands(room_state, &[self.id]);
+                        return;
                     }
-                    // Removing answers
-                    if let Some(ConnectionType::Student) = self.connection_type {
-                        room_state
-                            .questions
-                            .values_mut()
-                            .for_each(|question_state| {
-                                question_state.answers.remove(&self.id);
-                            });
+                    ClientMessage::HandPosition(None)
+                }
+                None => WSError::InvalidRoom(room.clone()).into(),
+            },
+            None => WSError::NoRoom.into(),
+        };
+        addr.do_send(msg);
+    }
+
+    /// Removes the student from the raised hands queue and lets it know it has the word
+    #[tracing::instrument(skip(self, addr))]
+    fn call_on(&mut self, student: Option<Uuid>, addr: Addr<Self>) {
+        let msg = match &self.room {
+            Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
+                Some(room_state) => {
+                    let before = room_state.raised_hands.clone();
+                    match room_state.call_on(student) {
+                        Ok(hand) => {
+                            let diff = RoomDiff::default().change(
+                                &["raised_hands"],
+                                Some(&before),
+                                Some(&room_state.raised_hands),
+                            );
+                            self.audit(room_state, "CallOn", diff);
+                            if let Some(student) = room_state.student_connections.get(&hand.id) {
+                                student.connection.do_send(ClientMessage::CalledOn);
+                            }
+                            Self::broadcast_hands(room_state, &[]);
+                            return;
+                        }
+                        Err(e) => WSError::from(e).into(),
                     }
-                    room_state.questions.clone()
                 }
-                None => {
-                    tracing::warn!(error.message = %WSError::InvalidRoom(name.clone()));
+                None => WSError::InvalidRoom(room.clone()).into(),
+            },
+            None => WSError::NoRoom.into(),
+        };
+        addr.do_send(msg);
+    }
+
+    #[tracing::instrument(skip(self, addr))]
+    fn clear_hands(&mut self, addr: Addr<Self>) {
+        let msg = match &self.room {
+            Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
+                Some(room_state) => {
+                    let before = room_state.raised_hands.clone();
+                    let lowered = room_state.clear_hands();
+                    let diff = RoomDiff::default().change(
+                        &["raised_hands"],
+                        Some(&before),
+                        Some(&room_state.raised_hands),
+                    );
+                    self.audit(room_state, "ClearHands", diff);
+                    Self::broadcast_hands(room_state, &lowered);
                     return;
                 }
+                None => WSError::InvalidRoom(room.clone()).into(),
             },
-            None => {
-                tracing::warn!(error.message = %WSError::NoRoom);
+            None => WSError::NoRoom.into(),
+        };
+        addr.do_send(msg);
+    }
+
+    #[tracing::instrument(skip(self, addr))]
+    fn reset_cups(&mut self, addr: Addr<Self>) {
+        let msg = match &self.room {
+            Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
+                Some(room_state) => {
+                    let history = room_state.cup_history.len();
+                    let cleared = room_state.reset_cups();
+                    let diff = RoomDiff::default().appended(
+                        "cup_history",
+                        room_state.cup_history.get(history..).unwrap_or_default(),
+                    );
+                    self.audit(room_state, "ResetCups", diff);
+                    Self::cups_cleared(&self.state, room_state, &cleared);
+                    return;
+                }
+                None => WSError::InvalidRoom(room.clone()).into(),
+            },
+            None => WSError::NoRoom.into(),
+        };
+        addr.do_send(msg);
+    }
+
+    #[tracing::instrument(skip(self, addr))]
+    fn share_results(&mut self, share_results: ShareResults, addr: Addr<Self>) {
+        let ShareResults { id, mode } = share_results;
+        let msg = match &self.room {
+            Some(room) => match self.state.rooms.lock().unwrap().get(room) {
+                Some(room_state) => match room_state.questions.get(&id.0) {
+                    Some(question) => {
+                        room_state.share_results(ClientMessage::QuestionResults(
+                            question.results(id.0, mode),
+                        ));
+                        ClientMessage::Ok
+                    }
+                    None => WSError::InvalidQuestionId(id.0).into(),
+                },
+                None => WSError::InvalidRoom(room.clone()).into(),
+            },
+            None => WSError::NoRoom.into(),
+        };
+        addr.do_send(msg);
+    }
+
+    #[tracing::instrument(skip(self, addr))]
+    fn set_playlist(&mut self, ids: Vec<QuestionId>, addr: Addr<Self>) {
+        let result = match &self.room {
+            Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
+                Some(room_state) => {
+                    let playlist = room_state.playlist.clone();
+                    let position = room_state.playlist_position;
+                    room_state
+                        .set_playlist(ids.into_iter().map(|id| id.0).collect())
+                        .map(|_| {
+                            let diff = RoomDiff::default()
+                                .change(&["playlist"], Some(&playlist), Some(&room_state.playlist))
+                                .change(
+                                    &["playlist_position"],
+                                    Some(&position),
+                                    Some(&room_state.playlist_position),
+                                );
+                            self.audit(room_state, "SetPlaylist", diff);
+                            room_state.broadcast_managers(room_state.playlist_info())
+                        })
+                        .map_err(WSError::from)
+                }
+                None => Err(WSError::InvalidRoom(room.clone())),
+            },
+            None => Err(WSError::NoRoom),
+        };
+        if let Err(e) = result {
+            addr.do_send(e.into());
+        }
+    }
+
+    /// Publishes the next question of the playlist, or the previous one if not `forward`
+    #[tracing::instrument(skip(self, addr))]
+    fn playlist_step(&mut self, forward: bool, secs: usize, addr: Addr<Self>) {
+        if let Err(e) = self.state.validator.publication_secs(secs) {
+            addr.do_send(WSError::from(e).into());
+            return;
+        }
+        let result = match &self.room {
+            Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
+                Some(room_state) => {
+                    let before = PublishCapture::new(room_state);
+                    let task = if forward {
+                        "NextQuestion"
+                    } else {
+                        "PreviousQuestion"
+                    };
+                    room_state
+                        .playlist_step(forward)
+                        .map_err(WSError::from)
+                        .and_then(|id| Self::publish(&self.state, room_state, id, secs))
+                        .map(|_| self.audit(room_state, task, before.diff(room_state)))
+                }
+                None => Err(WSError::InvalidRoom(room.clone())),
+            },
+            None => Err(WSError::NoRoom),
+        };
+        if let Err(e) = result {
+            addr.do_send(e.into());
+        }
+    }
+
+    /// Schedules the publication and starts its timer
+    #[tracing::instrument(skip(self, addr))]
+    fn schedule_question(&mut self, request: ScheduleQuestion, addr: Addr<Self>) {
+        if let Err(e) = self.state.validator.publication_secs(request.secs) {
+            addr.do_send(WSError::from(e).into());
+            return;
+        }
+        let at = match (request.at, request.delay_secs) {
+            (Some(at), None) => Ok(at),
+            (None, Some(delay_secs)) => i64::try_from(delay_secs)
+                .ok()
+                .and_then(chrono::Duration::try_seconds)
+                .and_then(|delay| Utc::now().checked_add_signed(delay))
+                .ok_or_else(|| "`delay_secs` is too big".to_string()),
+            _ => Err("give either `at` or `delay_secs`".to_string()),
+        };
+        let at = match at {
+            Ok(at) => at,
+            Err(e) => {
+                addr.do_send(WSError::from(StateError::InvalidSchedule(e)).into());
                 return;
             }
         };
-        // Send room information
-        let msg = self.get_room_info();
-        self.broadcast_all(msg);
-        // Send question information
-        self.broadcast_message(
-            ClientMessage::from_questions_map(question_info),
-            ConnectionType::Teacher,
-        );
+        let result = match &self.room {
+            Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
+                Some(room_state) => {
+                    let before = room_state.scheduled.clone();
+                    room_state
+                        .schedule_question(request.id.0, at, request.secs)
+                        .map(|scheduled| {
+                            let diff = RoomDiff::default().change(
+                                &["scheduled"],
+                                Some(&before),
+                                Some(&room_state.scheduled),
+                            );
+                            self.audit(room_state, "ScheduleQuestion", diff);
+                            QuestionTimer::new(self.state.clone(), room.clone(), scheduled).start();
+                            room_state.broadcast_managers(room_state.playlist_info());
+                        })
+                        .map_err(WSError::from)
+                }
+                None => Err(WSError::InvalidRoom(room.clone())),
+            },
+            None => Err(WSError::NoRoom),
+        };
+        if let Err(e) = result {
+            addr.do_send(e.into());
+        }
+    }
+
+    #[tracing::instrument(skip(self, addr))]
+    fn cancel_schedule(&mut self, id: QuestionId, addr: Addr<Self>) {
+        let result = match &self.room {
+            Some(room) => match self.state.rooms.lock().unwrap().get_mut(room) {
+                Some(room_state) => {
+                    let before = room_state.scheduled.clone();
+                    if room_state.unschedule_question(&id.0) {
+                        let diff = RoomDiff::default().change(
+                            &["scheduled"],
+                            Some(&before),
+                            Some(&room_state.scheduled),
+                        );
+                        self.audit(room_state, "CancelSchedule", diff);
+                        room_state.broadcast_managers(room_state.playlist_info());
+                        Ok(())
+                    } else {
+                        Err(StateError::InvalidSchedule(format!(
+                            "question {} is not scheduled",
+                            id.0
+                        ))
+                        .into())
+                    }
+                }
+                None => Err(WSError::InvalidRoom(room.clone())),
+            },
+            None => Err(WSError::NoRoom),
+        };
+        if let Err(e) = result {
+            addr.do_send(e.into());
+        }
+    }
+
+    /// Sends the score of the students connected from LTI launches to the room line item
+    #[tracing::instrument(skip(self, addr))]
+    fn submit_grades(&mut self, addr: Addr<Self>) {
+        let grades = match &self.room {
+            Some(room) => match self.state.rooms.lock().unwrap().get(room) {
+                Some(room_state) => {
+                    match (
+                        room_state.lti.as_ref().and_then(|lti| lti.lineitem.clone()),
+                        room_state.student_scores(),
+                    ) {
+                        (None, _) => Err(WSError::NoLtiLineItem),
+                        (_, None) => Err(WSError::NoGradableQuestions),
+                        (Some(lineitem), Some(scores)) => {
+                            let scores = scores
+                                .into_iter()
+                                .filter_map(|(id, score)| {
+                                    room_state
+                                        .student_connections
+                                        .get(&id)
+                                        .and_then(|student| student.lti_user.clone())
+                                        .map(|user_id| (user_id, score))
+                                })
+                                .collect::<Vec<_>>();
+                            Ok((lineitem, scores))
+                        }
+                    }
+                }
+                None => Err(WSError::InvalidRoom(room.clone())),
+            },
+            None => Err(WSError::NoRoom),
+        };
+        let (lineitem, scores) = match grades {
+            Ok(grades) => grades,
+            Err(e) => {
+                addr.do_send(e.into());
+                return;
+            }
+        };
+        let state = self.state.clone();
+        let room = self.room.clone().unwrap_or_default();
+        let actor = self.audit_actor();
+        actix_web::rt::spawn(async move {
+            let grades = scores.iter().cloned().collect::<BTreeMap<_, _>>();
+            let result = match &state.lti {
+                Some(lti) => lti.send_scores(&lineitem, scores).await,
+                None => Err(LtiError::NotConfigured),
+            };
+            let msg = match result {
+                Ok(()) => {
+                    let diff = RoomDiff::default().change(&["grades"], None, Some(&grades));
+                    state.audit.record_diff(&room, &actor, "SubmitGrades", diff);
+                    ClientMessage::Ok
+                }
+                Err(e) => {
+                    tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to send grades.");
+                    WSError::from(e).into()
+                }
+            };
+            addr.do_send(msg);
+        });
+    }
+}
+
+impl Actor for WSSession {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.hb(ctx);
+        self.state
+            .sessions
+            .lock()
+            .unwrap()
+            .insert(self.id, ctx.address().recipient());
+    }
+
+    fn stopped(&mut self, _ctx: &mut Self::Context) {
+        self.state.sessions.lock().unwrap().remove(&self.id);
+        match (&self.room, self.connection_type) {
+            (Some(name), Some(connection_type)) => self.leave_room(name, connection_type),
+            _ => tracing::warn!(error.message = %WSError::NoRoom),
+        }
     }
 }
 
@@ -365,7 +1387,7 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WSSession {
                 return;
             }
         };
-        tracing::Span::current().record("message", &tracing::field::debug(&msg));
+        tracing::Span::current().record("message", tracing::field::debug(&msg));
 
         match msg {
             ws::Message::Ping(msg) => {
@@ -375,7 +1397,11 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WSSession {
             ws::Message::Pong(_) => {
                 self.hb = Instant::now();
             }
-            ws::Message::Text(text) => self.process_message(text.trim(), ctx),
+            ws::Message::Text(text) => {
+                if self.check_limits(&text, ctx) {
+                    self.process_message(text.trim(), ctx);
+                }
+            }
             ws::Message::Close(reason) => {
                 ctx.close(reason);
                 ctx.stop();
@@ -398,5 +1424,9 @@ impl Handler<ClientMessage> for WSSession {
                 tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to send ClientMessage.")
             }
         }
+        if let ClientMessage::Disconnected(_) = msg {
+            ctx.close(Some(ws::CloseCode::Normal.into()));
+            ctx.stop();
+        }
     }
 }
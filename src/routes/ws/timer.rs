@@ -0,0 +1,72 @@
+//! Scheduled question publications
+
+use super::{
+    message::ScheduledQuestion,
+    session::{PublishCapture, WSSession},
+};
+use crate::{audit::AuditActor, state::AppState};
+use actix::{Actor, ActorContext, AsyncContext, Context};
+use actix_web::web;
+use chrono::Utc;
+
+/// Publishes a scheduled question once its time comes, unless it was cancelled or rescheduled
+pub struct QuestionTimer {
+    state: web::Data<AppState>,
+    room: String,
+    scheduled: ScheduledQuestion,
+}
+
+impl QuestionTimer {
+    pub fn new(state: web::Data<AppState>, room: String, scheduled: ScheduledQuestion) -> Self {
+        Self {
+            state,
+            room,
+            scheduled,
+        }
+    }
+
+    #[tracing::instrument(name = "Publishing scheduled question", skip(self), fields(room = %self.room))]
+    fn publish(&self) {
+        let id = self.scheduled.id.0;
+        if let Some(room_state) = self.state.rooms.lock().unwrap().get_mut(&self.room) {
+            let scheduled = room_state.scheduled.clone();
+            if room_state.take_scheduled(&id, self.scheduled.at).is_none() {
+                return;
+            }
+            let before = PublishCapture::new(room_state);
+            let position = room_state.playlist_position;
+            if let Err(e) = WSSession::publish(&self.state, room_state, id, self.scheduled.secs) {
+                tracing::warn!(error.message = %e, "Failed to publish scheduled question.");
+            }
+            let diff = before.diff(room_state).change(
+                &["scheduled"],
+                Some(&scheduled),
+                Some(&room_state.scheduled),
+            );
+            self.state.audit.record_diff(
+                &room_state.name,
+                &AuditActor::system(),
+                "ScheduledPublication",
+                diff,
+            );
+            // The playlist was already sent if the position moved
+            if room_state.playlist_position == position {
+                room_state.broadcast_managers(room_state.playlist_info());
+            }
+        }
+    }
+}
+
+impl Actor for QuestionTimer {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let delay = (self.scheduled.at - Utc::now())
+            .to_std()
+            .unwrap_or_default();
+        ctx.run_later(delay, |act, ctx| {
+            act.publish();
+            ctx.stop();
+        });
+    }
+}
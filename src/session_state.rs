@@ -0,0 +1,93 @@
+use crate::{
+    oidc::{LoginAttempt, Teacher},
+    state::AppState,
+    utils::e500,
+};
+use actix_session::{Session, SessionExt, SessionGetError, SessionInsertError};
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use std::future::{ready, Ready};
+use uuid::Uuid;
+
+pub struct TypedSession(Session);
+
+impl TypedSession {
+    const TEACHER_KEY: &'static str = "teacher";
+    const LOGIN_ATTEMPT_KEY: &'static str = "login_attempt";
+    const CLIENT_ID_KEY: &'static str = "client_id";
+
+    pub fn renew(&self) {
+        self.0.renew();
+    }
+
+    pub fn insert_teacher(&self, teacher: &Teacher) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::TEACHER_KEY, teacher)
+    }
+
+    pub fn get_teacher(&self) -> Result<Option<Teacher>, SessionGetError> {
+        self.0.get(Self::TEACHER_KEY)
+    }
+
+    pub fn insert_login_attempt(&self, attempt: &LoginAttempt) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::LOGIN_ATTEMPT_KEY, attempt)
+    }
+
+    /// Removes the login attempt from the session, it can only be used once
+    pub fn take_login_attempt(&self) -> Result<Option<LoginAttempt>, SessionGetError> {
+        let attempt = self.0.get(Self::LOGIN_ATTEMPT_KEY)?;
+        self.0.remove(Self::LOGIN_ATTEMPT_KEY);
+        Ok(attempt)
+    }
+
+    /// Id of the browser, to count the rooms of its websocket connections
+    pub fn get_client_id(&self) -> Result<Option<Uuid>, SessionGetError> {
+        self.0.get(Self::CLIENT_ID_KEY)
+    }
+
+    pub fn insert_client_id(&self, client_id: Uuid) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::CLIENT_ID_KEY, client_id)
+    }
+
+    pub fn log_out(self) {
+        self.0.purge()
+    }
+}
+
+impl FromRequest for TypedSession {
+    type Error = <Session as FromRequest>::Error;
+    type Future = Ready<Result<TypedSession, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(TypedSession(req.get_session())))
+    }
+}
+
+/// Teacher making the request.
+/// Rejects the request if the login is configured and the teacher is not logged in,
+/// contains `None` if the login is not configured.
+#[derive(Debug)]
+pub struct TeacherSession(pub Option<Teacher>);
+
+impl FromRequest for TeacherSession {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<TeacherSession, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let login_required = req
+            .app_data::<web::Data<AppState>>()
+            .map(|state| state.oidc.is_some())
+            .unwrap_or(false);
+        if !login_required {
+            return ready(Ok(TeacherSession(None)));
+        }
+        let teacher = match TypedSession(req.get_session()).get_teacher() {
+            Ok(teacher) => teacher,
+            Err(e) => return ready(Err(e500(e).into())),
+        };
+        ready(match teacher {
+            Some(teacher) => Ok(TeacherSession(Some(teacher))),
+            None => Err(actix_web::error::ErrorUnauthorized(
+                "Teacher login required.",
+            )),
+        })
+    }
+}
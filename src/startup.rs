@@ -1,65 +1,341 @@
 use crate::{
-    configuration::{Settings, WSSettings},
-    routes::{create_room, delete_room, get_cups_info, health_check_route, ws},
+    configuration::{AllowedOrigins, Settings, ShutdownSettings},
+    routes::{
+        add_co_owner, admin_announcement, admin_close_room, admin_disconnect_session, admin_room,
+        admin_rooms, clone_room, create_room, create_webhook, delete_room, delete_template,
+        delete_webhook, get_audit_trail, get_cup_timeline, get_cups_info, get_metrics,
+        get_room_history, get_room_snapshot, get_teacher, get_templates, health_check_route, login,
+        login_callback, logout, lti_launch, lti_login_form, lti_login_query, remove_co_owner,
+        replay, restore_room, save_template, set_alert_rules, set_auto_share_results,
+        set_cup_reset, set_identity_mode, upload_roster, ws,
+    },
     state::AppState,
+    tls::ReloadableCertificate,
+};
+use actix_cors::Cors;
+use actix_session::{
+    config::CookieContentSecurity, storage::CookieSessionStore, SessionMiddleware,
+};
+use actix_web::{
+    cookie::Key, dev::Server, http::header, web, App, HttpRequest, HttpResponse, HttpServer,
 };
-use actix_web::{dev::Server, web, App, HttpServer};
 use anyhow::Result;
-use std::net::TcpListener;
+use rustls::ServerConfig;
+use secrecy::ExposeSecret;
+use std::{net::TcpListener, sync::Arc, time::Duration};
+use tokio::sync::Notify;
 use tracing_actix_web::TracingLogger;
 
 pub struct Application {
     port: u16,
     server: Server,
+    /// Http listener that redirects to https
+    redirect: Option<(u16, Server)>,
+    state: web::Data<AppState>,
+    shutdown_settings: ShutdownSettings,
+    shutdown: ShutdownTrigger,
+}
+
+/// Starts the shutdown sequence of the application, as a SIGTERM would
+#[derive(Clone, Debug, Default)]
+pub struct ShutdownTrigger(Arc<Notify>);
+
+impl ShutdownTrigger {
+    pub fn shutdown(&self) {
+        self.0.notify_one();
+    }
+
+    async fn wait(&self) {
+        #[cfg(unix)]
+        let terminate = async {
+            use tokio::signal::unix::{signal, SignalKind};
+            match signal(SignalKind::terminate()) {
+                Ok(mut terminate) => {
+                    terminate.recv().await;
+                }
+                Err(e) => {
+                    tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to listen to SIGTERM.");
+                    std::future::pending::<()>().await;
+                }
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate => {}
+            _ = self.0.notified() => {}
+        }
+    }
 }
 
 impl Application {
     pub async fn build(configuration: Settings) -> Result<Self> {
+        configuration.validate()?;
         let address = format!(
             "{}:{}",
             configuration.application.host, configuration.application.port
         );
         let listener = TcpListener::bind(&address)?;
         let port = listener.local_addr()?.port();
-        let server = run(listener, configuration.websocket).await?;
-        Ok(Self { port, server })
+        let (tls_config, redirect) = match &configuration.application.tls {
+            Some(tls) => {
+                let certificate = Arc::new(ReloadableCertificate::load(tls.clone())?);
+                #[cfg(unix)]
+                certificate.reload_on_sighup()?;
+                let redirect = match tls.redirect_port {
+                    Some(redirect_port) => {
+                        let redirect_listener = TcpListener::bind(format!(
+                            "{}:{}",
+                            configuration.application.host, redirect_port
+                        ))?;
+                        let redirect_port = redirect_listener.local_addr()?.port();
+                        Some((redirect_port, run_https_redirect(redirect_listener, port)?))
+                    }
+                    None => None,
+                };
+                (Some(certificate.server_config()), redirect)
+            }
+            None => (None, None),
+        };
+        let state = web::Data::new(AppState::new(&configuration)?);
+        let loaded = state.load_stored()?;
+        if loaded > 0 {
+            tracing::info!("Loaded {} rooms from storage.", loaded);
+        }
+        let shutdown_settings = configuration.application.shutdown.clone();
+        let server = run(listener, tls_config, state.clone(), configuration).await?;
+        Ok(Self {
+            port,
+            server,
+            redirect,
+            state,
+            shutdown_settings,
+            shutdown: ShutdownTrigger::default(),
+        })
     }
 
     pub fn port(&self) -> u16 {
         self.port
     }
 
+    pub fn redirect_port(&self) -> Option<u16> {
+        self.redirect.as_ref().map(|(port, _)| *port)
+    }
+
+    pub fn shutdown_trigger(&self) -> ShutdownTrigger {
+        self.shutdown.clone()
+    }
+
+    /// Runs until SIGTERM, Ctrl-C or the shutdown trigger, then notifies the clients,
+    /// saves the rooms and gives the clients some time to disconnect.
     pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
         tracing::info!("Server running on port: {}", self.port);
-        self.server.await
+        let redirect_handle = self.redirect.map(|(port, redirect)| {
+            tracing::info!("Redirecting http to https from port: {}", port);
+            let handle = redirect.handle();
+            tokio::spawn(redirect);
+            handle
+        });
+        let server_handle = self.server.handle();
+        let mut server = tokio::spawn(self.server);
+        let result = tokio::select! {
+            result = &mut server => result,
+            _ = self.shutdown.wait() => {
+                tracing::info!("Shutting down.");
+                // Stop accepting connections
+                server_handle.pause().await;
+                self.state
+                    .broadcast_shutdown(self.shutdown_settings.reconnect_after);
+                if let Err(e) = self.state.save() {
+                    tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to save the rooms.");
+                }
+                drain(&self.state, self.shutdown_settings.drain_timeout).await;
+                server_handle.stop(false).await;
+                server.await
+            }
+        };
+        if let Some(handle) = redirect_handle {
+            handle.stop(true).await;
+        }
+        result.map_err(std::io::Error::other)?
     }
 }
 
+/// Waits until every websocket is closed or the timeout is over
+async fn drain(state: &AppState, timeout: Duration) {
+    let drained = tokio::time::timeout(timeout, async {
+        while state.connection_limiter.connections() > 0 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await;
+    if drained.is_err() {
+        tracing::warn!(
+            "Closing {} connections after the drain timeout.",
+            state.connection_limiter.connections()
+        );
+    }
+}
+
+/// Server that redirects every request to the https server on `https_port`
+fn run_https_redirect(listener: TcpListener, https_port: u16) -> Result<Server> {
+    let server = HttpServer::new(move || {
+        App::new()
+            .wrap(TracingLogger::default())
+            .default_service(web::to(move |req: HttpRequest| async move {
+                let connection_info = req.connection_info();
+                let host = connection_info.host();
+                // Leave out the port of the http listener
+                let host = match host.rsplit_once(':') {
+                    Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+                    _ => host,
+                };
+                let path = req
+                    .uri()
+                    .path_and_query()
+                    .map(|path| path.as_str())
+                    .unwrap_or("/");
+                let location = match https_port {
+                    443 => format!("https://{host}{path}"),
+                    port => format!("https://{host}:{port}{path}"),
+                };
+                HttpResponse::PermanentRedirect()
+                    .insert_header((header::LOCATION, location))
+                    .finish()
+            }))
+    })
+    .listen(listener)?
+    .run();
+    Ok(server)
+}
+
 /// Index for the single web app
 async fn spa_index() -> actix_files::NamedFile {
     actix_files::NamedFile::open("./frontend/dist/index.html").unwrap()
 }
 
-pub async fn run(listener: TcpListener, websocket_settings: WSSettings) -> Result<Server> {
-    let websocket_settings = web::Data::new(websocket_settings);
-    let app_state = web::Data::new(AppState::default());
+/// CORS policy for the API routes, the LTI routes are left out as the platforms post to them
+fn cors(allowed_origins: &AllowedOrigins) -> Cors {
+    let cors = Cors::default()
+        .allow_any_method()
+        .allow_any_header()
+        .max_age(3600);
+    if allowed_origins.allows_any() {
+        return cors.allow_any_origin();
+    }
+    let allowed_origins = allowed_origins.clone();
+    // Rejects the requests instead of only leaving out the CORS headers
+    cors.supports_credentials()
+        .block_on_origin_mismatch(true)
+        .allowed_origin_fn(move |origin, request| {
+            let host = request
+                .headers()
+                .get(header::HOST)
+                .and_then(|host| host.to_str().ok());
+            origin
+                .to_str()
+                .map(|origin| allowed_origins.is_allowed(origin, host))
+                .unwrap_or(false)
+        })
+}
+
+pub async fn run(
+    listener: TcpListener,
+    tls_config: Option<ServerConfig>,
+    app_state: web::Data<AppState>,
+    configuration: Settings,
+) -> Result<Server> {
+    let websocket_settings = web::Data::new(configuration.websocket);
+    let allowed_origins = web::Data::new(configuration.application.allowed_origins);
+    let secret_key = match &configuration.application.session_key {
+        Some(session_key) => Key::derive_from(session_key.expose_secret().as_bytes()),
+        None => {
+            tracing::warn!("No session key set, the sessions won't survive a restart.");
+            Key::generate()
+        }
+    };
+    let secure_cookies = configuration.application.secure_cookies;
     let server = HttpServer::new(move || {
         App::new()
+            .wrap(
+                SessionMiddleware::builder(CookieSessionStore::default(), secret_key.clone())
+                    .cookie_content_security(CookieContentSecurity::Private)
+                    .cookie_secure(secure_cookies)
+                    .build(),
+            )
             .wrap(TracingLogger::default())
             .route("/health_check", web::get().to(health_check_route))
+            .route("/metrics", web::get().to(get_metrics))
             .route("/ws", web::get().to(ws))
             .service(
                 web::scope("/cups")
+                    .wrap(cors(&allowed_origins))
                     .route("", web::get().to(get_cups_info))
                     .route("/create_room", web::post().to(create_room))
-                    .route("/delete_room", web::delete().to(delete_room)),
+                    .route("/delete_room", web::delete().to(delete_room))
+                    .route("/create_webhook", web::post().to(create_webhook))
+                    .route("/delete_webhook", web::delete().to(delete_webhook))
+                    .route("/add_co_owner", web::post().to(add_co_owner))
+                    .route("/remove_co_owner", web::delete().to(remove_co_owner))
+                    .route("/identity_mode", web::post().to(set_identity_mode))
+                    .route("/cup_reset", web::post().to(set_cup_reset))
+                    .route("/alert_rules", web::post().to(set_alert_rules))
+                    .route(
+                        "/auto_share_results",
+                        web::post().to(set_auto_share_results),
+                    )
+                    .route("/upload_roster", web::post().to(upload_roster))
+                    .route("/timeline", web::get().to(get_cup_timeline))
+                    .route("/templates", web::get().to(get_templates))
+                    .route("/templates", web::post().to(save_template))
+                    .route("/templates", web::delete().to(delete_template))
+                    .route("/restore", web::post().to(restore_room))
+                    .route("/{room}/clone", web::post().to(clone_room))
+                    .route("/{room}/audit", web::get().to(get_audit_trail))
+                    .route("/{room}/history", web::get().to(get_room_history))
+                    .route("/{room}/snapshot", web::get().to(get_room_snapshot))
+                    .route("/{room}/replay", web::get().to(replay)),
+            )
+            .service(
+                web::scope("/auth")
+                    .wrap(cors(&allowed_origins))
+                    .route("/login", web::get().to(login))
+                    .route("/callback", web::get().to(login_callback))
+                    .route("/logout", web::post().to(logout))
+                    .route("/teacher", web::get().to(get_teacher)),
+            )
+            .service(
+                web::scope("/admin")
+                    .route("/rooms", web::get().to(admin_rooms))
+                    .route("/rooms/{room}", web::get().to(admin_room))
+                    .route("/rooms/{room}", web::delete().to(admin_close_room))
+                    .route(
+                        "/sessions/{session}",
+                        web::delete().to(admin_disconnect_session),
+                    )
+                    .route("/announcement", web::post().to(admin_announcement)),
+            )
+            .service(
+                web::scope("/lti")
+                    .route("/login", web::get().to(lti_login_query))
+                    .route("/login", web::post().to(lti_login_form))
+                    .route("/launch", web::post().to(lti_launch)),
             )
             .service(actix_files::Files::new("/", "./frontend/dist").index_file("index.html"))
             .default_service(web::get().to(spa_index))
             .app_data(websocket_settings.clone())
+            .app_data(allowed_origins.clone())
             .app_data(app_state.clone()) //
     })
-    .listen(listener)?
+    // Signals are handled by `Application::run_until_stopped`
+    .disable_signals()
+    // Close the websockets as soon as their session stops instead of waiting for the client
+    .client_disconnect_timeout(Duration::ZERO);
+    let server = match tls_config {
+        Some(tls_config) => server.listen_rustls_0_21(listener, tls_config)?,
+        None => server.listen(listener)?,
+    }
     .run();
     Ok(server)
 }
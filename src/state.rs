@@ -1,18 +1,68 @@
-use crate::routes::message::{ClientMessage, Question};
-use crate::{error_chain_fmt, routes::message::CupColor};
+use crate::audit::{AuditError, AuditLog};
+use crate::configuration::Settings;
+use crate::connection_limit::ConnectionLimiter;
+use crate::history::{RoomEvent, RoomEventRecord, RoomHistory, RoomView};
+use crate::lti::{Lti, LtiRoom};
+use crate::oidc::{Oidc, Teacher};
+use crate::persistence::{PersistenceError, RoomStore, StoredState};
+use crate::rate_limit::IpRateLimiter;
+use crate::routes::message::{
+    ClientMessage, ConnectionType, CupAlert, CupAlertCondition, CupAlertRule, CupBucket,
+    CupResetOptions, CupSignal, CupTimeline, IdentityMode, PlaylistInfo, PublicationMark, Question,
+    QuestionId, QuestionResults, RaisedHand, ResultsMode, ScheduledQuestion, StudentDetail,
+};
+use crate::telemetry::WSMetrics;
+use crate::validation::Validator;
+use crate::webhooks::WebhookDispatcher;
+use crate::{error_chain_fmt, routes::message::SignalId};
 use actix::Recipient;
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
 use itertools::Itertools;
+use secrecy::Secret;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
 use std::{collections::HashMap, sync::Mutex};
 use uuid::Uuid;
 
+pub const DEFAULT_TIMELINE_BUCKET_SECS: usize = 60;
+/// Bigger buckets are used on long sessions to keep the timeline under this size
+const MAX_TIMELINE_BUCKETS: usize = 1000;
+/// Bigger buckets are reduced to a day
+const MAX_TIMELINE_BUCKET_SECS: usize = 24 * 60 * 60;
+const DEFAULT_ALERT_COOLDOWN_SECS: u64 = 60;
+
 #[derive(thiserror::Error)]
 pub enum StateError {
     #[error("Invalid client id.")]
     InvalidId,
     #[error("Invalid answer: {0}.")]
     InvalidAnswer(usize),
+    #[error("Invalid cup: {0:?}.")]
+    InvalidCup(String),
+    #[error("A display name is required.")]
+    DisplayNameRequired,
+    #[error("A student id is required.")]
+    StudentIdRequired,
+    #[error("Student id {0:?} is not in the roster.")]
+    NotInRoster(String),
+    #[error("Student id {0:?} is already connected.")]
+    StudentAlreadyConnected(String),
+    #[error("No raised hands.")]
+    NoRaisedHands,
+    #[error("Student {0} hasn't raised the hand.")]
+    HandNotRaised(Uuid),
+    #[error("Invalid playlist: {0}.")]
+    InvalidPlaylist(String),
+    #[error("No more questions in the playlist.")]
+    PlaylistEnd,
+    #[error("No previous question in the playlist.")]
+    PlaylistStart,
+    #[error("Invalid schedule: {0}.")]
+    InvalidSchedule(String),
+    #[error("Invalid question order: {0}.")]
+    InvalidQuestionOrder(String),
 }
 
 impl std::fmt::Debug for StateError {
@@ -21,83 +71,1157 @@ impl std::fmt::Debug for StateError {
     }
 }
 
-#[derive(Default, Debug)]
+#[derive(Debug)]
 pub struct AppState {
     pub rooms: Mutex<HashMap<String, RoomState>>,
+    /// Template name -> Template
+    pub templates: Mutex<HashMap<String, RoomTemplate>>,
+    pub webhooks: WebhookDispatcher,
+    pub lti: Option<Lti>,
+    pub oidc: Option<Oidc>,
+    pub validator: Validator,
+    pub ip_rate_limiter: IpRateLimiter,
+    pub connection_limiter: ConnectionLimiter,
+    pub metrics: WSMetrics,
+    /// Rooms are only kept in memory if missing
+    pub store: Option<RoomStore>,
+    /// Every open websocket session, including the ones not in a room yet
+    pub sessions: Mutex<HashMap<Uuid, Recipient<ClientMessage>>>,
+    /// The admin API is disabled if missing
+    pub admin_token: Option<Secret<String>>,
+    pub audit: AuditLog,
+}
+
+impl AppState {
+    pub fn new(configuration: &Settings) -> Result<Self, AuditError> {
+        Ok(Self {
+            rooms: Mutex::new(HashMap::new()),
+            templates: Mutex::new(HashMap::new()),
+            webhooks: WebhookDispatcher::new(configuration.webhooks.clone()),
+            lti: configuration.lti.clone().map(Lti::new),
+            oidc: configuration.oidc.clone().map(Oidc::new),
+            validator: Validator::new(configuration.limits.clone()),
+            ip_rate_limiter: IpRateLimiter::new(configuration.websocket.ip_rate_limit.clone()),
+            connection_limiter: ConnectionLimiter::new(&configuration.websocket),
+            metrics: WSMetrics::default(),
+            store: configuration.persistence.as_ref().map(RoomStore::new),
+            sessions: Mutex::new(HashMap::new()),
+            admin_token: configuration
+                .admin
+                .as_ref()
+                .map(|admin| admin.token.clone()),
+            audit: AuditLog::new(&configuration.audit)?,
+        })
+    }
+
+    /// Loads the rooms and templates from the store, returns the number of rooms loaded
+    pub fn load_stored(&self) -> Result<usize, PersistenceError> {
+        let stored = match self.store.as_ref().map(RoomStore::load).transpose()? {
+            Some(Some(stored)) => stored,
+            _ => return Ok(0),
+        };
+        let mut rooms = self.rooms.lock().unwrap();
+        let loaded = stored.rooms.len();
+        for snapshot in stored.rooms {
+            rooms.insert(snapshot.name.clone(), snapshot.into());
+        }
+        let mut templates = self.templates.lock().unwrap();
+        for template in stored.templates {
+            templates.insert(template.name.clone(), template);
+        }
+        Ok(loaded)
+    }
+
+    /// Saves the rooms and templates if there is a store
+    pub fn save(&self) -> Result<(), PersistenceError> {
+        match &self.store {
+            Some(store) => {
+                let stored = StoredState {
+                    rooms: self
+                        .rooms
+                        .lock()
+                        .unwrap()
+                        .values()
+                        .map(RoomState::snapshot)
+                        .collect(),
+                    templates: self.templates.lock().unwrap().values().cloned().collect(),
+                };
+                store.save(&stored)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Tells every session that the server is going down
+    pub fn broadcast_shutdown(&self, reconnect_after: Duration) {
+        self.broadcast_sessions(ClientMessage::ServerShutdown {
+            reconnect_after: reconnect_after.as_millis() as u64,
+        });
+    }
+
+    /// Sends `msg` to every session, returns the number of sessions
+    pub fn broadcast_sessions(&self, msg: ClientMessage) -> usize {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .values()
+            .for_each(|connection| connection.do_send(msg.clone()));
+        sessions.len()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct RoomState {
     pub name: String,
+    /// Id of the teacher that created the room, `None` when created without login
+    pub owner: Option<String>,
+    /// Teachers that can manage the room with the owner
+    pub co_owners: HashSet<String>,
+    pub created_at: DateTime<Utc>,
     pub student_connections: HashMap<Uuid, StudentInfo>,
-    pub teacher_connections: HashMap<Uuid, Recipient<ClientMessage>>,
-    /// QuestionId -> QuestionState
-    pub questions: HashMap<Uuid, QuestionState>,
+    /// Teachers, assistants and projectors
+    pub teacher_connections: HashMap<Uuid, TeacherInfo>,
+    /// QuestionId -> QuestionState, in display order
+    pub questions: IndexMap<Uuid, QuestionState>,
+    /// Webhook urls registered for this room
+    pub webhooks: Vec<String>,
+    /// Signals students can choose, in display order
+    pub signals: Vec<CupSignal>,
+    /// Whether the alert cups ratio is over the webhook threshold
+    pub cups_alert: bool,
+    pub cup_reset: CupResetOptions,
+    pub alert_rules: Vec<CupAlertRule>,
+    /// Results sent to the students when a question publication ends
+    pub auto_share_results: Option<ResultsMode>,
+    /// Rule id -> Evaluation of the rule
+    alert_states: HashMap<String, AlertState>,
+    /// LTI context that launches this room
+    pub lti: Option<LtiRoom>,
+    pub identity_mode: IdentityMode,
+    /// Student id -> Name, allowed students when the identity mode is `Roster`
+    pub roster: HashMap<String, String>,
+    /// Every cup change, in chronological order
+    pub cup_history: Vec<CupChange>,
+    /// Every question publication, in chronological order
+    pub publications: Vec<PublicationMark>,
+    /// Students waiting to speak, first in first out
+    pub raised_hands: VecDeque<RaisedHand>,
+    /// Questions to go through with `NextQuestion` and `PreviousQuestion`
+    pub playlist: Vec<Uuid>,
+    /// Index of the last published playlist question
+    pub playlist_position: Option<usize>,
+    /// Upcoming publications, sorted by time
+    pub scheduled: Vec<ScheduledQuestion>,
+    /// Every change of the connections, cups, questions and playlist, in chronological order.
+    /// Kept while the room lives to replay it, folding it gives the same `view` of the room.
+    pub events: Vec<RoomEventRecord>,
 }
 
 impl RoomState {
-    pub fn new(name: String) -> Self {
+    pub fn new(name: String, owner: Option<String>) -> Self {
         Self {
             name,
+            owner,
+            co_owners: HashSet::new(),
+            created_at: Utc::now(),
             student_connections: HashMap::new(),
             teacher_connections: HashMap::new(),
-            questions: HashMap::new(),
+            questions: IndexMap::new(),
+            webhooks: Vec::new(),
+            signals: CupSignal::defaults(),
+            cups_alert: false,
+            cup_reset: CupResetOptions::default(),
+            alert_rules: Vec::new(),
+            auto_share_results: None,
+            alert_states: HashMap::new(),
+            lti: None,
+            identity_mode: IdentityMode::default(),
+            roster: HashMap::new(),
+            cup_history: Vec::new(),
+            publications: Vec::new(),
+            raised_hands: VecDeque::new(),
+            playlist: Vec::new(),
+            playlist_position: None,
+            scheduled: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// New room with the questions and configuration of this one, answers are left out
+    /// unless `keep_answers`
+    pub fn clone_as(&self, name: String, owner: Option<String>, keep_answers: bool) -> Self {
+        let mut room_state = Self::new(name, owner);
+        room_state.questions = self
+            .questions
+            .iter()
+            .map(|(&id, question)| {
+                let mut question = question.clone();
+                question.closes_at = None;
+                if !keep_answers {
+                    question.answers.clear();
+                }
+                (id, question)
+            })
+            .collect();
+        room_state.webhooks = self.webhooks.clone();
+        room_state.signals = self.signals.clone();
+        room_state.cup_reset = self.cup_reset;
+        room_state.set_alert_rules(self.alert_rules.clone());
+        room_state.auto_share_results = self.auto_share_results;
+        room_state.identity_mode = self.identity_mode;
+        room_state.roster = self.roster.clone();
+        room_state.playlist = self.playlist.clone();
+        room_state.record_questions();
+        room_state
+    }
+
+    pub fn record(&mut self, event: RoomEvent) {
+        self.events.push(RoomEventRecord::new(event));
+    }
+
+    /// Records the current questions, answers and playlist, for rooms that don't come from their events
+    fn record_questions(&mut self) {
+        let events = self
+            .questions
+            .iter()
+            .flat_map(|(&id, question)| {
+                let created = RoomEventRecord::question_created(id, question);
+                let answers = question
+                    .answers
+                    .iter()
+                    .sorted()
+                    .map(move |(&student, &answer)| {
+                        RoomEventRecord::new(RoomEvent::QuestionAnswered {
+                            id,
+                            student,
+                            answer,
+                        })
+                    });
+                std::iter::once(created).chain(answers)
+            })
+            .collect::<Vec<_>>();
+        self.events.extend(events);
+        if !self.playlist.is_empty() {
+            self.record(RoomEvent::PlaylistChanged {
+                ids: self.playlist.clone(),
+            });
+        }
+    }
+
+    /// Events of the room with the state derived from them
+    pub fn history(&self) -> RoomHistory {
+        RoomHistory {
+            events: self.events.clone(),
+            state: RoomView::fold(&self.events),
+            room: self.view(),
+        }
+    }
+
+    /// Connections, questions and playlist of the room, as folding its events gives them
+    pub fn view(&self) -> RoomView {
+        let students = self
+            .student_connections
+            .iter()
+            .map(|(&id, student)| (id, student.cup_selection.clone()))
+            .collect();
+        let teachers = self
+            .teacher_connections
+            .iter()
+            .map(|(&id, teacher)| (id, teacher.connection_type))
+            .collect();
+        let questions = self
+            .questions
+            .iter()
+            .map(|(&id, question)| {
+                let mut question = question.clone();
+                question.closes_at = None;
+                (id, question)
+            })
+            .collect();
+        let published = self.publications.last().map(|mark| mark.id.0).filter(|id| {
+            self.questions
+                .get(id)
+                .is_some_and(|question| question.closes_at.is_some())
+        });
+        RoomView {
+            students,
+            teachers,
+            questions,
+            published,
+            playlist: self.playlist.clone(),
+        }
+    }
+
+    /// Template with the questions and configuration of this room
+    pub fn to_template(&self, name: String, owner: Option<String>) -> RoomTemplate {
+        RoomTemplate {
+            name,
+            owner,
+            created_at: Utc::now(),
+            questions: self
+                .questions
+                .values()
+                .map(|question| Question {
+                    title: question.title.clone(),
+                    options: question.options.clone(),
+                    correct: question.correct,
+                })
+                .collect(),
+            signals: self.signals.clone(),
+            cup_reset: self.cup_reset,
+            alert_rules: self.alert_rules.clone(),
+            auto_share_results: self.auto_share_results,
+            identity_mode: self.identity_mode,
+        }
+    }
+
+    /// Adds the template questions and takes its configuration
+    pub fn apply_template(&mut self, template: &RoomTemplate) {
+        template
+            .questions
+            .iter()
+            .cloned()
+            .for_each(|question| self.add_question(question));
+        self.signals = template.signals.clone();
+        self.cup_reset = template.cup_reset;
+        self.set_alert_rules(template.alert_rules.clone());
+        self.auto_share_results = template.auto_share_results;
+        self.identity_mode = template.identity_mode;
+    }
+
+    /// Data of the room that outlives its connections
+    pub fn snapshot(&self) -> RoomSnapshot {
+        RoomSnapshot {
+            name: self.name.clone(),
+            owner: self.owner.clone(),
+            co_owners: self.co_owners.clone(),
+            created_at: self.created_at,
+            questions: self.questions.clone(),
+            webhooks: self.webhooks.clone(),
+            signals: self.signals.clone(),
+            cup_reset: self.cup_reset,
+            alert_rules: self.alert_rules.clone(),
+            auto_share_results: self.auto_share_results,
+            lti: self.lti.clone(),
+            identity_mode: self.identity_mode,
+            roster: self.roster.clone(),
+            cup_history: self.cup_history.clone(),
+            publications: self.publications.clone(),
+            playlist: self.playlist.clone(),
+            playlist_position: self.playlist_position,
+            events: self.events.clone(),
+        }
+    }
+
+    /// Whether `teacher` is the room owner, requests without teacher (login not configured)
+    /// are not restricted and rooms without owner (like LTI ones) are not owned by any teacher
+    pub fn is_owner(&self, teacher: Option<&Teacher>) -> bool {
+        is_owner(
+            self.owner.as_deref(),
+            teacher.map(|teacher| teacher.id.as_str()),
+        )
+    }
+
+    /// Approximate bytes used by the room, counting its collections and texts
+    pub fn memory_footprint(&self) -> usize {
+        use std::mem::size_of;
+        let questions: usize = self
+            .questions
+            .values()
+            .map(|question| {
+                size_of::<(Uuid, QuestionState)>()
+                    + question.title.len()
+                    + question.options.iter().map(|o| o.len()).sum::<usize>()
+                    + question.answers.len() * size_of::<(Uuid, usize)>()
+            })
+            .sum();
+        let roster: usize = self
+            .roster
+            .iter()
+            .map(|(id, name)| size_of::<(String, String)>() + id.len() + name.len())
+            .sum();
+        size_of::<Self>()
+            + questions
+            + roster
+            + self.student_connections.len() * size_of::<(Uuid, StudentInfo)>()
+            + self.teacher_connections.len() * size_of::<(Uuid, TeacherInfo)>()
+            + self.cup_history.len() * size_of::<CupChange>()
+            + self.publications.len() * size_of::<PublicationMark>()
+            + self.events.len() * size_of::<RoomEventRecord>()
+    }
+
+    /// Whether `teacher` is the room owner or one of its co-owners
+    pub fn can_manage(&self, teacher: Option<&Teacher>) -> bool {
+        self.is_owner(teacher)
+            || teacher
+                .map(|teacher| self.co_owners.contains(&teacher.id))
+                .unwrap_or(false)
+    }
+
+    /// Identity of a connecting student according to the room identity mode
+    pub fn student_identity(
+        &self,
+        display_name: Option<String>,
+        student_id: Option<String>,
+    ) -> Result<Option<StudentIdentity>, StateError> {
+        match self.identity_mode {
+            IdentityMode::Anonymous => Ok(None),
+            IdentityMode::Nickname => {
+                let name = display_name
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty())
+                    .ok_or(StateError::DisplayNameRequired)?;
+                Ok(Some(StudentIdentity {
+                    name,
+                    student_id: None,
+                }))
+            }
+            IdentityMode::Roster => {
+                let student_id = student_id
+                    .map(|id| id.trim().to_string())
+                    .filter(|id| !id.is_empty())
+                    .ok_or(StateError::StudentIdRequired)?;
+                let connected = self.student_connections.values().any(|student| {
+                    student
+                        .identity
+                        .as_ref()
+                        .and_then(|identity| identity.student_id.as_ref())
+                        == Some(&student_id)
+                });
+                if connected {
+                    return Err(StateError::StudentAlreadyConnected(student_id));
+                }
+                match self.roster.get(&student_id) {
+                    Some(name) => Ok(Some(StudentIdentity {
+                        name: name.clone(),
+                        student_id: Some(student_id),
+                    })),
+                    None => Err(StateError::NotInRoster(student_id)),
+                }
+            }
         }
     }
 
-    pub fn choose_cup(&mut self, id: &Uuid, color: CupColor) -> Result<(), StateError> {
+    /// Cup and answers of each identified student, `None` for anonymous rooms
+    pub fn students_detail(&self) -> Option<ClientMessage> {
+        if self.identity_mode == IdentityMode::Anonymous {
+            return None;
+        }
+        let students = self
+            .student_connections
+            .iter()
+            .filter_map(|(id, student)| {
+                let identity = student.identity.as_ref()?;
+                let answers = self
+                    .questions
+                    .iter()
+                    .filter_map(|(question_id, question)| {
+                        question
+                            .answers
+                            .get(id)
+                            .map(|&answer| (*question_id, answer))
+                    })
+                    .collect();
+                Some(StudentDetail {
+                    id: *id,
+                    name: identity.name.clone(),
+                    student_id: identity.student_id.clone(),
+                    cup: student.cup_selection.clone(),
+                    answers,
+                })
+            })
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+            .collect();
+        Some(ClientMessage::StudentsInfo(students))
+    }
+
+    /// Returns the time of the selection
+    pub fn choose_cup(&mut self, id: &Uuid, color: SignalId) -> Result<DateTime<Utc>, StateError> {
+        if !self.signals.iter().any(|signal| signal.id == color.0) {
+            return Err(StateError::InvalidCup(color.0));
+        }
         match self.student_connections.get_mut(id) {
             Some(data) => {
-                data.cup_selection = Some(color);
-                Ok(())
+                let change = CupChange::new(*id, Some(color.clone()));
+                let selected_at = change.timestamp;
+                data.cup_selection = Some(color.clone());
+                data.cup_selected_at = Some(selected_at);
+                self.cup_history.push(change);
+                self.record(RoomEvent::CupChanged {
+                    student: *id,
+                    color: Some(color),
+                });
+                Ok(selected_at)
             }
             None => Err(StateError::InvalidId),
         }
     }
 
+    /// Clears the cup of the student, returns true if it had one
+    pub fn clear_cup(&mut self, id: &Uuid) -> bool {
+        match self.student_connections.get_mut(id) {
+            Some(data) if data.cup_selection.is_some() => {
+                data.cup_selection = None;
+                data.cup_selected_at = None;
+                self.cup_history.push(CupChange::new(*id, None));
+                self.record(RoomEvent::CupChanged {
+                    student: *id,
+                    color: None,
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Clears every cup and returns the students that had one
+    pub fn reset_cups(&mut self) -> Vec<Uuid> {
+        let ids = self.student_connections.keys().copied().collect::<Vec<_>>();
+        ids.into_iter().filter(|id| self.clear_cup(id)).collect()
+    }
+
+    /// Replaces the alert rules, restarting their evaluation
+    pub fn set_alert_rules(&mut self, rules: Vec<CupAlertRule>) {
+        self.alert_rules = rules;
+        self.alert_states.clear();
+    }
+
+    /// Ratio of students for the condition, and whether it is met
+    fn alert_condition_ratio(&self, condition: &CupAlertCondition) -> (f64, bool) {
+        let connections = self.student_connections.len();
+        if connections == 0 {
+            return (0.0, false);
+        }
+        let ratio_of = |count: usize| count as f64 / connections as f64;
+        match condition {
+            CupAlertCondition::SignalAtLeast { signal, ratio } => {
+                let value = ratio_of(
+                    self.student_connections
+                        .values()
+                        .filter(|student| {
+                            matches!(&student.cup_selection, Some(cup) if &cup.0 == signal)
+                        })
+                        .count(),
+                );
+                (value, value >= *ratio)
+            }
+            CupAlertCondition::ChosenBelow { ratio } => {
+                let value = ratio_of(
+                    self.student_connections
+                        .values()
+                        .filter(|student| student.cup_selection.is_some())
+                        .count(),
+                );
+                (value, value < *ratio)
+            }
+        }
+    }
+
+    /// Evaluates the alert rules, a rule alerts once its condition held for `hold_secs`
+    /// and again only after the condition stops and the cooldown passes.
+    /// Returns the triggered alerts and when the rules need to be evaluated again.
+    pub fn evaluate_alert_rules(&mut self) -> (Vec<CupAlert>, Option<Duration>) {
+        let now = Utc::now();
+        let mut alerts = Vec::new();
+        let mut recheck: Option<chrono::Duration> = None;
+        let mut schedule = |after: chrono::Duration| {
+            recheck = Some(recheck.map_or(after, |recheck| recheck.min(after)));
+        };
+        for rule in &self.alert_rules {
+            let (ratio, met) = self.alert_condition_ratio(&rule.condition);
+            let alert_state = self.alert_states.entry(rule.id.clone()).or_default();
+            if !met {
+                alert_state.since = None;
+                alert_state.active = false;
+                continue;
+            }
+            let since = *alert_state.since.get_or_insert(now);
+            if alert_state.active {
+                continue;
+            }
+            let hold_until = match secs_after(since, rule.hold_secs) {
+                Some(hold_until) => hold_until,
+                None => continue,
+            };
+            if now < hold_until {
+                schedule(hold_until - now);
+                continue;
+            }
+            let cooldown = rule.cooldown_secs.unwrap_or(DEFAULT_ALERT_COOLDOWN_SECS);
+            if let Some(last_alert) = alert_state.last_alert {
+                let cooldown_until = match secs_after(last_alert, cooldown) {
+                    Some(cooldown_until) => cooldown_until,
+                    None => continue,
+                };
+                if now < cooldown_until {
+                    schedule(cooldown_until - now);
+                    continue;
+                }
+            }
+            alert_state.active = true;
+            alert_state.last_alert = Some(now);
+            alerts.push(CupAlert {
+                rule: rule.id.clone(),
+                condition: rule.condition.clone(),
+                ratio,
+                connections: self.student_connections.len(),
+            });
+        }
+        (alerts, recheck.and_then(|recheck| recheck.to_std().ok()))
+    }
+
+    /// Sends `msg` to every connection of the room
+    pub fn broadcast(&self, msg: ClientMessage) {
+        self.student_connections
+            .values()
+            .map(|student| &student.connection)
+            .chain(
+                self.teacher_connections
+                    .values()
+                    .map(|teacher| &teacher.connection),
+            )
+            .for_each(|connection| connection.do_send(msg.clone()));
+    }
+
+    /// Removes the student, recording that its cup is gone
+    pub fn remove_student(&mut self, id: &Uuid) -> Option<StudentInfo> {
+        let student = self.student_connections.remove(id)?;
+        if student.cup_selection.is_some() {
+            self.cup_history.push(CupChange::new(*id, None));
+        }
+        self.lower_hand(id);
+        self.record(RoomEvent::Left { id: *id });
+        Some(student)
+    }
+
+    /// Adds the student to the end of the raised hands queue, keeping its place if already there.
+    /// Returns the student position.
+    pub fn raise_hand(&mut self, id: &Uuid) -> Result<usize, StateError> {
+        let student = self
+            .student_connections
+            .get(id)
+            .ok_or(StateError::InvalidId)?;
+        if let Some(position) = self.hand_position(id) {
+            return Ok(position);
+        }
+        let name = match self.identity_mode {
+            IdentityMode::Anonymous => None,
+            _ => student
+                .identity
+                .as_ref()
+                .map(|identity| identity.name.clone()),
+        };
+        self.raised_hands.push_back(RaisedHand {
+            id: *id,
+            name,
+            raised_at: Utc::now(),
+        });
+        Ok(self.raised_hands.len())
+    }
+
+    /// Returns true if the student was in the raised hands queue
+    pub fn lower_hand(&mut self, id: &Uuid) -> bool {
+        let len = self.raised_hands.len();
+        self.raised_hands.retain(|hand| &hand.id != id);
+        self.raised_hands.len() != len
+    }
+
+    /// Removes `id`, or the first student if `None`, from the raised hands queue
+    pub fn call_on(&mut self, id: Option<Uuid>) -> Result<RaisedHand, StateError> {
+        match id {
+            None => self
+                .raised_hands
+                .pop_front()
+                .ok_or(StateError::NoRaisedHands),
+            Some(id) => {
+                let position = self
+                    .raised_hands
+                    .iter()
+                    .position(|hand| hand.id == id)
+                    .ok_or(StateError::HandNotRaised(id))?;
+                Ok(self.raised_hands.remove(position).unwrap())
+            }
+        }
+    }
+
+    /// Empties the raised hands queue and returns the students that were on it
+    pub fn clear_hands(&mut self) -> Vec<Uuid> {
+        self.raised_hands.drain(..).map(|hand| hand.id).collect()
+    }
+
+    /// Position of the student in the raised hands queue, starting at 1
+    pub fn hand_position(&self, id: &Uuid) -> Option<usize> {
+        self.raised_hands
+            .iter()
+            .position(|hand| &hand.id == id)
+            .map(|i| i + 1)
+    }
+
+    pub fn hands_queue(&self) -> ClientMessage {
+        ClientMessage::HandsQueue(self.raised_hands.iter().cloned().collect())
+    }
+
+    /// Sends `msg` to the teachers and assistants of the room
+    pub fn broadcast_managers(&self, msg: ClientMessage) {
+        self.teacher_connections
+            .values()
+            .filter(|teacher| teacher.connection_type.manages_room())
+            .for_each(|teacher| teacher.connection.do_send(msg.clone()));
+    }
+
+    /// Sends the results of a question to the students and projectors
+    pub fn share_results(&self, msg: ClientMessage) {
+        self.student_connections
+            .values()
+            .map(|student| &student.connection)
+            .chain(
+                self.teacher_connections
+                    .values()
+                    .filter(|teacher| teacher.connection_type == ConnectionType::Projector)
+                    .map(|teacher| &teacher.connection),
+            )
+            .for_each(|connection| connection.do_send(msg.clone()));
+    }
+
+    /// Checks that `ids` are room questions without repetitions
+    fn check_question_ids(&self, ids: &[Uuid]) -> Result<(), String> {
+        if let Some(id) = ids.iter().duplicates().next() {
+            return Err(format!("question {id} is repeated"));
+        }
+        if let Some(id) = ids.iter().find(|id| !self.questions.contains_key(*id)) {
+            return Err(format!("unknown question {id}"));
+        }
+        Ok(())
+    }
+
+    /// Moves the `ids` questions to the front in the given order, the rest keep their order
+    pub fn reorder_questions(&mut self, ids: Vec<Uuid>) -> Result<(), StateError> {
+        self.check_question_ids(&ids)
+            .map_err(StateError::InvalidQuestionOrder)?;
+        move_to_front(&mut self.questions, &ids);
+        self.record(RoomEvent::QuestionsReordered { ids });
+        Ok(())
+    }
+
+    /// Replaces the playlist, starting again from its beginning
+    pub fn set_playlist(&mut self, ids: Vec<Uuid>) -> Result<(), StateError> {
+        self.check_question_ids(&ids)
+            .map_err(StateError::InvalidPlaylist)?;
+        self.playlist = ids.clone();
+        self.playlist_position = None;
+        self.record(RoomEvent::PlaylistChanged { ids });
+        Ok(())
+    }
+
+    /// Question after (or before, if not `forward`) the current playlist position
+    pub fn playlist_step(&self, forward: bool) -> Result<Uuid, StateError> {
+        let index = match (self.playlist_position, forward) {
+            (None, true) => 0,
+            (Some(position), true) => position + 1,
+            (Some(position), false) if position > 0 => position - 1,
+            (_, false) => return Err(StateError::PlaylistStart),
+        };
+        self.playlist
+            .get(index)
+            .copied()
+            .ok_or(StateError::PlaylistEnd)
+    }
+
+    /// Moves the playlist position to the published question, returns true if it changed
+    pub fn mark_published(&mut self, id: &Uuid) -> bool {
+        match self.playlist.iter().position(|question| question == id) {
+            Some(index) if self.playlist_position != Some(index) => {
+                self.playlist_position = Some(index);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Schedules the publication of a question, replacing its previous schedule
+    pub fn schedule_question(
+        &mut self,
+        id: Uuid,
+        at: DateTime<Utc>,
+        secs: usize,
+    ) -> Result<ScheduledQuestion, StateError> {
+        if !self.questions.contains_key(&id) {
+            return Err(StateError::InvalidSchedule(format!(
+                "unknown question {id}"
+            )));
+        }
+        if at <= Utc::now() {
+            return Err(StateError::InvalidSchedule(
+                "the publication time already passed".to_string(),
+            ));
+        }
+        self.unschedule_question(&id);
+        let scheduled = ScheduledQuestion {
+            id: QuestionId(id),
+            at,
+            secs,
+        };
+        let index = self.scheduled.partition_point(|other| other.at <= at);
+        self.scheduled.insert(index, scheduled.clone());
+        Ok(scheduled)
+    }
+
+    /// Returns true if the question was scheduled
+    pub fn unschedule_question(&mut self, id: &Uuid) -> bool {
+        let len = self.scheduled.len();
+        self.scheduled.retain(|scheduled| &scheduled.id.0 != id);
+        self.scheduled.len() != len
+    }
+
+    /// Removes the publication of `id` at `at`, unless it was cancelled or rescheduled
+    pub fn take_scheduled(&mut self, id: &Uuid, at: DateTime<Utc>) -> Option<ScheduledQuestion> {
+        let index = self
+            .scheduled
+            .iter()
+            .position(|scheduled| &scheduled.id.0 == id && scheduled.at == at)?;
+        Some(self.scheduled.remove(index))
+    }
+
+    /// Whether the question is on the playlist or scheduled
+    pub fn is_planned(&self, id: &Uuid) -> bool {
+        self.playlist.contains(id) || self.scheduled.iter().any(|scheduled| &scheduled.id.0 == id)
+    }
+
+    pub fn playlist_info(&self) -> ClientMessage {
+        ClientMessage::Playlist(PlaylistInfo {
+            questions: self.playlist.iter().copied().map(QuestionId).collect(),
+            position: self.playlist_position,
+            scheduled: self.scheduled.clone(),
+        })
+    }
+
+    /// Removes the question from the room, its playlist and its schedule
+    pub fn remove_question(&mut self, id: &Uuid) -> Option<QuestionState> {
+        let question = self.questions.shift_remove(id)?;
+        self.record(RoomEvent::QuestionDeleted { id: *id });
+        if let Some(index) = self.playlist.iter().position(|question| question == id) {
+            self.playlist.remove(index);
+            // Keep pointing at the question before the removed one
+            self.playlist_position = match self.playlist_position {
+                Some(position) if index <= position => position.checked_sub(1),
+                position => position,
+            };
+        }
+        self.unschedule_question(id);
+        Some(question)
+    }
+
+    /// Cup counts in buckets of `bucket_secs` since the room creation
+    pub fn cup_timeline(&self, bucket_secs: usize) -> CupTimeline {
+        let end = Utc::now();
+        let elapsed = (end - self.created_at).num_seconds().max(0) as usize;
+        let bucket_secs = bucket_secs
+            .clamp(1, MAX_TIMELINE_BUCKET_SECS)
+            .max(elapsed / MAX_TIMELINE_BUCKETS + 1);
+        let n_buckets = elapsed / bucket_secs + 1;
+        let mut current = HashMap::new();
+        let mut changes = self.cup_history.iter().peekable();
+        let buckets = (0..n_buckets)
+            .map_while(|i| {
+                let start = chrono::Duration::try_seconds((i * bucket_secs) as i64)
+                    .and_then(|offset| self.created_at.checked_add_signed(offset))?;
+                let bucket_end = chrono::Duration::try_seconds(bucket_secs as i64)
+                    .and_then(|length| start.checked_add_signed(length))?;
+                while let Some(change) = changes.next_if(|change| change.timestamp < bucket_end) {
+                    match &change.color {
+                        Some(color) => current.insert(change.student, color.clone()),
+                        None => current.remove(&change.student),
+                    };
+                }
+                Some(CupBucket {
+                    start,
+                    cups: self.cup_counts(current.values()),
+                })
+            })
+            .collect();
+        CupTimeline {
+            bucket_secs,
+            buckets,
+            publications: self.publications.clone(),
+        }
+    }
+
     pub fn add_question(&mut self, question: Question) {
-        self.questions.insert(Uuid::new_v4(), question.into());
+        let id = Uuid::new_v4();
+        let question = QuestionState::from(question);
+        self.events
+            .push(RoomEventRecord::question_created(id, &question));
+        self.questions.insert(id, question);
+    }
+
+    /// Ratio of correct answers of each student, `None` if no question has a correct answer
+    pub fn student_scores(&self) -> Option<HashMap<Uuid, f64>> {
+        let gradable = self
+            .questions
+            .values()
+            .filter_map(|question| question.correct.map(|correct| (question, correct)))
+            .collect::<Vec<_>>();
+        if gradable.is_empty() {
+            return None;
+        }
+        let scores = self
+            .student_connections
+            .keys()
+            .map(|id| {
+                let correct = gradable
+                    .iter()
+                    .filter(|(question, correct)| question.answers.get(id) == Some(correct))
+                    .count();
+                (*id, correct as f64 / gradable.len() as f64)
+            })
+            .collect();
+        Some(scores)
+    }
+
+    /// Signal id -> Count of `cups`, including every room signal
+    pub fn cup_counts<'a>(
+        &self,
+        cups: impl Iterator<Item = &'a SignalId>,
+    ) -> HashMap<String, usize> {
+        let mut counts = self
+            .signals
+            .iter()
+            .map(|signal| (signal.id.clone(), 0))
+            .collect::<HashMap<_, _>>();
+        for cup in cups {
+            if let Some(count) = counts.get_mut(&cup.0) {
+                *count += 1;
+            }
+        }
+        counts
+    }
+
+    /// Students with a cup of an alert signal
+    pub fn alert_cups(&self) -> usize {
+        self.student_connections
+            .values()
+            .filter_map(|student| student.cup_selection.as_ref())
+            .filter(|cup| {
+                self.signals
+                    .iter()
+                    .any(|signal| signal.alert && signal.id == cup.0)
+            })
+            .count()
+    }
+
+    /// Updates `cups_alert` and returns true if the ratio of alert cups just went over `threshold`
+    pub fn update_cups_alert(&mut self, threshold: f64) -> bool {
+        let connections = self.student_connections.len();
+        let alert = connections > 0 && (self.alert_cups() as f64 / connections as f64) >= threshold;
+        let crossed = alert && !self.cups_alert;
+        self.cups_alert = alert;
+        crossed
+    }
+}
+
+/// Room without its connections, scheduled publications or raised hands
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RoomSnapshot {
+    pub name: String,
+    pub owner: Option<String>,
+    pub co_owners: HashSet<String>,
+    pub created_at: DateTime<Utc>,
+    pub questions: IndexMap<Uuid, QuestionState>,
+    pub webhooks: Vec<String>,
+    pub signals: Vec<CupSignal>,
+    pub cup_reset: CupResetOptions,
+    pub alert_rules: Vec<CupAlertRule>,
+    pub auto_share_results: Option<ResultsMode>,
+    pub lti: Option<LtiRoom>,
+    pub identity_mode: IdentityMode,
+    pub roster: HashMap<String, String>,
+    pub cup_history: Vec<CupChange>,
+    pub publications: Vec<PublicationMark>,
+    pub playlist: Vec<Uuid>,
+    pub playlist_position: Option<usize>,
+    /// Missing in rooms stored before the events were recorded
+    #[serde(default)]
+    pub events: Vec<RoomEventRecord>,
+}
+
+impl From<RoomSnapshot> for RoomState {
+    fn from(snapshot: RoomSnapshot) -> Self {
+        let mut room_state = Self::new(snapshot.name, snapshot.owner);
+        room_state.co_owners = snapshot.co_owners;
+        room_state.created_at = snapshot.created_at;
+        room_state.questions = snapshot.questions;
+        room_state.webhooks = snapshot.webhooks;
+        room_state.signals = snapshot.signals;
+        room_state.cup_reset = snapshot.cup_reset;
+        room_state.set_alert_rules(snapshot.alert_rules);
+        room_state.auto_share_results = snapshot.auto_share_results;
+        room_state.lti = snapshot.lti;
+        room_state.identity_mode = snapshot.identity_mode;
+        room_state.roster = snapshot.roster;
+        room_state.cup_history = snapshot.cup_history;
+        room_state.publications = snapshot.publications;
+        room_state.playlist = snapshot.playlist;
+        room_state.playlist_position = snapshot.playlist_position;
+        room_state.events = snapshot.events;
+        room_state.record(RoomEvent::Restored);
+        // Snapshots without events, or edited ones, start again from their questions
+        if RoomView::fold(&room_state.events) != room_state.view() {
+            room_state.events.clear();
+            room_state.record_questions();
+            room_state.record(RoomEvent::Restored);
+        }
+        room_state
+    }
+}
+
+/// Questions and configuration that new rooms can start from
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RoomTemplate {
+    pub name: String,
+    /// Id of the teacher that saved the template, `None` when saved without login
+    pub owner: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub questions: Vec<Question>,
+    pub signals: Vec<CupSignal>,
+    pub cup_reset: CupResetOptions,
+    pub alert_rules: Vec<CupAlertRule>,
+    pub auto_share_results: Option<ResultsMode>,
+    pub identity_mode: IdentityMode,
+}
+
+impl RoomTemplate {
+    /// Whether `teacher` can use the template, same rules as room owners
+    pub fn is_owner(&self, teacher: Option<&Teacher>) -> bool {
+        is_owner(
+            self.owner.as_deref(),
+            teacher.map(|teacher| teacher.id.as_str()),
+        )
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct StudentInfo {
     pub connection: Recipient<ClientMessage>,
-    pub cup_selection: Option<CupColor>,
+    pub cup_selection: Option<SignalId>,
+    pub cup_selected_at: Option<DateTime<Utc>>,
+    /// LTI user id, when connected from an LTI launch
+    pub lti_user: Option<String>,
+    /// `None` on anonymous rooms
+    pub identity: Option<StudentIdentity>,
 }
 
 impl StudentInfo {
-    pub fn new(connection: Recipient<ClientMessage>) -> Self {
+    pub fn new(
+        connection: Recipient<ClientMessage>,
+        lti_user: Option<String>,
+        identity: Option<StudentIdentity>,
+    ) -> Self {
         Self {
             connection,
             cup_selection: None,
+            cup_selected_at: None,
+            lti_user,
+            identity,
         }
     }
 }
 
+#[derive(Debug, Clone, Default)]
+struct AlertState {
+    /// Since when the condition is met
+    since: Option<DateTime<Utc>>,
+    /// Whether it alerted since the condition is met
+    active: bool,
+    last_alert: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CupChange {
+    pub timestamp: DateTime<Utc>,
+    pub student: Uuid,
+    /// `None` when the student leaves the room
+    pub color: Option<SignalId>,
+}
+
+impl CupChange {
+    pub fn new(student: Uuid, color: Option<SignalId>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            student,
+            color,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StudentIdentity {
+    pub name: String,
+    pub student_id: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TeacherInfo {
+    pub connection: Recipient<ClientMessage>,
+    pub connection_type: ConnectionType,
+}
+
+impl TeacherInfo {
+    pub fn new(connection: Recipient<ClientMessage>, connection_type: ConnectionType) -> Self {
+        Self {
+            connection,
+            connection_type,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct QuestionState {
     pub title: String,
     pub options: Vec<String>,
+    /// Index of the correct option
+    pub correct: Option<usize>,
     /// StudentId -> answer idx
     pub answers: HashMap<Uuid, usize>,
+    pub created_at: DateTime<Utc>,
+    /// When the last publication of the question ends
+    #[serde(skip)]
+    pub closes_at: Option<Instant>,
 }
 
 impl QuestionState {
-    pub fn new(title: String, options: Vec<String>) -> Self {
+    pub fn new(title: String, options: Vec<String>, correct: Option<usize>) -> Self {
         Self {
             title,
             options,
+            correct,
             answers: HashMap::new(),
+            created_at: Utc::now(),
+            closes_at: None,
         }
     }
 
-    pub fn answer(&mut self, student_id: Uuid, answer: usize) -> Result<(), StateError> {
-        if answer > self.options.len() {
+    /// The question doesn't close if `secs` is out of range
+    pub fn publish(&mut self, secs: usize) {
+        self.closes_at = Instant::now().checked_add(Duration::from_secs(secs as u64));
+    }
+
+    /// Closes the question if its publication time is over, returns false if it
+    /// was not published or if it was published again.
+    pub fn close(&mut self) -> bool {
+        match self.closes_at {
+            Some(closes_at) if closes_at <= Instant::now() => {
+                self.closes_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the previous answer of the student
+    pub fn answer(&mut self, student_id: Uuid, answer: usize) -> Result<Option<usize>, StateError> {
+        if answer >= self.options.len() {
             Err(StateError::InvalidAnswer(answer))
         } else {
-            self.answers.insert(student_id, answer);
-            Ok(())
+            Ok(self.answers.insert(student_id, answer))
         }
     }
 
@@ -126,6 +1250,12 @@ impl QuestionState {
                 .iter()
                 .filter_map(|(&id, &answer)| prev2new.get(&answer).map(|&new_idx| (id, new_idx)))
                 .collect();
+            // Keep the correct option
+            self.correct = self.correct.and_then(|previous_idx| {
+                self.options
+                    .get(previous_idx)
+                    .and_then(|correct| options.iter().position(|o| o == correct))
+            });
             // Set new options
             self.options = options;
         }
@@ -138,10 +1268,60 @@ impl QuestionState {
             .map(|i| counts.get(&i).cloned().unwrap_or(0))
             .collect()
     }
+
+    /// Results that can be shown to students
+    pub fn results(&self, id: Uuid, mode: ResultsMode) -> QuestionResults {
+        let counts = self.summary();
+        let total = counts.iter().sum::<usize>().max(1) as f64;
+        let percentages = counts
+            .iter()
+            .map(|&count| 100.0 * count as f64 / total)
+            .collect();
+        let (counts, percentages) = match mode {
+            ResultsMode::Counts => (Some(counts), None),
+            ResultsMode::Percentages => (None, Some(percentages)),
+            ResultsMode::Both => (Some(counts), Some(percentages)),
+        };
+        QuestionResults {
+            id: QuestionId(id),
+            title: self.title.clone(),
+            options: self.options.clone(),
+            counts,
+            percentages,
+            correct: self.correct,
+        }
+    }
 }
 
 impl From<Question> for QuestionState {
     fn from(question: Question) -> Self {
-        Self::new(question.title, question.options)
+        Self::new(question.title, question.options, question.correct)
+    }
+}
+
+/// Ownership rule shared by rooms and templates
+fn is_owner(owner: Option<&str>, user: Option<&str>) -> bool {
+    match (owner, user) {
+        (Some(owner), Some(user)) => owner == user,
+        (None, Some(_)) => false,
+        (_, None) => true,
     }
 }
+
+/// `secs` after `time`, `None` if out of range
+fn secs_after(time: DateTime<Utc>, secs: u64) -> Option<DateTime<Utc>> {
+    i64::try_from(secs)
+        .ok()
+        .and_then(chrono::Duration::try_seconds)
+        .and_then(|secs| time.checked_add_signed(secs))
+}
+
+/// Moves the `ids` questions to the front in the given order, the rest keep their order
+pub fn move_to_front(questions: &mut IndexMap<Uuid, QuestionState>, ids: &[Uuid]) {
+    let mut moved = ids
+        .iter()
+        .filter_map(|id| questions.shift_remove(id).map(|question| (*id, question)))
+        .collect::<IndexMap<_, _>>();
+    moved.extend(questions.drain(..));
+    *questions = moved;
+}
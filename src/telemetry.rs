@@ -1,4 +1,7 @@
+use crate::connection_limit::ConnectionLimiter;
 use actix_web::rt::task::JoinHandle;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
 use tracing::{subscriber::set_global_default, Subscriber};
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
@@ -45,3 +48,55 @@ where
     let current_span = tracing::Span::current();
     tokio::task::spawn_blocking(move || current_span.in_scope(f))
 }
+
+/// Counters of the websocket messages rejected to protect the server
+#[derive(Debug, Default)]
+pub struct WSMetrics {
+    throttled_messages: AtomicU64,
+    oversized_messages: AtomicU64,
+    throttled_disconnections: AtomicU64,
+    rejected_connections: AtomicU64,
+}
+
+impl WSMetrics {
+    pub fn throttled_message(&self) {
+        self.throttled_messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn oversized_message(&self) {
+        self.oversized_messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn throttled_disconnection(&self) {
+        self.throttled_disconnections
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn rejected_connection(&self) {
+        self.rejected_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self, connection_limiter: &ConnectionLimiter) -> MetricsInfo {
+        MetricsInfo {
+            connections: connection_limiter.connections(),
+            connected_ips: connection_limiter.connected_ips(),
+            rejected_connections: self.rejected_connections.load(Ordering::Relaxed),
+            throttled_messages: self.throttled_messages.load(Ordering::Relaxed),
+            oversized_messages: self.oversized_messages.load(Ordering::Relaxed),
+            throttled_disconnections: self.throttled_disconnections.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MetricsInfo {
+    /// Open websocket connections
+    pub connections: usize,
+    pub connected_ips: usize,
+    /// Connections refused by the connection limits
+    pub rejected_connections: u64,
+    pub throttled_messages: u64,
+    pub oversized_messages: u64,
+    /// Clients disconnected after breaking the limits too many times
+    pub throttled_disconnections: u64,
+}
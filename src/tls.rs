@@ -0,0 +1,117 @@
+use crate::{configuration::TlsSettings, error_chain_fmt};
+use rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::{self, CertifiedKey},
+    Certificate, PrivateKey, ServerConfig,
+};
+use std::{
+    fs::File,
+    io::BufReader,
+    sync::{Arc, RwLock},
+};
+
+#[derive(thiserror::Error)]
+pub enum TlsError {
+    #[error("Failed to read {0:?}.")]
+    ReadError(String, #[source] std::io::Error),
+    #[error("No certificates in {0:?}.")]
+    NoCertificates(String),
+    #[error("No private key in {0:?}.")]
+    NoPrivateKey(String),
+    #[error("Unsupported private key.")]
+    InvalidKey(#[from] sign::SignError),
+}
+
+impl std::fmt::Debug for TlsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Certificate served to the clients, it can be reloaded from its files
+/// without restarting the server.
+pub struct ReloadableCertificate {
+    settings: TlsSettings,
+    certified_key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ReloadableCertificate {
+    pub fn load(settings: TlsSettings) -> Result<Self, TlsError> {
+        let certified_key = load_certified_key(&settings)?;
+        Ok(Self {
+            settings,
+            certified_key: RwLock::new(Arc::new(certified_key)),
+        })
+    }
+
+    /// Keeps the current certificate if the files are not valid
+    pub fn reload(&self) -> Result<(), TlsError> {
+        let certified_key = load_certified_key(&self.settings)?;
+        *self.certified_key.write().unwrap() = Arc::new(certified_key);
+        Ok(())
+    }
+
+    pub fn server_config(self: &Arc<Self>) -> ServerConfig {
+        ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone())
+    }
+
+    /// Reloads the certificate every time the process gets a SIGHUP
+    #[cfg(unix)]
+    pub fn reload_on_sighup(self: &Arc<Self>) -> std::io::Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangup = signal(SignalKind::hangup())?;
+        let certificate = self.clone();
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                match certificate.reload() {
+                    Ok(()) => tracing::info!("TLS certificate reloaded."),
+                    Err(e) => {
+                        tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to reload the TLS certificate.")
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+impl ResolvesServerCert for ReloadableCertificate {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.certified_key.read().unwrap().clone())
+    }
+}
+
+fn load_certified_key(settings: &TlsSettings) -> Result<CertifiedKey, TlsError> {
+    let read_error = |path: &str| {
+        let path = path.to_string();
+        move |e| TlsError::ReadError(path, e)
+    };
+    let mut cert_file =
+        BufReader::new(File::open(&settings.cert_path).map_err(read_error(&settings.cert_path))?);
+    let certificates = rustls_pemfile::certs(&mut cert_file)
+        .map_err(read_error(&settings.cert_path))?
+        .into_iter()
+        .map(Certificate)
+        .collect::<Vec<_>>();
+    if certificates.is_empty() {
+        return Err(TlsError::NoCertificates(settings.cert_path.clone()));
+    }
+    let mut key_file =
+        BufReader::new(File::open(&settings.key_path).map_err(read_error(&settings.key_path))?);
+    let key = rustls_pemfile::read_all(&mut key_file)
+        .map_err(read_error(&settings.key_path))?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| TlsError::NoPrivateKey(settings.key_path.clone()))?;
+    let signing_key = sign::any_supported_type(&key)?;
+    Ok(CertifiedKey::new(certificates, signing_key))
+}
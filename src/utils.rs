@@ -2,13 +2,37 @@ use actix_web::HttpResponse;
 
 /// Return a 400 with the user-representation of the validation error as body.
 /// The error root cause is preserved for logging purposes.
-pub fn e400<T: std::fmt::Debug + std::fmt::Display>(e: T) -> actix_web::Error
+pub fn e400<T>(e: T) -> actix_web::Error
 where
     T: std::fmt::Debug + std::fmt::Display + 'static,
 {
     actix_web::error::ErrorBadRequest(e)
 }
 
+/// Return a 403 with the user-representation of the error as body.
+pub fn e403<T>(e: T) -> actix_web::Error
+where
+    T: std::fmt::Debug + std::fmt::Display + 'static,
+{
+    actix_web::error::ErrorForbidden(e)
+}
+
+/// Return a 429 with the user-representation of the error as body.
+pub fn e429<T>(e: T) -> actix_web::Error
+where
+    T: std::fmt::Debug + std::fmt::Display + 'static,
+{
+    actix_web::error::ErrorTooManyRequests(e)
+}
+
+/// Return a 503 with the user-representation of the error as body.
+pub fn e503<T>(e: T) -> actix_web::Error
+where
+    T: std::fmt::Debug + std::fmt::Display + 'static,
+{
+    actix_web::error::ErrorServiceUnavailable(e)
+}
+
 /// Return an opaque 500 while preserving the error's root cause
 pub fn e500<T>(e: T) -> actix_web::error::InternalError<T> {
     actix_web::error::InternalError::from_response(e, HttpResponse::InternalServerError().finish())
@@ -0,0 +1,134 @@
+use crate::{configuration::LimitsSettings, error_chain_fmt, routes::message::Question};
+use std::collections::HashSet;
+
+#[derive(thiserror::Error)]
+pub enum ValidationError {
+    #[error("The name can't be empty.")]
+    EmptyName,
+    #[error("The name can't be longer than {0} characters.")]
+    NameTooLong(usize),
+    #[error("Invalid character in the name: {0:?}.")]
+    InvalidNameCharacter(char),
+    #[error("The question title can't be empty.")]
+    EmptyTitle,
+    #[error("The question title can't be longer than {0} characters.")]
+    TitleTooLong(usize),
+    #[error("A question needs at least 2 options.")]
+    TooFewOptions,
+    #[error("A question can't have more than {0} options.")]
+    TooManyOptions(usize),
+    #[error("The question options can't be empty.")]
+    EmptyOption,
+    #[error("The question options can't be longer than {0} characters.")]
+    OptionTooLong(usize),
+    #[error("Repeated option: {0:?}.")]
+    RepeatedOption(String),
+    #[error("Invalid correct option: {0}.")]
+    InvalidCorrectOption(usize),
+    #[error("A room can't have more than {0} questions.")]
+    TooManyQuestions(usize),
+    #[error("A question can't be published for more than {0} seconds.")]
+    PublicationTooLong(usize),
+}
+
+impl std::fmt::Debug for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Checks the user input against the configured limits
+#[derive(Debug)]
+pub struct Validator {
+    limits: LimitsSettings,
+}
+
+impl Validator {
+    pub fn new(limits: LimitsSettings) -> Self {
+        Self { limits }
+    }
+
+    /// Room and template names
+    pub fn name(&self, name: &str) -> Result<(), ValidationError> {
+        if name.trim().is_empty() {
+            return Err(ValidationError::EmptyName);
+        }
+        if name.chars().count() > self.limits.max_name_length {
+            return Err(ValidationError::NameTooLong(self.limits.max_name_length));
+        }
+        match name
+            .chars()
+            .find(|&c| !c.is_alphanumeric() && !self.limits.name_symbols.contains(c))
+        {
+            Some(c) => Err(ValidationError::InvalidNameCharacter(c)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn title(&self, title: &str) -> Result<(), ValidationError> {
+        if title.trim().is_empty() {
+            return Err(ValidationError::EmptyTitle);
+        }
+        if title.chars().count() > self.limits.max_title_length {
+            return Err(ValidationError::TitleTooLong(self.limits.max_title_length));
+        }
+        Ok(())
+    }
+
+    pub fn options(&self, options: &[String]) -> Result<(), ValidationError> {
+        if options.len() < 2 {
+            return Err(ValidationError::TooFewOptions);
+        }
+        if options.len() > self.limits.max_options {
+            return Err(ValidationError::TooManyOptions(self.limits.max_options));
+        }
+        let mut seen = HashSet::new();
+        for option in options {
+            if option.trim().is_empty() {
+                return Err(ValidationError::EmptyOption);
+            }
+            if option.chars().count() > self.limits.max_option_length {
+                return Err(ValidationError::OptionTooLong(
+                    self.limits.max_option_length,
+                ));
+            }
+            if !seen.insert(option) {
+                return Err(ValidationError::RepeatedOption(option.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn question(&self, question: &Question) -> Result<(), ValidationError> {
+        self.title(&question.title)?;
+        self.options(&question.options)?;
+        match question.correct {
+            Some(correct) if correct >= question.options.len() => {
+                Err(ValidationError::InvalidCorrectOption(correct))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Seconds a question is published for
+    pub fn publication_secs(&self, secs: usize) -> Result<(), ValidationError> {
+        if secs > self.limits.max_publication_secs {
+            Err(ValidationError::PublicationTooLong(
+                self.limits.max_publication_secs,
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks that a room with `questions` questions can get a new one
+    pub fn new_question(&self, questions: usize) -> Result<(), ValidationError> {
+        if questions >= self.limits.max_questions_per_room {
+            Err(ValidationError::TooManyQuestions(
+                self.limits.max_questions_per_room,
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! Outgoing webhooks for room and question events
+
+use crate::{configuration::WebhookSettings, error_chain_fmt};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{redirect::Policy, Url};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    time::Duration,
+};
+use uuid::Uuid;
+
+/// Header containing the hex encoded HMAC-SHA256 of the request body
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "event", content = "data")]
+pub enum WebhookEvent {
+    RoomCreated {
+        room: String,
+    },
+    RoomDeleted {
+        room: String,
+    },
+    QuestionPublished {
+        room: String,
+        id: Uuid,
+        title: String,
+        options: Vec<String>,
+        secs: usize,
+    },
+    QuestionClosed {
+        room: String,
+        id: Uuid,
+        title: String,
+        options: Vec<String>,
+        /// #answers for each option
+        summary: Vec<usize>,
+    },
+    StudentJoined {
+        room: String,
+        connections: usize,
+    },
+    StudentLeft {
+        room: String,
+        connections: usize,
+    },
+    CupThresholdCrossed {
+        room: String,
+        connections: usize,
+        /// Students with an alert signal (red cups by default)
+        red: usize,
+        threshold: f64,
+    },
+}
+
+/// Body sent to the webhook urls
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct WebhookPayload {
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: WebhookEvent,
+}
+
+#[derive(thiserror::Error)]
+pub enum WebhookError {
+    #[error("Invalid webhook url: {0:?}.")]
+    InvalidUrl(String),
+    #[error("Failed to resolve {0:?}.")]
+    UnresolvedHost(String, #[source] std::io::Error),
+    #[error("{0:?} resolves to a loopback or private address.")]
+    PrivateAddress(String),
+    #[error("Failed to send the webhook.")]
+    RequestError(#[from] reqwest::Error),
+}
+
+impl std::fmt::Debug for WebhookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(Debug)]
+pub struct WebhookDispatcher {
+    client: reqwest::Client,
+    settings: WebhookSettings,
+}
+
+impl WebhookDispatcher {
+    pub fn new(settings: WebhookSettings) -> Self {
+        let client = client_builder(settings.timeout)
+            .build()
+            .expect("Failed to build webhooks http client.");
+        Self { client, settings }
+    }
+
+    pub fn settings(&self) -> &WebhookSettings {
+        &self.settings
+    }
+
+    /// Room webhooks need a signing secret
+    pub fn enabled(&self) -> bool {
+        self.settings.secret.is_some()
+    }
+
+    /// Whether a room webhook can be sent to `url`: http(s) only and, unless allowed in
+    /// the settings, not to loopback or private addresses
+    pub fn allows_url(&self, url: &str) -> bool {
+        let url = match Url::parse(url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+            _ => return false,
+        };
+        let host = match url.host_str() {
+            Some(host) => host.trim_start_matches('[').trim_end_matches(']'),
+            None => return false,
+        };
+        if self.settings.allow_private_urls {
+            return true;
+        }
+        match host.parse::<IpAddr>() {
+            Ok(ip) => is_public_ip(ip),
+            Err(_) => {
+                let domain = host.trim_end_matches('.').to_lowercase();
+                domain != "localhost" && !domain.ends_with(".localhost")
+            }
+        }
+    }
+
+    /// Sends the event to the global urls and to `room_urls` in the background.
+    /// Unless allowed in the settings, room urls are only sent to public addresses.
+    #[tracing::instrument(skip(self))]
+    pub fn dispatch(&self, room_urls: &[String], event: WebhookEvent) {
+        let check_room_urls = !self.settings.allow_private_urls;
+        let urls = self
+            .settings
+            .urls
+            .iter()
+            .map(|url| (url.clone(), false))
+            .chain(room_urls.iter().map(|url| (url.clone(), check_room_urls)))
+            .collect::<Vec<_>>();
+        if urls.is_empty() {
+            return;
+        }
+        let payload = WebhookPayload {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event,
+        };
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to serialize webhook payload.");
+                return;
+            }
+        };
+        let signature = match &self.settings.secret {
+            Some(secret) => sign(secret.expose_secret(), &body),
+            None => {
+                tracing::warn!("Webhooks not sent, there is no signing secret.");
+                return;
+            }
+        };
+        for (url, public_only) in urls {
+            let client = self.client.clone();
+            let body = body.clone();
+            let signature = signature.clone();
+            let timeout = self.settings.timeout;
+            let max_retries = self.settings.max_retries;
+            let retry_interval = self.settings.retry_interval;
+            tokio::spawn(async move {
+                let mut wait = retry_interval;
+                for attempt in 0..=max_retries {
+                    let result = match public_only {
+                        true => match public_client(&url, timeout).await {
+                            Ok(client) => deliver(&client, &url, &body, &signature).await,
+                            Err(e) => Err(e),
+                        },
+                        false => deliver(&client, &url, &body, &signature).await,
+                    };
+                    match result {
+                        Ok(()) => return,
+                        Err(e @ WebhookError::PrivateAddress(_)) => {
+                            tracing::error!(error.cause_chain = ?e, error.message = %e, %url, "Webhook not sent.");
+                            return;
+                        }
+                        Err(e) => {
+                            tracing::warn!(error.cause_chain = ?e, error.message = %e, %url, attempt, "Failed to deliver webhook.");
+                        }
+                    }
+                    if attempt < max_retries {
+                        tokio::time::sleep(wait).await;
+                        wait *= 2;
+                    }
+                }
+                tracing::error!(%url, "Giving up delivering webhook.");
+            });
+        }
+    }
+}
+
+/// Redirects are not followed, they could lead to any address
+fn client_builder(timeout: Duration) -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .redirect(Policy::none())
+}
+
+/// Client that only connects to the addresses the host of `url` resolves to now, if they are all
+/// public, so the host can't point to a private address after it is checked
+async fn public_client(url: &str, timeout: Duration) -> Result<reqwest::Client, WebhookError> {
+    let parsed = Url::parse(url).map_err(|_| WebhookError::InvalidUrl(url.to_string()))?;
+    let (host, port) = match (parsed.host_str(), parsed.port_or_known_default()) {
+        (Some(host), Some(port)) => (host.trim_start_matches('[').trim_end_matches(']'), port),
+        _ => return Err(WebhookError::InvalidUrl(url.to_string())),
+    };
+    let addrs = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| WebhookError::UnresolvedHost(host.to_string(), e))?
+        .collect::<Vec<_>>();
+    if addrs.is_empty() || addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err(WebhookError::PrivateAddress(host.to_string()));
+    }
+    Ok(client_builder(timeout)
+        .resolve_to_addrs(host, &addrs)
+        .build()?)
+}
+
+async fn deliver(
+    client: &reqwest::Client,
+    url: &str,
+    body: &[u8],
+    signature: &str,
+) -> Result<(), WebhookError> {
+    client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, signature)
+        .body(body.to_vec())
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(ip),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        // Shared address space (100.64.0.0/10)
+        || (ip.octets()[0] == 100 && ip.octets()[1] & 0xc0 == 64))
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        // Unique local (fc00::/7) and link local (fe80::/10)
+        || first & 0xfe00 == 0xfc00
+        || first & 0xffc0 == 0xfe80)
+}
+
+/// Returns the signature as `sha256=<hex digest>`
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
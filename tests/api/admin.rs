@@ -0,0 +1,253 @@
+use crate::helpers::{
+    create_question, get_next_ws_msg, spawn_app, spawn_app_with_admin, ADMIN_TOKEN,
+};
+use awc::ws::{CloseCode, Frame};
+use futures::StreamExt;
+use interactive_class::routes::{
+    message::{ClientMessage, ConnectionType},
+    AdminRoomDetail, AdminRoomsInfo, AnnouncementInfo,
+};
+use reqwest::Method;
+use std::time::Duration;
+
+type Connection = actix_codec::Framed<awc::BoxedSocket, awc::ws::Codec>;
+
+/// Waits for the server to close the connection
+async fn get_close_code(connection: &mut Connection) -> Option<CloseCode> {
+    tokio::time::timeout(Duration::from_millis(500), async {
+        loop {
+            match connection.next().await {
+                Some(Ok(Frame::Close(reason))) => return reason.map(|reason| reason.code),
+                Some(_) => {}
+                None => return None,
+            }
+        }
+    })
+    .await
+    .expect("Server did not disconnect.")
+}
+
+#[tokio::test]
+async fn admin_api_is_disabled_without_a_token() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .admin_request(Method::GET, "rooms")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn requests_without_a_valid_admin_token_are_rejected() {
+    // Arrange
+    let app = spawn_app_with_admin().await;
+    let url = format!("{}/admin/rooms", app.address);
+
+    // Act
+    let without_token = app.api_client.get(&url).send().await.unwrap();
+    let invalid_token = app
+        .api_client
+        .get(&url)
+        .bearer_auth(format!("{ADMIN_TOKEN}-invalid"))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(without_token.status().as_u16(), 401);
+    assert_eq!(invalid_token.status().as_u16(), 401);
+}
+
+#[actix_rt::test]
+async fn admin_lists_rooms_with_their_connections() {
+    // Arrange
+    let app = spawn_app_with_admin().await;
+    app.create_cups_room("room1").await;
+    app.create_cups_room("room2").await;
+    let (_teacher_connection, _student_connection) =
+        app.get_ws_teacher_student_connections("room1").await;
+
+    // Act
+    let rooms_info = app
+        .admin_request(Method::GET, "rooms")
+        .send()
+        .await
+        .unwrap()
+        .json::<AdminRoomsInfo>()
+        .await
+        .unwrap();
+
+    // Assert
+    let rooms = rooms_info
+        .rooms
+        .iter()
+        .map(|room| (room.name.as_str(), room.students, room.teachers))
+        .collect::<Vec<_>>();
+    assert_eq!(rooms, vec![("room1", 1, 1), ("room2", 0, 0)]);
+    assert!(rooms_info.rooms.iter().all(|room| room.memory > 0));
+    assert!(rooms_info.rooms[0].memory > rooms_info.rooms[1].memory);
+}
+
+#[actix_rt::test]
+async fn admin_shows_the_room_state() {
+    // Arrange
+    let app = spawn_app_with_admin().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, _student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+
+    // Act
+    let response = app
+        .admin_request(Method::GET, "rooms/room")
+        .send()
+        .await
+        .unwrap();
+    let missing_room = app
+        .admin_request(Method::GET, "rooms/other")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    let room_detail = response.json::<AdminRoomDetail>().await.unwrap();
+    assert_eq!(room_detail.summary.name, "room");
+    assert_eq!(room_detail.state.questions.len(), 1);
+    let mut connection_types = room_detail
+        .sessions
+        .iter()
+        .map(|session| format!("{:?}", session.connection_type))
+        .collect::<Vec<_>>();
+    connection_types.sort();
+    assert_eq!(connection_types, vec!["Student", "Teacher"]);
+    assert_eq!(missing_room.status().as_u16(), 400);
+}
+
+#[actix_rt::test]
+async fn admin_closing_a_room_disconnects_its_clients() {
+    // Arrange
+    let app = spawn_app_with_admin().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+
+    // Act
+    let rooms_info = app
+        .admin_request(Method::DELETE, "rooms/room")
+        .send()
+        .await
+        .unwrap()
+        .json::<AdminRoomsInfo>()
+        .await
+        .unwrap();
+    let teacher_msg = get_next_ws_msg(&mut teacher_connection).await;
+    let student_msg = get_next_ws_msg(&mut student_connection).await;
+
+    // Assert
+    assert!(rooms_info.rooms.is_empty());
+    for msg in [teacher_msg, student_msg] {
+        match msg {
+            ClientMessage::Disconnected(msg) => {
+                assert_eq!(msg, "The room was closed by an administrator.")
+            }
+            msg => panic!("Invalid msg: {msg:?}"),
+        }
+    }
+    assert_eq!(
+        get_close_code(&mut student_connection).await,
+        Some(CloseCode::Normal)
+    );
+    assert!(app.get_cups_info().await.rooms.is_empty());
+}
+
+#[actix_rt::test]
+async fn admin_disconnects_a_session() {
+    // Arrange
+    let app = spawn_app_with_admin().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let room_detail = app
+        .admin_request(Method::GET, "rooms/room")
+        .send()
+        .await
+        .unwrap()
+        .json::<AdminRoomDetail>()
+        .await
+        .unwrap();
+    let student = room_detail
+        .sessions
+        .iter()
+        .find(|session| session.connection_type == ConnectionType::Student)
+        .unwrap();
+
+    // Act
+    let response = app
+        .admin_request(Method::DELETE, &format!("sessions/{}", student.id))
+        .send()
+        .await
+        .unwrap();
+    let student_msg = get_next_ws_msg(&mut student_connection).await;
+    let close_code = get_close_code(&mut student_connection).await;
+    let teacher_msg = get_next_ws_msg(&mut teacher_connection).await;
+
+    // Assert
+    assert!(response.status().is_success());
+    match student_msg {
+        ClientMessage::Disconnected(msg) => assert_eq!(msg, "Disconnected by an administrator."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    assert_eq!(close_code, Some(CloseCode::Normal));
+    match teacher_msg {
+        ClientMessage::RoomInfo(info) => assert_eq!(info.connections, 0),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn admin_announcements_reach_every_session() {
+    // Arrange
+    let app = spawn_app_with_admin().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    // Not in a room yet
+    let mut connection = app.get_ws_connection().await;
+
+    // Act
+    let announcement_info = app
+        .admin_request(Method::POST, "announcement")
+        .json(&serde_json::json!({ "message": "Maintenance at 18:00." }))
+        .send()
+        .await
+        .unwrap()
+        .json::<AnnouncementInfo>()
+        .await
+        .unwrap();
+    let empty_announcement = app
+        .admin_request(Method::POST, "announcement")
+        .json(&serde_json::json!({ "message": " " }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(announcement_info.sessions, 3);
+    for connection in [
+        &mut teacher_connection,
+        &mut student_connection,
+        &mut connection,
+    ] {
+        match get_next_ws_msg(connection).await {
+            ClientMessage::Announcement(msg) => assert_eq!(msg, "Maintenance at 18:00."),
+            msg => panic!("Invalid msg: {msg:?}"),
+        }
+    }
+    assert_eq!(empty_announcement.status().as_u16(), 400);
+}
@@ -0,0 +1,137 @@
+use crate::helpers::{get_next_ws_msg, select_cup_color, spawn_app, TestApp};
+use interactive_class::routes::message::ClientMessage;
+
+async fn create_room_with_red_rule(app: &TestApp, hold_secs: u64) {
+    app.create_cups_room("room").await;
+    app.set_alert_rules(
+        "room",
+        serde_json::json!([{
+            "id": "too many red",
+            "condition": { "kind": "SignalAtLeast", "signal": "Red", "ratio": 0.5 },
+            "hold_secs": hold_secs
+        }]),
+    )
+    .await
+    .error_for_status()
+    .unwrap();
+}
+
+#[actix_rt::test]
+async fn teachers_get_an_alert_when_a_rule_triggers() {
+    // Arrange
+    let app = spawn_app().await;
+    create_room_with_red_rule(&app, 0).await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+
+    // Act
+    select_cup_color(&mut student_connection, "Red").await;
+
+    // Assert
+    match get_next_ws_msg(&mut teacher_connection).await {
+        ClientMessage::CupAlert(alert) => {
+            assert_eq!(alert.rule, "too many red");
+            assert_eq!(alert.ratio, 1.0);
+            assert_eq!(alert.connections, 1);
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn alerts_wait_for_the_hold_time() {
+    // Arrange
+    let app = spawn_app().await;
+    create_room_with_red_rule(&app, 1).await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+
+    // Act
+    select_cup_color(&mut student_connection, "Red").await;
+
+    // Assert
+    let msg = get_next_ws_msg(&mut teacher_connection).await;
+    assert!(matches!(msg, ClientMessage::RoomInfo(_)), "{msg:?}");
+    let msg = get_next_ws_msg(&mut teacher_connection).await;
+    assert!(matches!(msg, ClientMessage::CupAlert(_)), "{msg:?}");
+}
+
+#[actix_rt::test]
+async fn alerts_are_not_repeated_within_the_cooldown() {
+    // Arrange
+    let app = spawn_app().await;
+    create_room_with_red_rule(&app, 0).await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    select_cup_color(&mut student_connection, "Red").await;
+    // Alert and room info
+    get_next_ws_msg(&mut teacher_connection).await;
+    get_next_ws_msg(&mut teacher_connection).await;
+    select_cup_color(&mut student_connection, "Green").await;
+    get_next_ws_msg(&mut teacher_connection).await;
+
+    // Act
+    select_cup_color(&mut student_connection, "Red").await;
+
+    // Assert
+    let msg = get_next_ws_msg(&mut teacher_connection).await;
+    assert!(matches!(msg, ClientMessage::RoomInfo(_)), "{msg:?}");
+}
+
+#[tokio::test]
+async fn set_alert_rules_fails_with_invalid_rules() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let test_cases = [
+        (
+            serde_json::json!([{
+                "id": "rule",
+                "condition": { "kind": "SignalAtLeast", "signal": "Blue", "ratio": 0.5 }
+            }]),
+            "unknown signal",
+        ),
+        (
+            serde_json::json!([{
+                "id": "rule",
+                "condition": { "kind": "ChosenBelow", "ratio": 1.5 }
+            }]),
+            "invalid ratio",
+        ),
+        (
+            serde_json::json!([
+                { "id": "rule", "condition": { "kind": "ChosenBelow", "ratio": 0.5 } },
+                { "id": "rule", "condition": { "kind": "ChosenBelow", "ratio": 0.2 } }
+            ]),
+            "duplicated id",
+        ),
+        (
+            serde_json::json!([{
+                "id": "rule",
+                "condition": { "kind": "ChosenBelow", "ratio": 0.5 },
+                "hold_secs": u64::MAX
+            }]),
+            "a too long hold",
+        ),
+        (
+            serde_json::json!([{
+                "id": "rule",
+                "condition": { "kind": "ChosenBelow", "ratio": 0.5 },
+                "cooldown_secs": 24 * 60 * 60 + 1
+            }]),
+            "a too long cooldown",
+        ),
+    ];
+
+    for (rules, error_msg) in test_cases {
+        // Act
+        let response = app.set_alert_rules("room", rules).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 when the payload had {error_msg}."
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use crate::helpers::{
+    answer_question, create_question, delete_question, get_next_ws_msg, modify_question,
+    select_cup_color, send_ws_msg, spawn_app_with,
+};
+use interactive_class::audit::{AuditEntry, AuditRole};
+use uuid::Uuid;
+
+fn actions(entries: &[AuditEntry]) -> Vec<&str> {
+    entries.iter().map(|entry| entry.action.as_str()).collect()
+}
+
+#[actix_rt::test]
+async fn teacher_actions_are_recorded_in_the_audit_trail() {
+    // Arrange
+    let app = spawn_app_with(|_| {}).await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, _student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    modify_question(
+        &mut teacher_connection,
+        question.id.0,
+        Some("new title"),
+        None,
+    )
+    .await;
+    delete_question(&mut teacher_connection, question.id.0).await;
+
+    // Act
+    let trail = app.get_audit_trail("room", "").await;
+
+    // Assert
+    assert_eq!(
+        actions(&trail.entries),
+        vec![
+            "CreateRoom",
+            "CreateQuestion",
+            "ModifyQuestion",
+            "DeleteQuestion"
+        ]
+    );
+    assert_eq!(trail.entries[0].role, AuditRole::Teacher);
+    assert_eq!(trail.entries[0].session, None);
+    let modification = &trail.entries[2];
+    assert_eq!(modification.role, AuditRole::Teacher);
+    assert!(modification.session.is_some());
+    assert_eq!(
+        modification.diff["questions"][question.id.0.to_string()]["title"],
+        serde_json::json!({ "before": "question", "after": "new title" })
+    );
+    assert_eq!(
+        trail.entries[3].diff["questions"][question.id.0.to_string()]["after"],
+        serde_json::Value::Null
+    );
+}
+
+#[actix_rt::test]
+async fn answers_dropped_by_a_modification_are_in_the_audit_trail() {
+    // Arrange
+    let app = spawn_app_with(|_| {}).await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    answer_question(&mut student_connection, question.id.0, 1).await;
+
+    // Act
+    modify_question(
+        &mut teacher_connection,
+        question.id.0,
+        None,
+        Some(vec!["a", "c"]),
+    )
+    .await;
+    let trail = app.get_audit_trail("room", "").await;
+
+    // Assert
+    let answer = trail
+        .entries
+        .iter()
+        .find(|entry| entry.action == "AnswerQuestion")
+        .unwrap();
+    assert_eq!(answer.role, AuditRole::Student);
+    let student = answer.session.unwrap().to_string();
+    let modification = trail.entries.last().unwrap();
+    assert_eq!(modification.action, "ModifyQuestion");
+    assert_eq!(
+        modification.diff["questions"][question.id.0.to_string()]["answers"][student],
+        serde_json::json!({ "before": 1, "after": null })
+    );
+}
+
+#[actix_rt::test]
+async fn scheduled_publications_are_recorded_as_system_changes() {
+    // Arrange
+    let app = spawn_app_with(|_| {}).await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    send_ws_msg(
+        &mut teacher_connection,
+        serde_json::json!({
+            "task": "ScheduleQuestion",
+            "payload": { "id": question.id.0, "secs": 10, "delay_secs": 1 }
+        }),
+    )
+    .await;
+    // Publication
+    get_next_ws_msg(&mut student_connection).await;
+
+    // Act
+    let trail = app.get_audit_trail("room", "").await;
+
+    // Assert
+    let publication = trail.entries.last().unwrap();
+    assert_eq!(publication.action, "ScheduledPublication");
+    assert_eq!(publication.role, AuditRole::System);
+    assert_eq!(publication.session, None);
+    assert_eq!(
+        publication.diff["publications"]["appended"][0]["id"],
+        serde_json::json!(question.id.0)
+    );
+    assert_eq!(
+        publication.diff["scheduled"]["after"],
+        serde_json::json!([])
+    );
+}
+
+#[actix_rt::test]
+async fn student_entries_do_not_push_out_the_teacher_ones() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.audit.max_entries_per_room = 2;
+        c.audit.max_student_entries_per_room = 2;
+    })
+    .await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+
+    // Act
+    for color in ["Red", "Green", "Yellow"] {
+        select_cup_color(&mut student_connection, color).await;
+    }
+    let trail = app.get_audit_trail("room", "").await;
+
+    // Assert
+    assert_eq!(
+        actions(&trail.entries),
+        vec!["CreateRoom", "CreateQuestion", "ChooseCup", "ChooseCup"]
+    );
+    assert_eq!(
+        trail.entries[3].diff["cup_history"]["appended"][0]["color"],
+        "Yellow"
+    );
+}
+
+#[actix_rt::test]
+async fn audit_trail_can_be_filtered() {
+    // Arrange
+    let app = spawn_app_with(|_| {}).await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    answer_question(&mut student_connection, question.id.0, 0).await;
+    let answer = app
+        .get_audit_trail("room", "action=AnswerQuestion")
+        .await
+        .entries
+        .remove(0);
+
+    // Act
+    let by_action = app.get_audit_trail("room", "action=CreateQuestion").await;
+    let by_session = app
+        .get_audit_trail("room", &format!("session={}", answer.session.unwrap()))
+        .await;
+    let by_unknown_session = app
+        .get_audit_trail("room", &format!("session={}", Uuid::new_v4()))
+        .await;
+
+    // Assert
+    assert_eq!(actions(&by_action.entries), vec!["CreateQuestion"]);
+    assert_eq!(actions(&by_session.entries), vec!["AnswerQuestion"]);
+    assert!(by_unknown_session.entries.is_empty());
+}
+
+#[actix_rt::test]
+async fn audit_entries_are_written_to_the_file_sink() {
+    // Arrange
+    let path = std::env::temp_dir().join(format!("interactive_class-{}.jsonl", Uuid::new_v4()));
+    let audit_path = path.to_string_lossy().into_owned();
+    let app = spawn_app_with(|c| c.audit.path = Some(audit_path)).await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, _student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+
+    // Act
+    app.delete_cups_room("room").await;
+    let lines = std::fs::read_to_string(&path).unwrap();
+
+    // Assert
+    let entries = lines
+        .lines()
+        .map(|line| serde_json::from_str::<AuditEntry>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        actions(&entries),
+        vec!["CreateRoom", "CreateQuestion", "DeleteRoom"]
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn audit_trail_of_a_missing_room_is_rejected() {
+    // Arrange
+    let app = spawn_app_with(|_| {}).await;
+
+    // Act
+    let response = app.get_route("cups/room/audit").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -0,0 +1,446 @@
+use crate::helpers::{
+    create_question, get_query_param, lti_settings, mount_oidc_provider, mount_oidc_token,
+    oidc_settings, room_names, send_ws_msg, spawn_app_with, spawn_app_with_oidc, LTI_LEARNER,
+    OIDC_CLIENT_ID,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use interactive_class::{
+    configuration::get_configuration,
+    oidc::Teacher,
+    routes::message::{ClientMessage, ConnectionType},
+    Application,
+};
+use secrecy::Secret;
+use sha2::{Digest, Sha256};
+use wiremock::MockServer;
+
+#[tokio::test]
+async fn login_redirects_to_provider_with_pkce() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_oidc().await;
+
+    // Act
+    let url = app.oidc_login().await;
+
+    // Assert
+    assert!(url
+        .as_str()
+        .starts_with(&format!("{}/authorize?", mock_server.uri())));
+    assert_eq!(get_query_param(&url, "client_id"), OIDC_CLIENT_ID);
+    assert_eq!(get_query_param(&url, "response_type"), "code");
+    assert_eq!(get_query_param(&url, "scope"), "openid profile email");
+    assert_eq!(get_query_param(&url, "code_challenge_method"), "S256");
+}
+
+#[tokio::test]
+async fn callback_logs_in_the_teacher() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_oidc().await;
+
+    // Act
+    app.teacher_login(&mock_server, "teacher").await;
+    let teacher = app
+        .get_route("auth/teacher")
+        .await
+        .json::<Option<Teacher>>()
+        .await
+        .unwrap();
+
+    // Assert
+    let expected = Teacher {
+        id: "teacher".to_string(),
+        name: Some("Teacher teacher".to_string()),
+        email: Some("teacher@example.com".to_string()),
+    };
+    assert_eq!(teacher, Some(expected));
+}
+
+#[tokio::test]
+async fn callback_sends_the_pkce_code_verifier() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_oidc().await;
+    let url = app.oidc_login().await;
+    mount_oidc_token(
+        &mock_server,
+        "code",
+        &get_query_param(&url, "nonce"),
+        "teacher",
+    )
+    .await;
+
+    // Act
+    app.oidc_callback(&get_query_param(&url, "state"), "code")
+        .await;
+
+    // Assert
+    let requests = mock_server.received_requests().await.unwrap();
+    let token_request = requests
+        .iter()
+        .find(|request| request.url.path() == "/token")
+        .unwrap();
+    let body = String::from_utf8_lossy(&token_request.body);
+    let code_verifier = body
+        .split('&')
+        .find_map(|param| param.strip_prefix("code_verifier="))
+        .unwrap();
+    let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier));
+    assert_eq!(code_challenge, get_query_param(&url, "code_challenge"));
+}
+
+#[tokio::test]
+async fn callback_fails_with_invalid_state() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_oidc().await;
+    let url = app.oidc_login().await;
+    mount_oidc_token(
+        &mock_server,
+        "code",
+        &get_query_param(&url, "nonce"),
+        "teacher",
+    )
+    .await;
+
+    // Act
+    let response = app.oidc_callback("other state", "code").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(response.text().await.unwrap(), "Invalid login state.");
+}
+
+#[tokio::test]
+async fn callback_fails_with_invalid_nonce() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_oidc().await;
+    let url = app.oidc_login().await;
+    mount_oidc_token(&mock_server, "code", "other nonce", "teacher").await;
+
+    // Act
+    let response = app
+        .oidc_callback(&get_query_param(&url, "state"), "code")
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(response.text().await.unwrap(), "Invalid login nonce.");
+}
+
+#[tokio::test]
+async fn cups_routes_require_login() {
+    // Arrange
+    let (app, _mock_server) = spawn_app_with_oidc().await;
+
+    // Act
+    let info_response = app.get_route("cups").await;
+    let create_response = app.create_cups_room("room").await;
+
+    // Assert
+    assert_eq!(info_response.status().as_u16(), 401);
+    assert_eq!(create_response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn logged_in_teachers_can_create_rooms() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_oidc().await;
+    app.teacher_login(&mock_server, "teacher").await;
+
+    // Act
+    let response = app.create_cups_room("room").await;
+
+    // Assert
+    assert!(response.status().is_success());
+    let cups_info = app.get_cups_info().await;
+    assert_eq!(cups_info.rooms[0].name, "room");
+    assert_eq!(cups_info.rooms[0].owner.as_deref(), Some("teacher"));
+}
+
+#[tokio::test]
+async fn logout_ends_the_session() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_oidc().await;
+    app.teacher_login(&mock_server, "teacher").await;
+
+    // Act
+    app.api_client
+        .post(format!("{}/auth/logout", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(app.get_route("cups").await.status().as_u16(), 401);
+}
+
+#[actix_rt::test]
+async fn teachers_need_to_log_in_to_connect() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_oidc().await;
+    app.teacher_login(&mock_server, "teacher").await;
+    app.create_cups_room("room").await;
+    app.api_client
+        .post(format!("{}/auth/logout", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    // Act
+    let mut connection = app.get_ws_connection().await;
+    let msg = send_ws_msg(
+        &mut connection,
+        serde_json::json!({
+            "task": "RoomConnect",
+            "payload": { "room_name": "room", "connection_type": "Teacher" }
+        }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Teacher login required."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn logged_in_teachers_and_students_can_connect() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_oidc().await;
+    app.teacher_login(&mock_server, "teacher").await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+
+    // Act
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+
+    // Assert
+    assert_eq!(question.title, "question");
+    let msg = send_ws_msg(
+        &mut student_connection,
+        serde_json::json!({ "task": "ChooseCup", "payload": "Green" }),
+    )
+    .await;
+    assert!(matches!(msg, ClientMessage::Ok), "{msg:?}");
+}
+
+#[tokio::test]
+async fn teachers_only_see_their_rooms() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_oidc().await;
+    let other_app = app.new_session();
+    app.teacher_login(&mock_server, "teacher").await;
+    other_app.teacher_login(&mock_server, "other").await;
+    app.create_cups_room("room").await;
+    other_app.create_cups_room("other room").await;
+
+    // Act
+    let cups_info = app.get_cups_info().await;
+
+    // Assert
+    assert_eq!(room_names(&cups_info), ["room"]);
+}
+
+#[tokio::test]
+async fn only_the_owner_can_delete_a_room() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_oidc().await;
+    let other_app = app.new_session();
+    app.teacher_login(&mock_server, "teacher").await;
+    other_app.teacher_login(&mock_server, "other").await;
+    app.create_cups_room("room").await;
+    app.add_co_owner("room", "other").await;
+
+    // Act
+    let response = other_app
+        .api_client
+        .delete(format!("{}/cups/delete_room", &other_app.address))
+        .json(&serde_json::json!({ "room": "room" }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "Not allowed to manage room \"room\"."
+    );
+    assert_eq!(room_names(&app.get_cups_info().await), ["room"]);
+}
+
+#[actix_rt::test]
+async fn co_owners_can_manage_the_room() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_oidc().await;
+    let other_app = app.new_session();
+    app.teacher_login(&mock_server, "teacher").await;
+    other_app.teacher_login(&mock_server, "other").await;
+    app.create_cups_room("room").await;
+
+    // Act
+    let response = app.add_co_owner("room", "other").await;
+
+    // Assert
+    assert!(response.status().is_success());
+    let cups_info = other_app.get_cups_info().await;
+    assert_eq!(cups_info.rooms[0].co_owners, ["other"]);
+    let (_, room_info, _) = other_app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    assert!(
+        matches!(room_info, ClientMessage::RoomInfo(_)),
+        "{room_info:?}"
+    );
+}
+
+#[actix_rt::test]
+async fn projectors_need_a_teacher_allowed_to_manage_the_room() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_oidc().await;
+    let other_app = app.new_session();
+    let logged_out_app = app.new_session();
+    app.teacher_login(&mock_server, "teacher").await;
+    other_app.teacher_login(&mock_server, "other").await;
+    app.create_cups_room("room").await;
+    let projector_connect = serde_json::json!({
+        "task": "RoomConnect",
+        "payload": { "room_name": "room", "connection_type": "Projector" }
+    });
+
+    // Act
+    let mut connection = logged_out_app.get_ws_connection().await;
+    let logged_out_msg = send_ws_msg(&mut connection, projector_connect.clone()).await;
+    let mut connection = other_app.get_ws_connection().await;
+    let other_msg = send_ws_msg(&mut connection, projector_connect.clone()).await;
+    let mut connection = app.get_ws_connection().await;
+    let owner_msg = send_ws_msg(&mut connection, projector_connect).await;
+
+    // Assert
+    match logged_out_msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Teacher login required."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    match other_msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Not allowed to manage room \"room\"."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    assert!(matches!(owner_msg, ClientMessage::RoomInfo(_)));
+}
+
+#[actix_rt::test]
+async fn other_teachers_cannot_connect_to_the_room() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_oidc().await;
+    let other_app = app.new_session();
+    app.teacher_login(&mock_server, "teacher").await;
+    other_app.teacher_login(&mock_server, "other").await;
+    app.create_cups_room("room").await;
+
+    // Act
+    let mut connection = other_app.get_ws_connection().await;
+    let msg = send_ws_msg(
+        &mut connection,
+        serde_json::json!({
+            "task": "RoomConnect",
+            "payload": { "room_name": "room", "connection_type": "Teacher" }
+        }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Not allowed to manage room \"room\"."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[tokio::test]
+async fn startup_fails_without_a_valid_session_key() {
+    // Arrange
+    let mock_server = MockServer::start().await;
+    mount_oidc_provider(&mock_server).await;
+    let test_cases = vec![
+        (
+            None,
+            Some(oidc_settings(&mock_server)),
+            None,
+            "missing with OIDC",
+        ),
+        (
+            None,
+            None,
+            Some(lti_settings(&mock_server)),
+            "missing with LTI",
+        ),
+        (
+            Some(Secret::new("short".to_string())),
+            None,
+            None,
+            "too short",
+        ),
+    ];
+
+    for (session_key, oidc, lti, error_msg) in test_cases {
+        let mut configuration = get_configuration().unwrap();
+        configuration.application.port = 0;
+        configuration.application.session_key = session_key;
+        configuration.oidc = oidc;
+        configuration.lti = lti;
+
+        // Act
+        let application = Application::build(configuration).await;
+
+        // Assert
+        assert!(
+            application.is_err(),
+            "The application started when the session key was {error_msg}."
+        );
+    }
+}
+
+#[tokio::test]
+async fn startup_without_login_does_not_need_a_session_key() {
+    // Arrange
+    let mut configuration = get_configuration().unwrap();
+    configuration.application.port = 0;
+    configuration.application.session_key = None;
+    configuration.oidc = None;
+    configuration.lti = None;
+
+    // Act
+    let application = Application::build(configuration).await;
+
+    // Assert
+    assert!(application.is_ok());
+}
+
+#[tokio::test]
+async fn teachers_cannot_manage_rooms_without_owner() {
+    // Arrange
+    let mock_server = MockServer::start().await;
+    mount_oidc_provider(&mock_server).await;
+    let app = spawn_app_with(|c| {
+        c.oidc = Some(oidc_settings(&mock_server));
+        c.lti = Some(lti_settings(&mock_server));
+    })
+    .await;
+    // LTI rooms have no owner
+    app.lti_login_and_launch("student", LTI_LEARNER, "101", "http://localhost/lineitem")
+        .await;
+    app.teacher_login(&mock_server, "teacher").await;
+
+    // Act
+    let cups_info = app.get_cups_info().await;
+    let response = app
+        .api_client
+        .delete(format!("{}/cups/delete_room", &app.address))
+        .json(&serde_json::json!({ "room": "Course 101" }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(room_names(&cups_info).is_empty());
+    assert_eq!(response.status().as_u16(), 403);
+}
@@ -0,0 +1,207 @@
+use crate::helpers::{send_ws_msg, spawn_app, spawn_app_with};
+use awc::error::WsClientError;
+use interactive_class::{
+    routes::message::{ClientMessage, ConnectionType},
+    telemetry::MetricsInfo,
+};
+use std::time::Duration;
+
+#[actix_rt::test]
+async fn connections_over_the_ip_limit_are_refused() {
+    // Arrange
+    let app = spawn_app_with(|c| c.websocket.max_connections_per_ip = 1).await;
+    let _connection = app.get_ws_connection().await;
+
+    // Act
+    let result = app.try_ws_connection().await;
+
+    // Assert
+    match result {
+        Err(WsClientError::InvalidResponseStatus(status)) => assert_eq!(status.as_u16(), 429),
+        _ => panic!("The connection was not refused with 429."),
+    }
+}
+
+#[actix_rt::test]
+async fn forwarded_addresses_from_untrusted_peers_are_ignored() {
+    // Arrange
+    let app = spawn_app_with(|c| c.websocket.max_connections_per_ip = 1).await;
+    let _connection = app.try_ws_connection_forwarded_for("10.0.0.1").await;
+
+    // Act
+    let result = app.try_ws_connection_forwarded_for("10.0.0.2").await;
+
+    // Assert
+    match result {
+        Err(WsClientError::InvalidResponseStatus(status)) => assert_eq!(status.as_u16(), 429),
+        _ => panic!("The connection was not refused with 429."),
+    }
+}
+
+#[actix_rt::test]
+async fn forwarded_addresses_from_trusted_proxies_are_limited_separately() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.websocket.max_connections_per_ip = 1;
+        c.websocket.trusted_proxies = vec!["127.0.0.1".parse().unwrap()];
+    })
+    .await;
+    let _connection = app
+        .try_ws_connection_forwarded_for("10.0.0.1")
+        .await
+        .unwrap();
+
+    // Act
+    let other_client = app.try_ws_connection_forwarded_for("10.0.0.2").await;
+    let same_client = app
+        .try_ws_connection_forwarded_for("10.0.0.2, 10.0.0.1")
+        .await;
+
+    // Assert
+    assert!(other_client.is_ok());
+    match same_client {
+        Err(WsClientError::InvalidResponseStatus(status)) => assert_eq!(status.as_u16(), 429),
+        _ => panic!("The connection was not refused with 429."),
+    }
+}
+
+#[actix_rt::test]
+async fn connections_over_the_server_limit_are_refused() {
+    // Arrange
+    let app = spawn_app_with(|c| c.websocket.max_connections = 1).await;
+    let _connection = app.get_ws_connection().await;
+
+    // Act
+    let result = app.try_ws_connection().await;
+
+    // Assert
+    match result {
+        Err(WsClientError::InvalidResponseStatus(status)) => assert_eq!(status.as_u16(), 503),
+        _ => panic!("The connection was not refused with 503."),
+    }
+}
+
+#[actix_rt::test]
+async fn closed_connections_free_their_slot() {
+    // Arrange
+    let app = spawn_app_with(|c| c.websocket.max_connections_per_ip = 1).await;
+    let connection = app.get_ws_connection().await;
+    drop(connection);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Act
+    let result = app.try_ws_connection().await;
+
+    // Assert
+    assert!(result.is_ok(), "The connection was refused.");
+}
+
+#[actix_rt::test]
+async fn clients_can_not_join_more_rooms_than_the_limit() {
+    // Arrange
+    let app = spawn_app_with(|c| c.websocket.max_rooms_per_client = 1).await;
+    app.create_cups_room("room1").await;
+    app.create_cups_room("room2").await;
+    let _first_connection = app
+        .get_ws_room_connection("room1", ConnectionType::Student)
+        .await;
+
+    // Act
+    let (_second_connection, msg, _) = app
+        .get_ws_room_connection("room2", ConnectionType::Student)
+        .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "A client can't join more than 1 rooms."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn the_room_limit_is_counted_per_session() {
+    // Arrange
+    let app = spawn_app_with(|c| c.websocket.max_rooms_per_client = 1).await;
+    let other_app = app.new_session();
+    app.create_cups_room("room1").await;
+    app.create_cups_room("room2").await;
+    let _first_connection = app
+        .get_ws_room_connection("room1", ConnectionType::Student)
+        .await;
+
+    // Act
+    let (_second_connection, msg, _) = other_app
+        .get_ws_room_connection("room2", ConnectionType::Student)
+        .await;
+
+    // Assert
+    assert!(matches!(msg, ClientMessage::RoomInfo(_)), "{msg:?}");
+}
+
+#[actix_rt::test]
+async fn connecting_to_another_room_leaves_the_previous_one() {
+    // Arrange
+    let app = spawn_app_with(|c| c.websocket.max_rooms_per_client = 1).await;
+    app.create_cups_room("room1").await;
+    app.create_cups_room("room2").await;
+    let (mut connection, _, _) = app
+        .get_ws_room_connection("room1", ConnectionType::Student)
+        .await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut connection,
+        serde_json::json!({
+            "task": "RoomConnect",
+            "payload": { "room_name": "room2", "connection_type": "Student" }
+        }),
+    )
+    .await;
+    let again = send_ws_msg(
+        &mut connection,
+        serde_json::json!({
+            "task": "RoomConnect",
+            "payload": { "room_name": "room2", "connection_type": "Teacher" }
+        }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::RoomInfo(info) => assert_eq!(info.name, "room2"),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    match again {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Client already connected.."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    let students = app
+        .get_cups_info()
+        .await
+        .rooms
+        .into_iter()
+        .map(|room| (room.name, room.students))
+        .collect::<Vec<_>>();
+    assert!(students.contains(&("room1".to_string(), 0)), "{students:?}");
+    assert!(students.contains(&("room2".to_string(), 1)), "{students:?}");
+}
+
+#[actix_rt::test]
+async fn metrics_show_the_open_connections() {
+    // Arrange
+    let app = spawn_app().await;
+    let _first_connection = app.get_ws_connection().await;
+    let _second_connection = app.get_ws_connection().await;
+
+    // Act
+    let metrics = app
+        .get_route("metrics")
+        .await
+        .json::<MetricsInfo>()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(metrics.connections, 2);
+    assert_eq!(metrics.connected_ips, 1);
+}
@@ -1,10 +1,12 @@
-use crate::helpers::{get_next_ws_msg, select_cup_color, spawn_app};
+use crate::helpers::{
+    create_question, get_next_ws_msg, publish_question, room_names, select_cup_color, send_ws_msg,
+    spawn_app,
+};
 use futures::SinkExt;
 use interactive_class::routes::{
-    message::{ClientMessage, ConnectionType, CupColor},
+    message::{ClientMessage, ConnectionType},
     CupsInfo,
 };
-use std::collections::HashSet;
 
 #[tokio::test]
 async fn get_cups_info() {
@@ -43,9 +45,44 @@ async fn get_cups_info_after_rooms_are_created() {
     let cups_info = app.get_cups_info().await;
 
     // Assert
-    let rooms = HashSet::from_iter(["room1".to_string(), "room2".to_string()]);
-    let expected = CupsInfo { rooms };
-    assert_eq!(cups_info, expected);
+    assert_eq!(room_names(&cups_info), ["room1", "room2"]);
+}
+
+#[tokio::test]
+async fn create_room_fails_on_existing_room() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+
+    // Act
+    let response = app.create_cups_room("room").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "Room \"room\" already exists."
+    );
+}
+
+#[actix_rt::test]
+async fn get_cups_info_shows_room_activity() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, _student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+
+    // Act
+    let cups_info = app.get_cups_info().await;
+
+    // Assert
+    let room = &cups_info.rooms[0];
+    assert_eq!(room.name, "room");
+    assert_eq!(room.owner, None);
+    assert_eq!(room.students, 1);
+    assert_eq!(room.questions, 1);
 }
 
 #[tokio::test]
@@ -200,14 +237,14 @@ async fn teacher_gets_msg_when_student_chooses_a_cup() {
     let (mut teacher_connection, mut student_connection) =
         app.get_ws_teacher_student_connections(room_name).await;
     // Student chooses a cup
-    select_cup_color(&mut student_connection, CupColor::Yellow).await;
+    select_cup_color(&mut student_connection, "Yellow").await;
     let msg = get_next_ws_msg(&mut teacher_connection).await;
 
     // Assert
     match msg {
         ClientMessage::RoomInfo(msg) => {
             assert_eq!(&msg.name, room_name);
-            assert_eq!(msg.yellow, 1);
+            assert_eq!(msg.cups["Yellow"], 1);
         }
         msg => panic!("Invalid msg: {msg:?}"),
     }
@@ -225,7 +262,7 @@ async fn choosing_cup_fails_if_not_connected_to_room() {
     // Start connections
     let mut connection = app.get_ws_connection().await;
     // Student chooses a cup
-    let msg = select_cup_color(&mut connection, CupColor::Yellow).await;
+    let msg = select_cup_color(&mut connection, "Yellow").await;
 
     // Assert
     match msg {
@@ -249,19 +286,227 @@ async fn changing_cup_color_works() {
     let (mut teacher_connection, mut student_connection) =
         app.get_ws_teacher_student_connections(room_name).await;
     // Student chooses a cup
-    select_cup_color(&mut student_connection, CupColor::Yellow).await;
+    select_cup_color(&mut student_connection, "Yellow").await;
     get_next_ws_msg(&mut teacher_connection).await;
     // Student choose a different cup
-    select_cup_color(&mut student_connection, CupColor::Red).await;
+    select_cup_color(&mut student_connection, "Red").await;
     let msg = get_next_ws_msg(&mut teacher_connection).await;
 
     // Assert
     match msg {
         ClientMessage::RoomInfo(msg) => {
             assert_eq!(&msg.name, room_name);
-            assert_eq!(msg.yellow, 0);
-            assert_eq!(msg.red, 1);
+            assert_eq!(msg.cups["Yellow"], 0);
+            assert_eq!(msg.cups["Red"], 1);
         }
         msg => panic!("Invalid msg: {msg:?}"),
     }
 }
+
+#[actix_rt::test]
+async fn rooms_use_their_custom_signals() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room_with_signals(
+        "room",
+        serde_json::json!([
+            { "id": "ok", "label": "All good", "color": "blue" },
+            { "id": "lost", "label": "I'm lost", "color": "purple", "alert": true }
+        ]),
+    )
+    .await
+    .error_for_status()
+    .unwrap();
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+
+    // Act
+    select_cup_color(&mut student_connection, "lost").await;
+    let msg = get_next_ws_msg(&mut teacher_connection).await;
+
+    // Assert
+    match msg {
+        ClientMessage::RoomInfo(msg) => {
+            let ids = msg
+                .signals
+                .iter()
+                .map(|s| s.id.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(ids, ["ok", "lost"]);
+            assert_eq!(msg.cups.len(), 2);
+            assert_eq!(msg.cups["ok"], 0);
+            assert_eq!(msg.cups["lost"], 1);
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn choosing_a_cup_outside_the_room_signals_fails() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (_teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+
+    // Act
+    let msg = select_cup_color(&mut student_connection, "Blue").await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Invalid cup: \"Blue\"."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[tokio::test]
+async fn create_room_fails_with_invalid_signals() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases = [
+        (serde_json::json!([]), "no signals"),
+        (
+            serde_json::json!([{ "id": "a", "label": " ", "color": "blue" }]),
+            "empty label",
+        ),
+        (
+            serde_json::json!([
+                { "id": "a", "label": "A", "color": "blue" },
+                { "id": "a", "label": "B", "color": "red" }
+            ]),
+            "duplicated id",
+        ),
+        (
+            serde_json::json!([{ "id": "a", "label": "A", "color": "red; display: none" }]),
+            "an invalid color",
+        ),
+        (
+            serde_json::json!([{ "id": "a", "label": "A", "color": "#12345" }]),
+            "an invalid hex color",
+        ),
+        (
+            serde_json::json!((0..11)
+                .map(|i| serde_json::json!({ "id": i.to_string(), "label": "A", "color": "blue" }))
+                .collect::<Vec<_>>()),
+            "too many signals",
+        ),
+    ];
+
+    for (signals, error_msg) in test_cases {
+        // Act
+        let response = app.create_cups_room_with_signals("room", signals).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 when the payload had {error_msg}."
+        );
+    }
+    assert!(app.get_cups_info().await.rooms.is_empty());
+}
+
+#[actix_rt::test]
+async fn teachers_can_reset_the_cups() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    select_cup_color(&mut student_connection, "Red").await;
+    get_next_ws_msg(&mut teacher_connection).await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut teacher_connection,
+        serde_json::json!({ "task": "ResetCups" }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::RoomInfo(msg) => assert_eq!(msg.cups["Red"], 0),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    let msg = get_next_ws_msg(&mut student_connection).await;
+    assert!(matches!(msg, ClientMessage::CupCleared), "{msg:?}");
+}
+
+#[actix_rt::test]
+async fn students_cannot_reset_the_cups() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (_teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut student_connection,
+        serde_json::json!({ "task": "ResetCups" }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Student connections can't do this."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn cups_are_reset_when_a_question_is_published() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    app.set_cup_reset("room", serde_json::json!({ "on_publish": true }))
+        .await
+        .error_for_status()
+        .unwrap();
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    select_cup_color(&mut student_connection, "Yellow").await;
+    get_next_ws_msg(&mut teacher_connection).await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+
+    // Act
+    publish_question(&mut teacher_connection, question.id.0, 10).await;
+
+    // Assert
+    match get_next_ws_msg(&mut teacher_connection).await {
+        ClientMessage::RoomInfo(msg) => assert_eq!(msg.cups["Yellow"], 0),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    let cups_info = app.get_cups_info().await;
+    assert!(cups_info.rooms[0].cup_reset.on_publish);
+}
+
+#[tokio::test]
+async fn set_cup_reset_fails_with_invalid_decay() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let test_cases = [
+        (serde_json::json!({ "decay_minutes": 0 }), "no minutes"),
+        (
+            serde_json::json!({ "decay_minutes": u64::MAX }),
+            "too many minutes",
+        ),
+    ];
+
+    for (options, error_msg) in test_cases {
+        // Act
+        let response = app.set_cup_reset("room", options).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 when the payload had {error_msg}."
+        );
+    }
+    assert_eq!(
+        app.get_cups_info().await.rooms[0].cup_reset.decay_minutes,
+        None
+    );
+}
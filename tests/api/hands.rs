@@ -0,0 +1,171 @@
+use crate::helpers::{get_next_ws_msg, send_ws_msg, spawn_app};
+use interactive_class::routes::message::{ClientMessage, ConnectionType};
+
+#[actix_rt::test]
+async fn students_get_their_position_in_the_queue() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut student_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Student)
+        .await;
+    let (mut other_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Student)
+        .await;
+    let (mut teacher_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    send_ws_msg(
+        &mut student_connection,
+        serde_json::json!({ "task": "RaiseHand" }),
+    )
+    .await;
+    get_next_ws_msg(&mut teacher_connection).await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut other_connection,
+        serde_json::json!({ "task": "RaiseHand" }),
+    )
+    .await;
+
+    // Assert
+    assert!(
+        matches!(msg, ClientMessage::HandPosition(Some(2))),
+        "{msg:?}"
+    );
+    match get_next_ws_msg(&mut teacher_connection).await {
+        ClientMessage::HandsQueue(hands) => assert_eq!(hands.len(), 2),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn calling_on_a_student_moves_the_queue() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut student_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Student)
+        .await;
+    let (mut other_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Student)
+        .await;
+    let (mut teacher_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    send_ws_msg(
+        &mut student_connection,
+        serde_json::json!({ "task": "RaiseHand" }),
+    )
+    .await;
+    get_next_ws_msg(&mut teacher_connection).await;
+    send_ws_msg(
+        &mut other_connection,
+        serde_json::json!({ "task": "RaiseHand" }),
+    )
+    .await;
+    get_next_ws_msg(&mut teacher_connection).await;
+    // Position update after the second hand
+    get_next_ws_msg(&mut student_connection).await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut teacher_connection,
+        serde_json::json!({ "task": "CallOn", "payload": { "student": null } }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::HandsQueue(hands) => assert_eq!(hands.len(), 1),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    let msg = get_next_ws_msg(&mut student_connection).await;
+    assert!(matches!(msg, ClientMessage::CalledOn), "{msg:?}");
+    let msg = get_next_ws_msg(&mut other_connection).await;
+    assert!(
+        matches!(msg, ClientMessage::HandPosition(Some(1))),
+        "{msg:?}"
+    );
+}
+
+#[actix_rt::test]
+async fn clearing_hands_lowers_every_hand() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut student_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Student)
+        .await;
+    let (mut teacher_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    send_ws_msg(
+        &mut student_connection,
+        serde_json::json!({ "task": "RaiseHand" }),
+    )
+    .await;
+    get_next_ws_msg(&mut teacher_connection).await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut teacher_connection,
+        serde_json::json!({ "task": "ClearHands" }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::HandsQueue(hands) => assert!(hands.is_empty()),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    let msg = get_next_ws_msg(&mut student_connection).await;
+    assert!(matches!(msg, ClientMessage::HandPosition(None)), "{msg:?}");
+}
+
+#[actix_rt::test]
+async fn call_on_fails_without_raised_hands() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut teacher_connection,
+        serde_json::json!({ "task": "CallOn", "payload": {} }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "No raised hands."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn students_cannot_call_on() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut student_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Student)
+        .await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut student_connection,
+        serde_json::json!({ "task": "CallOn", "payload": {} }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Student connections can't do this."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
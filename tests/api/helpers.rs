@@ -1,17 +1,32 @@
+use awc::error::WsClientError;
+use awc::ws::WebsocketsRequest;
 use awc::ws::{self, Message};
 use awc::Client;
 use futures::{SinkExt, StreamExt};
-use interactive_class::routes::message::{ConnectionType, CupColor, QuestionInfo};
+use interactive_class::routes::message::{ConnectionType, QuestionInfo};
+use interactive_class::webhooks::WebhookPayload;
 use interactive_class::{
-    configuration::get_configuration,
+    audit::AuditTrail,
+    configuration::{get_configuration, AdminSettings, LtiSettings, OidcSettings, Settings},
+    history::RoomHistory,
     routes::{message::ClientMessage, CupsInfo},
+    state::RoomSnapshot,
     telemetry::{get_subscriber, init_subscriber},
-    Application,
+    Application, ShutdownTrigger,
 };
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
 use once_cell::sync::Lazy;
-use reqwest::Response;
-use std::time::Duration;
+use reqwest::{
+    cookie::{CookieStore, Jar},
+    Response,
+};
+use secrecy::Secret;
+use std::{sync::Arc, time::Duration};
 use uuid::Uuid;
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
 
 // Ensure that 'tracing' stack is only initialized once using `once_cell`
 static TRACING: Lazy<()> = Lazy::new(|| {
@@ -30,18 +45,77 @@ type Connection = actix_codec::Framed<awc::BoxedSocket, awc::ws::Codec>;
 
 pub struct TestApp {
     pub address: String,
+    #[allow(unused)]
     pub port: u16,
     pub api_client: reqwest::Client,
+    /// Cookies of `api_client`, also sent on the ws connections
+    pub cookie_jar: Arc<Jar>,
+    /// Port of the http to https redirect listener
+    pub redirect_port: Option<u16>,
+    pub shutdown: ShutdownTrigger,
 }
 
 impl TestApp {
     pub async fn get_ws_connection(&self) -> Connection {
-        let (_response, connection) = Client::new()
-            .ws(format!("{}/ws", self.address))
-            .connect()
+        self.try_ws_connection()
             .await
-            .expect("Failed to connect to websocket.");
-        connection
+            .expect("Failed to connect to websocket.")
+    }
+
+    /// Keeps the session cookie like a browser, so the connections share the session
+    pub async fn try_ws_connection(&self) -> Result<Connection, WsClientError> {
+        let (response, connection) = self.ws_request().connect().await?;
+        let url = reqwest::Url::parse(&self.address).unwrap();
+        for cookie in response.headers().get_all("set-cookie") {
+            self.cookie_jar
+                .add_cookie_str(cookie.to_str().unwrap(), &url);
+        }
+        Ok(connection)
+    }
+
+    /// Connects like a browser page served from `origin`
+    pub async fn try_ws_connection_from(&self, origin: &str) -> Result<Connection, WsClientError> {
+        let (_response, connection) = self.ws_request().origin(origin).connect().await?;
+        Ok(connection)
+    }
+
+    /// Connects through a proxy that forwards the request from `ip`
+    pub async fn try_ws_connection_forwarded_for(
+        &self,
+        ip: &str,
+    ) -> Result<Connection, WsClientError> {
+        let (_response, connection) = self
+            .ws_request()
+            .header("X-Forwarded-For", ip)
+            .connect()
+            .await?;
+        Ok(connection)
+    }
+
+    /// Read-only replay of the room events
+    pub async fn try_replay_connection(
+        &self,
+        room: &str,
+        query: &str,
+    ) -> Result<Connection, WsClientError> {
+        let (_response, connection) = self
+            .ws_request_to(&format!("cups/{room}/replay?{query}"))
+            .connect()
+            .await?;
+        Ok(connection)
+    }
+
+    fn ws_request(&self) -> WebsocketsRequest {
+        self.ws_request_to("ws")
+    }
+
+    fn ws_request_to(&self, route: &str) -> WebsocketsRequest {
+        let mut request = Client::new().ws(format!("{}/{}", self.address, route));
+        let url = reqwest::Url::parse(&self.address).unwrap();
+        if let Some(cookies) = self.cookie_jar.cookies(&url) {
+            request = request.header("Cookie", cookies.to_str().unwrap());
+        }
+        request
     }
 
     /// Gets ws connection, room info and question info
@@ -50,16 +124,31 @@ impl TestApp {
         room_name: &str,
         connection_type: ConnectionType,
     ) -> (Connection, ClientMessage, Option<ClientMessage>) {
+        self.get_ws_room_connection_with(room_name, connection_type, serde_json::json!({}))
+            .await
+    }
+
+    /// Same as `get_ws_room_connection` adding the fields of `extra` to the `RoomConnect` payload
+    pub async fn get_ws_room_connection_with(
+        &self,
+        room_name: &str,
+        connection_type: ConnectionType,
+        extra: serde_json::Value,
+    ) -> (Connection, ClientMessage, Option<ClientMessage>) {
+        let mut payload = serde_json::json!({
+            "room_name": room_name,
+            "connection_type": format!("{connection_type:?}")
+        });
+        if let (Some(payload), Some(extra)) = (payload.as_object_mut(), extra.as_object()) {
+            payload.extend(extra.clone());
+        }
         let msg = serde_json::json!({
             "task": "RoomConnect",
-            "payload": {
-                "room_name": room_name,
-                "connection_type": format!("{connection_type:?}")
-            }
+            "payload": payload
         });
         let mut connection = self.get_ws_connection().await;
         let room_info = send_ws_msg(&mut connection, msg).await;
-        let questions_info = if matches!(connection_type, ConnectionType::Teacher) {
+        let questions_info = if connection_type != ConnectionType::Student {
             Some(get_next_ws_msg(&mut connection).await)
         } else {
             None
@@ -92,6 +181,13 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    /// Request to the admin API using `ADMIN_TOKEN`
+    pub fn admin_request(&self, method: reqwest::Method, route: &str) -> reqwest::RequestBuilder {
+        self.api_client
+            .request(method, format!("{}/admin/{}", &self.address, route))
+            .bearer_auth(ADMIN_TOKEN)
+    }
+
     pub async fn create_cups_room(&self, name: &str) -> reqwest::Response {
         self.api_client
             .post(format!("{}/cups/create_room", &self.address))
@@ -101,6 +197,50 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn create_cups_room_with_signals(
+        &self,
+        name: &str,
+        signals: serde_json::Value,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/cups/create_room", &self.address))
+            .json(&serde_json::json!({ "new_room": name, "signals": signals }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn create_cups_room_from_template(
+        &self,
+        name: &str,
+        template: &str,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/cups/create_room", &self.address))
+            .json(&serde_json::json!({ "new_room": name, "template": template }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn clone_cups_room(&self, room: &str, body: serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/cups/{}/clone", &self.address, room))
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn save_template(&self, room: &str, name: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/cups/templates", &self.address))
+            .json(&serde_json::json!({ "room": room, "name": name }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn delete_cups_room(&self, name: &str) -> CupsInfo {
         self.api_client
             .delete(format!("{}/cups/delete_room", &self.address))
@@ -115,6 +255,218 @@ impl TestApp {
             .unwrap()
     }
 
+    pub async fn create_webhook(&self, room: &str, url: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/cups/create_webhook", &self.address))
+            .json(&serde_json::json!({ "room": room, "url": url }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Starts an LTI login and returns the `state` and `nonce` sent to the platform
+    pub async fn lti_login(&self) -> (String, String) {
+        let response = self
+            .api_client
+            .get(format!("{}/lti/login", &self.address))
+            .query(&[("iss", LTI_ISSUER), ("login_hint", "user")])
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(response.status().as_u16(), 302);
+        let location = response
+            .headers()
+            .get("Location")
+            .unwrap()
+            .to_str()
+            .unwrap();
+        let url = reqwest::Url::parse(location).unwrap();
+        let get_param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.to_string())
+                .unwrap()
+        };
+        (get_param("state"), get_param("nonce"))
+    }
+
+    pub async fn lti_launch(&self, state: &str, id_token: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/lti/launch", &self.address))
+            .form(&[("state", state), ("id_token", id_token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Does the LTI login and launch, returning the redirection
+    pub async fn lti_login_and_launch(
+        &self,
+        user: &str,
+        role: &str,
+        context_id: &str,
+        lineitem: &str,
+    ) -> String {
+        let (state, nonce) = self.lti_login().await;
+        let id_token = lti_id_token(&nonce, user, role, context_id, lineitem);
+        let response = self.lti_launch(&state, &id_token).await;
+        assert_eq!(response.status().as_u16(), 303);
+        response
+            .headers()
+            .get("Location")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    /// Starts the teacher login, returning the redirection to the OIDC provider
+    pub async fn oidc_login(&self) -> reqwest::Url {
+        let response = self
+            .api_client
+            .get(format!("{}/auth/login", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(response.status().as_u16(), 302);
+        let location = response
+            .headers()
+            .get("Location")
+            .unwrap()
+            .to_str()
+            .unwrap();
+        reqwest::Url::parse(location).unwrap()
+    }
+
+    pub async fn oidc_callback(&self, state: &str, code: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/auth/callback", &self.address))
+            .query(&[("state", state), ("code", code)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Logs in as `teacher` with the mock OIDC provider
+    pub async fn teacher_login(&self, mock_server: &MockServer, teacher: &str) {
+        let url = self.oidc_login().await;
+        let code = Uuid::new_v4().to_string();
+        mount_oidc_token(mock_server, &code, &get_query_param(&url, "nonce"), teacher).await;
+        let response = self
+            .oidc_callback(&get_query_param(&url, "state"), &code)
+            .await;
+        assert_eq!(response.status().as_u16(), 303);
+    }
+
+    /// Same app with a new client, as another user with its own cookies
+    pub fn new_session(&self) -> TestApp {
+        let cookie_jar = Arc::new(Jar::default());
+        TestApp {
+            address: self.address.clone(),
+            port: self.port,
+            api_client: api_client(cookie_jar.clone()),
+            cookie_jar,
+            redirect_port: self.redirect_port,
+            shutdown: self.shutdown.clone(),
+        }
+    }
+
+    pub async fn add_co_owner(&self, room: &str, teacher: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/cups/add_co_owner", &self.address))
+            .json(&serde_json::json!({ "room": room, "teacher": teacher }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn set_identity_mode(&self, room: &str, mode: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/cups/identity_mode", &self.address))
+            .json(&serde_json::json!({ "room": room, "mode": mode }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn set_cup_reset(&self, room: &str, options: serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/cups/cup_reset", &self.address))
+            .json(&serde_json::json!({ "room": room, "options": options }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn set_alert_rules(&self, room: &str, rules: serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/cups/alert_rules", &self.address))
+            .json(&serde_json::json!({ "room": room, "rules": rules }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn set_auto_share_results(&self, room: &str, mode: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/cups/auto_share_results", &self.address))
+            .json(&serde_json::json!({ "room": room, "mode": mode }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn upload_roster(&self, room: &str, csv: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/cups/upload_roster", &self.address))
+            .query(&[("room", room)])
+            .header("Content-Type", "text/csv")
+            .body(csv.to_string())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Audit trail of `room`, `query` holds the filters
+    pub async fn get_audit_trail(&self, room: &str, query: &str) -> AuditTrail {
+        self.get_route(&format!("cups/{room}/audit?{query}"))
+            .await
+            .error_for_status()
+            .unwrap()
+            .json()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_room_history(&self, room: &str) -> RoomHistory {
+        self.get_route(&format!("cups/{room}/history"))
+            .await
+            .error_for_status()
+            .unwrap()
+            .json()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_room_snapshot(&self, room: &str) -> RoomSnapshot {
+        self.get_route(&format!("cups/{room}/snapshot"))
+            .await
+            .error_for_status()
+            .unwrap()
+            .json()
+            .await
+            .unwrap()
+    }
+
+    pub async fn restore_room(&self, body: serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/cups/restore", &self.address))
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn get_cups_info(&self) -> CupsInfo {
         self.get_route("cups")
             .await
@@ -126,7 +478,8 @@ impl TestApp {
     }
 }
 
-pub async fn spawn_app_with_timeout(timeout: u64) -> TestApp {
+/// Spawns the app after applying `configure` to the test configuration
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
     // Set up tracing
     Lazy::force(&TRACING);
 
@@ -136,7 +489,17 @@ pub async fn spawn_app_with_timeout(timeout: u64) -> TestApp {
         // Port 0 give us a random available port
         c.application.port = 0;
         c.websocket.heartbeat_interval = Duration::from_millis(50);
-        c.websocket.client_timeout = Duration::from_millis(timeout);
+        c.websocket.client_timeout = Duration::from_millis(2000);
+        c.webhooks.retry_interval = Duration::from_millis(10);
+        c.application.session_key = Some(Secret::new(format!(
+            "{}{}",
+            Uuid::new_v4().simple(),
+            Uuid::new_v4().simple()
+        )));
+        c.webhooks.secret = Some(Secret::new("webhook-secret".to_string()));
+        // Mock servers listen on localhost
+        c.webhooks.allow_private_urls = true;
+        configure(&mut c);
         c
     };
 
@@ -145,22 +508,53 @@ pub async fn spawn_app_with_timeout(timeout: u64) -> TestApp {
         .await
         .expect("Failed to build application.");
     let application_port = application.port();
+    let redirect_port = application.redirect_port();
+    let shutdown = application.shutdown_trigger();
     tokio::spawn(application.run_until_stopped());
 
-    let client = reqwest::Client::builder()
-        .redirect(reqwest::redirect::Policy::none())
-        .build()
-        .unwrap();
-
+    let scheme = match configuration.application.tls {
+        Some(_) => "https",
+        None => "http",
+    };
+    let cookie_jar = Arc::new(Jar::default());
     TestApp {
-        address: format!("http://localhost:{}", application_port),
+        address: format!("{}://localhost:{}", scheme, application_port),
         port: application_port,
-        api_client: client,
+        api_client: api_client(cookie_jar.clone()),
+        cookie_jar,
+        redirect_port,
+        shutdown,
     }
 }
 
+fn api_client(cookie_jar: Arc<Jar>) -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_provider(cookie_jar)
+        // The tls tests use self-signed certificates
+        .danger_accept_invalid_certs(true)
+        .tls_info(true)
+        .build()
+        .unwrap()
+}
+
+pub async fn spawn_app_with_timeout(timeout: u64) -> TestApp {
+    spawn_app_with(|c| c.websocket.client_timeout = Duration::from_millis(timeout)).await
+}
+
+pub const ADMIN_TOKEN: &str = "admin-token";
+
+pub async fn spawn_app_with_admin() -> TestApp {
+    spawn_app_with(|c| {
+        c.admin = Some(AdminSettings {
+            token: Secret::new(ADMIN_TOKEN.to_string()),
+        })
+    })
+    .await
+}
+
 pub async fn spawn_app() -> TestApp {
-    spawn_app_with_timeout(2000).await
+    spawn_app_with(|_| {}).await
 }
 
 pub async fn get_next_ws_msg(connection: &mut Connection) -> ClientMessage {
@@ -192,10 +586,10 @@ pub async fn send_ws_msg(connection: &mut Connection, msg: serde_json::Value) ->
 }
 
 /// Student selects a cup color
-pub async fn select_cup_color(connection: &mut Connection, cup_color: CupColor) -> ClientMessage {
+pub async fn select_cup_color(connection: &mut Connection, cup_color: &str) -> ClientMessage {
     let msg = serde_json::json!({
         "task": "ChooseCup",
-        "payload": format!("{cup_color:?}")
+        "payload": cup_color
     });
     send_ws_msg(connection, msg).await
 }
@@ -221,6 +615,29 @@ pub async fn create_question(
     }
 }
 
+/// Creates a question with a correct answer
+pub async fn create_quiz_question(
+    connection: &mut Connection,
+    title: &str,
+    options: &[&str],
+    correct: usize,
+) -> QuestionInfo {
+    let msg = serde_json::json!({
+        "task": "CreateQuestion",
+        "payload": {
+            "title": title,
+            "options": options,
+            "correct": correct
+        }
+    });
+    match send_ws_msg(connection, msg).await {
+        ClientMessage::QuestionsInfo(d) => d.into_iter().last().unwrap(),
+        msg => {
+            panic!("Invalid msg: {msg:?}");
+        }
+    }
+}
+
 pub async fn publish_question(connection: &mut Connection, id: Uuid, secs: usize) -> ClientMessage {
     let msg = serde_json::json!({
         "task": "PublishQuestion",
@@ -232,6 +649,31 @@ pub async fn publish_question(connection: &mut Connection, id: Uuid, secs: usize
     send_ws_msg(connection, msg).await
 }
 
+pub async fn reorder_questions(connection: &mut Connection, ids: &[Uuid]) -> ClientMessage {
+    let msg = serde_json::json!({
+        "task": "ReorderQuestions",
+        "payload": ids
+    });
+    send_ws_msg(connection, msg).await
+}
+
+pub async fn set_playlist(connection: &mut Connection, ids: &[Uuid]) -> ClientMessage {
+    let msg = serde_json::json!({
+        "task": "SetPlaylist",
+        "payload": ids
+    });
+    send_ws_msg(connection, msg).await
+}
+
+/// Moves through the playlist with `NextQuestion` or `PreviousQuestion`
+pub async fn playlist_step(connection: &mut Connection, task: &str, secs: usize) -> ClientMessage {
+    let msg = serde_json::json!({
+        "task": task,
+        "payload": { "secs": secs }
+    });
+    send_ws_msg(connection, msg).await
+}
+
 pub async fn delete_question(connection: &mut Connection, id: Uuid) -> ClientMessage {
     let msg = serde_json::json!({
         "task": "DeleteQuestion",
@@ -272,6 +714,175 @@ pub async fn answer_question(
     send_ws_msg(connection, msg).await
 }
 
+pub const LTI_ISSUER: &str = "https://lms.example.com";
+pub const LTI_CLIENT_ID: &str = "interactive_class";
+pub const LTI_INSTRUCTOR: &str = "http://purl.imsglobal.org/vocab/lis/v2/membership#Instructor";
+pub const LTI_TEACHING_ASSISTANT: &str =
+    "http://purl.imsglobal.org/vocab/lis/v2/membership/Instructor#TeachingAssistant";
+pub const LTI_LEARNER: &str = "http://purl.imsglobal.org/vocab/lis/v2/membership#Learner";
+pub const LTI_PRIVATE_KEY: &str = include_str!("fixtures/lti_private_key.pem");
+pub const LTI_JWKS: &str = include_str!("fixtures/jwks.json");
+
+/// LTI settings using `mock_server` as the platform
+pub const OIDC_CLIENT_ID: &str = "interactive_class";
+
+pub fn lti_settings(mock_server: &MockServer) -> LtiSettings {
+    LtiSettings {
+        issuer: LTI_ISSUER.to_string(),
+        client_id: LTI_CLIENT_ID.to_string(),
+        deployment_id: Some("deployment".to_string()),
+        auth_url: format!("{}/auth", mock_server.uri()),
+        jwks_url: format!("{}/jwks", mock_server.uri()),
+        launch_url: "http://localhost/lti/launch".to_string(),
+        token_url: Some(format!("{}/token", mock_server.uri())),
+        private_key: Some(Secret::new(LTI_PRIVATE_KEY.to_string())),
+    }
+}
+
+/// Id token of a resource link launch signed with the fixture key
+pub fn lti_id_token(
+    nonce: &str,
+    user: &str,
+    role: &str,
+    context_id: &str,
+    lineitem: &str,
+) -> String {
+    let now = chrono::Utc::now().timestamp();
+    let claims = serde_json::json!({
+        "iss": LTI_ISSUER,
+        "aud": LTI_CLIENT_ID,
+        "sub": user,
+        "iat": now,
+        "exp": now + 60,
+        "nonce": nonce,
+        "https://purl.imsglobal.org/spec/lti/claim/message_type": "LtiResourceLinkRequest",
+        "https://purl.imsglobal.org/spec/lti/claim/version": "1.3.0",
+        "https://purl.imsglobal.org/spec/lti/claim/deployment_id": "deployment",
+        "https://purl.imsglobal.org/spec/lti/claim/roles": [role],
+        "https://purl.imsglobal.org/spec/lti/claim/context": {
+            "id": context_id,
+            "title": format!("Course {context_id}")
+        },
+        "https://purl.imsglobal.org/spec/lti-ags/claim/endpoint": {
+            "scope": ["https://purl.imsglobal.org/spec/lti-ags/scope/score"],
+            "lineitem": lineitem
+        }
+    });
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some("test-key".to_string());
+    let key = EncodingKey::from_rsa_pem(LTI_PRIVATE_KEY.as_bytes()).unwrap();
+    jsonwebtoken::encode(&header, &claims, &key).unwrap()
+}
+
+pub fn oidc_settings(mock_server: &MockServer) -> OidcSettings {
+    OidcSettings {
+        issuer_url: mock_server.uri(),
+        client_id: OIDC_CLIENT_ID.to_string(),
+        client_secret: None,
+        redirect_url: "http://localhost/auth/callback".to_string(),
+        scopes: vec!["profile".to_string(), "email".to_string()],
+    }
+}
+
+/// Spawns the app with a mock OIDC provider serving its metadata and JWKS
+pub async fn spawn_app_with_oidc() -> (TestApp, MockServer) {
+    let mock_server = MockServer::start().await;
+    mount_oidc_provider(&mock_server).await;
+    let app = spawn_app_with(|c| c.oidc = Some(oidc_settings(&mock_server))).await;
+    (app, mock_server)
+}
+
+/// Serves the OIDC provider metadata and JWKS (the same as the LTI platform one)
+pub async fn mount_oidc_provider(mock_server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "issuer": mock_server.uri(),
+            "authorization_endpoint": format!("{}/authorize", mock_server.uri()),
+            "token_endpoint": format!("{}/token", mock_server.uri()),
+            "jwks_uri": format!("{}/jwks", mock_server.uri()),
+        })))
+        .mount(mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/jwks"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(LTI_JWKS, "application/json"))
+        .mount(mock_server)
+        .await;
+}
+
+/// Makes the mock OIDC provider exchange `code` for an id token of `teacher`
+pub async fn mount_oidc_token(mock_server: &MockServer, code: &str, nonce: &str, teacher: &str) {
+    let now = chrono::Utc::now().timestamp();
+    let claims = serde_json::json!({
+        "iss": mock_server.uri(),
+        "aud": OIDC_CLIENT_ID,
+        "sub": teacher,
+        "iat": now,
+        "exp": now + 60,
+        "nonce": nonce,
+        "name": format!("Teacher {teacher}"),
+        "email": format!("{teacher}@example.com"),
+    });
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some("test-key".to_string());
+    let key = EncodingKey::from_rsa_pem(LTI_PRIVATE_KEY.as_bytes()).unwrap();
+    let id_token = jsonwebtoken::encode(&header, &claims, &key).unwrap();
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .and(body_string_contains(format!("code={code}")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "access_token": "token",
+            "token_type": "Bearer",
+            "id_token": id_token,
+        })))
+        .mount(mock_server)
+        .await;
+}
+
+/// Room names sorted by creation time
+pub fn room_names(cups_info: &CupsInfo) -> Vec<&str> {
+    cups_info
+        .rooms
+        .iter()
+        .map(|room| room.name.as_str())
+        .collect()
+}
+
+pub fn get_query_param(url: &reqwest::Url, name: &str) -> String {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.to_string())
+        .unwrap()
+}
+
+/// Mock server to receive webhooks.
+/// Not taken from the wiremock pool, apps of previous tests may still send webhooks to pooled servers.
+pub async fn webhook_server() -> MockServer {
+    MockServer::builder().start().await
+}
+
+/// Waits until the mock server receives `n` webhooks, sorted by dispatch time
+/// as they are delivered concurrently
+pub async fn get_webhooks(server: &MockServer, n: usize) -> Vec<WebhookPayload> {
+    let timeout = tokio::time::Instant::now() + Duration::from_secs(3);
+    loop {
+        let requests = server.received_requests().await.unwrap();
+        if requests.len() >= n {
+            let mut webhooks = requests
+                .into_iter()
+                .map(|request| serde_json::from_slice::<WebhookPayload>(&request.body).unwrap())
+                .collect::<Vec<_>>();
+            webhooks.sort_by_key(|webhook| webhook.timestamp);
+            return webhooks;
+        }
+        if tokio::time::Instant::now() > timeout {
+            panic!("Timed out waiting for webhooks, got {}.", requests.len());
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
 #[allow(unused)]
 pub fn assert_is_redirect_to(response: &Response, location: &str) {
     assert_eq!(response.status().as_u16(), 303);
@@ -0,0 +1,303 @@
+use crate::helpers::{
+    answer_question, create_question, delete_question, get_next_ws_msg, modify_question,
+    playlist_step, publish_question, reorder_questions, select_cup_color, set_playlist, spawn_app,
+    TestApp,
+};
+use awc::ws::{CloseCode, Frame};
+use futures::StreamExt;
+use interactive_class::{
+    history::{RoomEvent, RoomEventRecord},
+    routes::message::{ClientMessage, ConnectionType, SignalId},
+};
+use std::time::Duration;
+
+fn event_names(events: &[RoomEventRecord]) -> Vec<String> {
+    events
+        .iter()
+        .map(|record| {
+            serde_json::to_value(record).unwrap()["event"]["kind"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+async fn assert_folded_events_match_the_room(app: &TestApp, step: &str) {
+    let history = app.get_room_history("room").await;
+    assert_eq!(history.state, history.room, "After: {step}.");
+}
+
+#[actix_rt::test]
+async fn room_changes_are_recorded_as_events_in_order() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    publish_question(&mut teacher_connection, question.id.0, 10).await;
+    // Publication
+    get_next_ws_msg(&mut student_connection).await;
+    answer_question(&mut student_connection, question.id.0, 1).await;
+    select_cup_color(&mut student_connection, "Green").await;
+    drop(student_connection);
+    get_next_ws_msg(&mut teacher_connection).await;
+
+    // Act
+    let history = app.get_room_history("room").await;
+
+    // Assert
+    assert_eq!(
+        event_names(&history.events),
+        vec![
+            "TeacherJoined",
+            "StudentJoined",
+            "QuestionCreated",
+            "QuestionPublished",
+            "QuestionAnswered",
+            "CupChanged",
+            "Left"
+        ]
+    );
+    assert!(history
+        .events
+        .windows(2)
+        .all(|records| records[0].timestamp <= records[1].timestamp));
+    match &history.events[0].event {
+        RoomEvent::TeacherJoined {
+            connection_type, ..
+        } => assert_eq!(*connection_type, ConnectionType::Teacher),
+        event => panic!("Invalid event: {event:?}"),
+    }
+    assert!(history.state.students.is_empty());
+    assert_eq!(history.state.teachers.len(), 1);
+    assert_eq!(history.state.published, Some(question.id.0));
+}
+
+#[actix_rt::test]
+async fn reorders_playlists_and_closes_are_recorded_as_events() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, _student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question1 = create_question(&mut teacher_connection, "question1", &["a", "b"]).await;
+    let question2 = create_question(&mut teacher_connection, "question2", &["c", "d"]).await;
+    let ids = [question2.id.0, question1.id.0];
+    reorder_questions(&mut teacher_connection, &ids).await;
+    set_playlist(&mut teacher_connection, &ids).await;
+    publish_question(&mut teacher_connection, question2.id.0, 1).await;
+
+    // Act
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let history = app.get_room_history("room").await;
+
+    // Assert
+    assert_eq!(
+        event_names(&history.events)[4..],
+        [
+            "QuestionsReordered",
+            "PlaylistChanged",
+            "QuestionPublished",
+            "QuestionClosed"
+        ]
+    );
+    assert_eq!(
+        history.state.questions.keys().copied().collect::<Vec<_>>(),
+        ids
+    );
+    assert_eq!(history.state.playlist, ids);
+    assert_eq!(history.state.published, None);
+}
+
+#[actix_rt::test]
+async fn folded_events_match_the_room_state() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question1 = create_question(&mut teacher_connection, "question1", &["a", "b"]).await;
+    let question2 = create_question(&mut teacher_connection, "question2", &["c", "d"]).await;
+    let question3 = create_question(&mut teacher_connection, "question3", &["e", "f"]).await;
+    answer_question(&mut student_connection, question1.id.0, 0).await;
+    answer_question(&mut student_connection, question2.id.0, 1).await;
+    modify_question(
+        &mut teacher_connection,
+        question2.id.0,
+        Some("new question2"),
+        Some(vec!["d", "e"]),
+    )
+    .await;
+    delete_question(&mut teacher_connection, question3.id.0).await;
+    select_cup_color(&mut student_connection, "Yellow").await;
+
+    // Act
+    let history = app.get_room_history("room").await;
+
+    // Assert
+    let state = history.state;
+    let questions = state
+        .questions
+        .iter()
+        .map(|(id, question)| {
+            let mut answers = question.answers.values().copied().collect::<Vec<_>>();
+            answers.sort();
+            (
+                *id,
+                question.title.as_str(),
+                question.options.clone(),
+                answers,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        questions,
+        vec![
+            (
+                question1.id.0,
+                "question1",
+                vec!["a".into(), "b".into()],
+                vec![0]
+            ),
+            (
+                question2.id.0,
+                "new question2",
+                vec!["d".into(), "e".into()],
+                vec![0]
+            ),
+        ]
+    );
+    assert_eq!(
+        state.students.values().collect::<Vec<_>>(),
+        vec![&Some(SignalId("Yellow".into()))]
+    );
+}
+
+#[actix_rt::test]
+async fn folded_events_match_the_room_after_every_change() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    assert_folded_events_match_the_room(&app, "connect").await;
+
+    // Act
+    let question1 = create_question(&mut teacher_connection, "question1", &["a", "b"]).await;
+    let question2 = create_question(&mut teacher_connection, "question2", &["c", "d"]).await;
+    let question3 = create_question(&mut teacher_connection, "question3", &["e", "f"]).await;
+    assert_folded_events_match_the_room(&app, "create").await;
+    select_cup_color(&mut student_connection, "Yellow").await;
+    assert_folded_events_match_the_room(&app, "cup").await;
+    modify_question(
+        &mut teacher_connection,
+        question1.id.0,
+        Some("new question1"),
+        Some(vec!["a", "c"]),
+    )
+    .await;
+    assert_folded_events_match_the_room(&app, "modify").await;
+    let ids = [question3.id.0, question1.id.0, question2.id.0];
+    reorder_questions(&mut teacher_connection, &ids).await;
+    assert_folded_events_match_the_room(&app, "reorder").await;
+    set_playlist(&mut teacher_connection, &ids).await;
+    assert_folded_events_match_the_room(&app, "playlist").await;
+    playlist_step(&mut teacher_connection, "NextQuestion", 1).await;
+    assert_folded_events_match_the_room(&app, "next question").await;
+    publish_question(&mut teacher_connection, question1.id.0, 10).await;
+    assert_folded_events_match_the_room(&app, "publish").await;
+    answer_question(&mut student_connection, question1.id.0, 1).await;
+    answer_question(&mut student_connection, question2.id.0, 0).await;
+    assert_folded_events_match_the_room(&app, "answer").await;
+    tokio::time::sleep(Duration::from_millis(1200)).await;
+    assert_folded_events_match_the_room(&app, "close").await;
+    delete_question(&mut teacher_connection, question1.id.0).await;
+    assert_folded_events_match_the_room(&app, "delete").await;
+    drop(student_connection);
+    get_next_ws_msg(&mut teacher_connection).await;
+    assert_folded_events_match_the_room(&app, "leave").await;
+    let snapshot = app.get_room_snapshot("room").await;
+    app.restore_room(serde_json::json!({ "snapshot": snapshot, "on_conflict": "Replace" }))
+        .await;
+
+    // Assert
+    assert_folded_events_match_the_room(&app, "restore").await;
+    let history = app.get_room_history("room").await;
+    let mut titles = history
+        .room
+        .questions
+        .values()
+        .map(|question| question.title.as_str())
+        .collect::<Vec<_>>();
+    titles.sort();
+    assert_eq!(titles, vec!["question2", "question3"]);
+}
+
+#[actix_rt::test]
+async fn replay_streams_every_event_and_finishes() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    answer_question(&mut student_connection, question.id.0, 0).await;
+    select_cup_color(&mut student_connection, "Green").await;
+    let history = app.get_room_history("room").await;
+
+    // Act
+    let mut connection = app
+        .try_replay_connection("room", "speed=100")
+        .await
+        .expect("Failed to connect to the replay.");
+    let mut events = Vec::new();
+    let mut finished = false;
+    let close_code = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            match connection.next().await {
+                Some(Ok(Frame::Text(msg))) => {
+                    match serde_json::from_slice::<ClientMessage>(&msg).unwrap() {
+                        ClientMessage::ReplayEvent(record) => events.push(record),
+                        ClientMessage::ReplayFinished => finished = true,
+                        msg => panic!("Invalid msg: {msg:?}"),
+                    }
+                }
+                Some(Ok(Frame::Close(reason))) => return reason.map(|reason| reason.code),
+                Some(_) => {}
+                None => return None,
+            }
+        }
+    })
+    .await
+    .expect("Replay did not finish.");
+
+    // Assert
+    assert_eq!(events, history.events);
+    assert!(finished);
+    assert_eq!(close_code, Some(CloseCode::Normal));
+}
+
+#[actix_rt::test]
+async fn replay_rejects_invalid_speeds_and_rooms() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+
+    // Act
+    let results = [
+        app.try_replay_connection("room", "speed=0").await,
+        app.try_replay_connection("room", "speed=-2").await,
+        app.try_replay_connection("room", "speed=NaN").await,
+        app.try_replay_connection("room", "speed=1e-300").await,
+        app.try_replay_connection("room", "speed=1000").await,
+        app.try_replay_connection("other", "").await,
+    ];
+
+    // Assert
+    for result in results {
+        assert!(result.is_err());
+    }
+    assert!(app.try_replay_connection("room", "").await.is_ok());
+}
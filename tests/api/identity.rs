@@ -0,0 +1,175 @@
+use crate::helpers::{get_next_ws_msg, select_cup_color, spawn_app};
+use interactive_class::routes::message::{ClientMessage, ConnectionType, SignalId};
+
+#[actix_rt::test]
+async fn nickname_rooms_require_a_display_name() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    app.set_identity_mode("room", "Nickname").await;
+
+    // Act
+    let (_, msg, _) = app
+        .get_ws_room_connection("room", ConnectionType::Student)
+        .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "A display name is required."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn teachers_see_the_cup_of_each_student() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    app.set_identity_mode("room", "Nickname").await;
+    let (mut teacher_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    // Empty students detail
+    get_next_ws_msg(&mut teacher_connection).await;
+    let (mut student_connection, _, _) = app
+        .get_ws_room_connection_with(
+            "room",
+            ConnectionType::Student,
+            serde_json::json!({ "display_name": " Ana " }),
+        )
+        .await;
+    // Room info and students detail of the new student
+    get_next_ws_msg(&mut teacher_connection).await;
+    get_next_ws_msg(&mut teacher_connection).await;
+
+    // Act
+    select_cup_color(&mut student_connection, "Red").await;
+    get_next_ws_msg(&mut teacher_connection).await;
+    let msg = get_next_ws_msg(&mut teacher_connection).await;
+
+    // Assert
+    match msg {
+        ClientMessage::StudentsInfo(students) => {
+            assert_eq!(students.len(), 1);
+            assert_eq!(students[0].name, "Ana");
+            assert_eq!(students[0].cup, Some(SignalId("Red".to_string())));
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn roster_rooms_reject_unknown_students() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    app.set_identity_mode("room", "Roster").await;
+    app.upload_roster("room", "id,name\n001,Ana\n002,Luis\n")
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let (_, msg, _) = app
+        .get_ws_room_connection_with(
+            "room",
+            ConnectionType::Student,
+            serde_json::json!({ "student_id": "003" }),
+        )
+        .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Student id \"003\" is not in the roster."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn roster_ids_can_only_be_used_by_one_connection() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    app.set_identity_mode("room", "Roster").await;
+    app.upload_roster("room", "id,name\n001,Ana\n002,Luis\n")
+        .await
+        .error_for_status()
+        .unwrap();
+    let (student_connection, _, _) = app
+        .get_ws_room_connection_with(
+            "room",
+            ConnectionType::Student,
+            serde_json::json!({ "student_id": "001" }),
+        )
+        .await;
+
+    // Act
+    let (_, msg, _) = app
+        .get_ws_room_connection_with(
+            "room",
+            ConnectionType::Student,
+            serde_json::json!({ "student_id": " 001 " }),
+        )
+        .await;
+    drop(student_connection);
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    let (_, reconnect_msg, _) = app
+        .get_ws_room_connection_with(
+            "room",
+            ConnectionType::Student,
+            serde_json::json!({ "student_id": "001" }),
+        )
+        .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Student id \"001\" is already connected."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    assert!(matches!(reconnect_msg, ClientMessage::RoomInfo(_)));
+}
+
+#[actix_rt::test]
+async fn roster_students_get_their_roster_name() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    app.set_identity_mode("room", "Roster").await;
+    app.upload_roster("room", "id,name\n001,Ana\n002\n").await;
+    let (mut teacher_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    get_next_ws_msg(&mut teacher_connection).await;
+
+    // Act
+    app.get_ws_room_connection_with(
+        "room",
+        ConnectionType::Student,
+        serde_json::json!({ "student_id": "001", "display_name": "Other" }),
+    )
+    .await;
+    get_next_ws_msg(&mut teacher_connection).await;
+    let msg = get_next_ws_msg(&mut teacher_connection).await;
+
+    // Assert
+    match msg {
+        ClientMessage::StudentsInfo(students) => {
+            assert_eq!(students[0].name, "Ana");
+            assert_eq!(students[0].student_id.as_deref(), Some("001"));
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[tokio::test]
+async fn upload_roster_fails_without_id_column() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+
+    // Act
+    let response = app.upload_roster("room", "name\nAna\n").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
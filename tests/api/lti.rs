@@ -0,0 +1,385 @@
+use crate::helpers::{
+    answer_question, create_quiz_question, get_next_ws_msg, lti_id_token, lti_settings, room_names,
+    send_ws_msg, spawn_app_with, TestApp, LTI_INSTRUCTOR, LTI_JWKS, LTI_LEARNER,
+    LTI_TEACHING_ASSISTANT,
+};
+use base64::Engine;
+use interactive_class::{
+    audit::AuditRole,
+    routes::message::{ClientMessage, ConnectionType},
+};
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const LINEITEM: &str = "http://localhost/lineitem";
+
+/// Spawns the app with a mock LTI platform serving its JWKS
+async fn spawn_app_with_lti() -> (TestApp, MockServer) {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/jwks"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(LTI_JWKS, "application/json"))
+        .mount(&mock_server)
+        .await;
+    let app = spawn_app_with(|c| c.lti = Some(lti_settings(&mock_server))).await;
+    (app, mock_server)
+}
+
+/// Gets the launch id from the redirection url
+fn launch_id(location: &str) -> String {
+    location.split("launch=").last().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn lti_login_redirects_to_platform() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_lti().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/lti/login", &app.address))
+        .query(&[("iss", crate::helpers::LTI_ISSUER), ("login_hint", "user")])
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 302);
+    let location = response
+        .headers()
+        .get("Location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(location.starts_with(&format!("{}/auth?", mock_server.uri())));
+}
+
+#[tokio::test]
+async fn lti_login_fails_with_unknown_issuer() {
+    // Arrange
+    let (app, _mock_server) = spawn_app_with_lti().await;
+
+    // Act
+    let error_msg = app
+        .api_client
+        .get(format!("{}/lti/login", &app.address))
+        .query(&[("iss", "https://other.com"), ("login_hint", "user")])
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(error_msg, "Invalid LTI issuer: \"https://other.com\".");
+}
+
+#[tokio::test]
+async fn lti_launch_redirects_learner_to_student_view() {
+    // Arrange
+    let (app, _mock_server) = spawn_app_with_lti().await;
+
+    // Act
+    let location = app
+        .lti_login_and_launch("student", LTI_LEARNER, "101", LINEITEM)
+        .await;
+
+    // Assert
+    assert!(location.starts_with("/room/Course%20101?launch="));
+}
+
+#[tokio::test]
+async fn lti_launch_redirects_instructor_to_teacher_view() {
+    // Arrange
+    let (app, _mock_server) = spawn_app_with_lti().await;
+
+    // Act
+    let location = app
+        .lti_login_and_launch("teacher", LTI_INSTRUCTOR, "101", LINEITEM)
+        .await;
+
+    // Assert
+    assert!(location.starts_with("/room/Course%20101/teacher?launch="));
+}
+
+#[tokio::test]
+async fn lti_launch_redirects_teaching_assistant_to_assistant_view() {
+    // Arrange
+    let (app, _mock_server) = spawn_app_with_lti().await;
+
+    // Act
+    let location = app
+        .lti_login_and_launch("assistant", LTI_TEACHING_ASSISTANT, "101", LINEITEM)
+        .await;
+
+    // Assert
+    assert!(location.starts_with("/room/Course%20101/assistant?launch="));
+}
+
+#[tokio::test]
+async fn lti_launches_share_the_room_of_the_context() {
+    // Arrange
+    let (app, _mock_server) = spawn_app_with_lti().await;
+
+    // Act
+    app.lti_login_and_launch("teacher", LTI_INSTRUCTOR, "101", LINEITEM)
+        .await;
+    app.lti_login_and_launch("student", LTI_LEARNER, "101", LINEITEM)
+        .await;
+    app.lti_login_and_launch("student", LTI_LEARNER, "102", LINEITEM)
+        .await;
+    let cups_info = app.get_cups_info().await;
+
+    // Assert
+    assert_eq!(room_names(&cups_info), ["Course 101", "Course 102"]);
+}
+
+#[tokio::test]
+async fn lti_launch_fails_with_unknown_state() {
+    // Arrange
+    let (app, _mock_server) = spawn_app_with_lti().await;
+    let (_, nonce) = app.lti_login().await;
+    let id_token = lti_id_token(&nonce, "student", LTI_LEARNER, "101", LINEITEM);
+
+    // Act
+    let error_msg = app
+        .lti_launch("unknown", &id_token)
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(error_msg, "Invalid LTI state.");
+}
+
+#[tokio::test]
+async fn lti_launch_fails_with_invalid_nonce() {
+    // Arrange
+    let (app, _mock_server) = spawn_app_with_lti().await;
+    let (state, _) = app.lti_login().await;
+    let id_token = lti_id_token("other nonce", "student", LTI_LEARNER, "101", LINEITEM);
+
+    // Act
+    let error_msg = app
+        .lti_launch(&state, &id_token)
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(error_msg, "Invalid LTI nonce.");
+}
+
+#[tokio::test]
+async fn lti_launch_fails_with_invalid_signature() {
+    // Arrange
+    let (app, _mock_server) = spawn_app_with_lti().await;
+    let (state, nonce) = app.lti_login().await;
+    let id_token = lti_id_token(&nonce, "student", LTI_LEARNER, "101", LINEITEM);
+    let other_token = lti_id_token(&nonce, "teacher", LTI_INSTRUCTOR, "101", LINEITEM);
+    // Use the claims of a token with the signature of other
+    let mut parts = id_token.split('.').collect::<Vec<_>>();
+    parts[2] = other_token.rsplit('.').next().unwrap();
+    let id_token = parts.join(".");
+
+    // Act
+    let response = app.lti_launch(&state, &id_token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(response.text().await.unwrap(), "Invalid LTI id token.");
+}
+
+#[tokio::test]
+async fn unknown_signing_keys_do_not_fetch_the_jwks_every_time() {
+    // Arrange
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/jwks"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(LTI_JWKS, "application/json"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let app = spawn_app_with(|c| c.lti = Some(lti_settings(&mock_server))).await;
+    let header = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .encode(r#"{"typ":"JWT","alg":"RS256","kid":"unknown-key"}"#);
+
+    for _ in 0..3 {
+        let (state, nonce) = app.lti_login().await;
+        let id_token = lti_id_token(&nonce, "student", LTI_LEARNER, "101", LINEITEM);
+        let mut parts = id_token.split('.').collect::<Vec<_>>();
+        parts[0] = &header;
+        let id_token = parts.join(".");
+
+        // Act
+        let response = app.lti_launch(&state, &id_token).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 400);
+        assert_eq!(
+            response.text().await.unwrap(),
+            "Unknown signing key: Some(\"unknown-key\")."
+        );
+    }
+}
+
+#[actix_rt::test]
+async fn students_connect_to_the_room_with_their_launch() {
+    // Arrange
+    let (app, _mock_server) = spawn_app_with_lti().await;
+    let location = app
+        .lti_login_and_launch("student", LTI_LEARNER, "101", LINEITEM)
+        .await;
+
+    // Act
+    let (_connection, room_info, _) = app
+        .get_ws_room_connection_with(
+            "Course 101",
+            ConnectionType::Student,
+            serde_json::json!({ "lti_launch": launch_id(&location) }),
+        )
+        .await;
+
+    // Assert
+    match room_info {
+        ClientMessage::RoomInfo(msg) => assert_eq!(msg.connections, 1),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn launches_can_only_be_used_once() {
+    // Arrange
+    let (app, _mock_server) = spawn_app_with_lti().await;
+    let location = app
+        .lti_login_and_launch("student", LTI_LEARNER, "101", LINEITEM)
+        .await;
+    let room_connect = serde_json::json!({
+        "task": "RoomConnect",
+        "payload": {
+            "room_name": "Course 101",
+            "connection_type": "Student",
+            "lti_launch": launch_id(&location)
+        }
+    });
+    let mut connection = app.get_ws_connection().await;
+    send_ws_msg(&mut connection, room_connect.clone()).await;
+
+    // Act
+    let mut other_connection = app.get_ws_connection().await;
+    let msg = send_ws_msg(&mut other_connection, room_connect).await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Invalid LTI launch."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn students_cannot_connect_as_teachers_with_their_launch() {
+    // Arrange
+    let (app, _mock_server) = spawn_app_with_lti().await;
+    let location = app
+        .lti_login_and_launch("student", LTI_LEARNER, "101", LINEITEM)
+        .await;
+
+    // Act
+    let mut connection = app.get_ws_connection().await;
+    let msg = send_ws_msg(
+        &mut connection,
+        serde_json::json!({
+            "task": "RoomConnect",
+            "payload": {
+                "room_name": "Course 101",
+                "connection_type": "Teacher",
+                "lti_launch": launch_id(&location)
+            }
+        }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Invalid LTI launch."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn teachers_can_submit_grades() {
+    // Arrange
+    let (app, mock_server) = spawn_app_with_lti().await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({ "access_token": "token" })),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/lineitem/scores"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let lineitem = format!("{}/lineitem", mock_server.uri());
+    let teacher_location = app
+        .lti_login_and_launch("teacher", LTI_INSTRUCTOR, "101", &lineitem)
+        .await;
+    let student_location = app
+        .lti_login_and_launch("student", LTI_LEARNER, "101", &lineitem)
+        .await;
+    let (mut teacher_connection, _, _) = app
+        .get_ws_room_connection_with(
+            "Course 101",
+            ConnectionType::Teacher,
+            serde_json::json!({ "lti_launch": launch_id(&teacher_location) }),
+        )
+        .await;
+    let (mut student_connection, _, _) = app
+        .get_ws_room_connection_with(
+            "Course 101",
+            ConnectionType::Student,
+            serde_json::json!({ "lti_launch": launch_id(&student_location) }),
+        )
+        .await;
+    get_next_ws_msg(&mut teacher_connection).await;
+    let question = create_quiz_question(&mut teacher_connection, "question", &["a", "b"], 1).await;
+    answer_question(&mut student_connection, question.id.0, 1).await;
+    get_next_ws_msg(&mut teacher_connection).await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut teacher_connection,
+        serde_json::json!({ "task": "SubmitGrades" }),
+    )
+    .await;
+
+    // Assert
+    assert!(matches!(msg, ClientMessage::Ok), "{msg:?}");
+    let requests = mock_server.received_requests().await.unwrap();
+    let score = requests
+        .iter()
+        .find(|request| request.url.path() == "/lineitem/scores")
+        .unwrap();
+    let score = serde_json::from_slice::<serde_json::Value>(&score.body).unwrap();
+    assert_eq!(score["userId"], "student");
+    assert_eq!(score["scoreGiven"], 1.0);
+    let trail = app.get_audit_trail("Course 101", "").await;
+    let submission = trail.entries.last().unwrap();
+    assert_eq!(submission.action, "SubmitGrades");
+    assert_eq!(submission.role, AuditRole::Teacher);
+    assert_eq!(
+        submission.diff["grades"]["after"],
+        serde_json::json!({ "student": 1.0 })
+    );
+}
@@ -1,5 +1,25 @@
+mod admin;
+mod alerts;
+mod audit;
+mod auth;
+mod connections;
 mod cups;
+mod hands;
 mod health_check;
 mod heartbeat;
 mod helpers;
+mod history;
+mod identity;
+mod lti;
+mod origins;
+mod playlist;
 mod questions;
+mod rate_limit;
+mod roles;
+mod shutdown;
+mod snapshot;
+mod templates;
+mod timeline;
+mod tls;
+mod validation;
+mod webhooks;
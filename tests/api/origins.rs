@@ -0,0 +1,90 @@
+use crate::helpers::{spawn_app, spawn_app_with};
+use awc::error::WsClientError;
+use interactive_class::configuration::{AllowedOrigins, Settings};
+
+fn allow_class_origin(c: &mut Settings) {
+    c.application.allowed_origins = AllowedOrigins(vec!["https://class.example.com".to_string()]);
+}
+
+#[actix_rt::test]
+async fn ws_connections_from_not_allowed_origins_are_refused() {
+    // Arrange
+    let app = spawn_app_with(allow_class_origin).await;
+
+    // Act
+    let result = app.try_ws_connection_from("https://evil.example.com").await;
+
+    // Assert
+    match result {
+        Err(WsClientError::InvalidResponseStatus(status)) => assert_eq!(status.as_u16(), 403),
+        _ => panic!("The connection was not refused with 403."),
+    }
+}
+
+#[actix_rt::test]
+async fn ws_connections_from_allowed_origins_work() {
+    // Arrange
+    let app = spawn_app_with(allow_class_origin).await;
+    let same_origin = app.address.clone();
+
+    for origin in ["https://class.example.com", same_origin.as_str()] {
+        // Act
+        let result = app.try_ws_connection_from(origin).await;
+
+        // Assert
+        assert!(result.is_ok(), "The connection from {origin} was refused.");
+    }
+}
+
+#[tokio::test]
+async fn cors_preflight_allows_the_allowed_origins() {
+    // Arrange
+    let app = spawn_app_with(allow_class_origin).await;
+
+    // Act
+    let response = app
+        .api_client
+        .request(
+            reqwest::Method::OPTIONS,
+            format!("{}/cups/create_room", app.address),
+        )
+        .header("Origin", "https://class.example.com")
+        .header("Access-Control-Request-Method", "POST")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers()["Access-Control-Allow-Origin"],
+        "https://class.example.com"
+    );
+    assert_eq!(
+        response.headers()["Access-Control-Allow-Credentials"],
+        "true"
+    );
+}
+
+#[tokio::test]
+async fn cors_requests_from_other_origins_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/cups/create_room", app.address))
+        .header("Origin", "https://evil.example.com")
+        .json(&serde_json::json!({ "new_room": "room" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response
+        .headers()
+        .get("Access-Control-Allow-Origin")
+        .is_none());
+    assert!(app.get_cups_info().await.rooms.is_empty());
+}
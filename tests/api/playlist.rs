@@ -0,0 +1,211 @@
+use crate::helpers::{
+    create_question, get_next_ws_msg, playlist_step, send_ws_msg, set_playlist, spawn_app,
+};
+use interactive_class::routes::message::{ClientMessage, ConnectionType};
+use std::time::Duration;
+
+#[actix_rt::test]
+async fn next_question_publishes_the_playlist_in_order() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let first = create_question(&mut teacher_connection, "first", &["a", "b"]).await;
+    let second = create_question(&mut teacher_connection, "second", &["a", "b"]).await;
+    set_playlist(&mut teacher_connection, &[second.id.0, first.id.0]).await;
+
+    // Act
+    playlist_step(&mut teacher_connection, "NextQuestion", 10).await;
+    let first_msg = get_next_ws_msg(&mut student_connection).await;
+    playlist_step(&mut teacher_connection, "NextQuestion", 10).await;
+    let second_msg = get_next_ws_msg(&mut student_connection).await;
+
+    // Assert
+    match (first_msg, second_msg) {
+        (
+            ClientMessage::QuestionPublication(first_publication),
+            ClientMessage::QuestionPublication(second_publication),
+        ) => {
+            assert_eq!(first_publication.title, "second");
+            assert_eq!(second_publication.title, "first");
+        }
+        msgs => panic!("Invalid msgs: {msgs:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn previous_question_publishes_the_previous_playlist_question() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let first = create_question(&mut teacher_connection, "first", &["a", "b"]).await;
+    let second = create_question(&mut teacher_connection, "second", &["a", "b"]).await;
+    set_playlist(&mut teacher_connection, &[first.id.0, second.id.0]).await;
+    playlist_step(&mut teacher_connection, "NextQuestion", 10).await;
+    get_next_ws_msg(&mut teacher_connection).await;
+    playlist_step(&mut teacher_connection, "NextQuestion", 10).await;
+    get_next_ws_msg(&mut teacher_connection).await;
+    get_next_ws_msg(&mut student_connection).await;
+    get_next_ws_msg(&mut student_connection).await;
+
+    // Act
+    playlist_step(&mut teacher_connection, "PreviousQuestion", 10).await;
+    let student_msg = get_next_ws_msg(&mut student_connection).await;
+    let teacher_msg = get_next_ws_msg(&mut teacher_connection).await;
+
+    // Assert
+    match student_msg {
+        ClientMessage::QuestionPublication(publication) => {
+            assert_eq!(publication.title, "first")
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    match teacher_msg {
+        ClientMessage::Playlist(playlist) => assert_eq!(playlist.position, Some(0)),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn next_question_fails_at_the_end_of_the_playlist() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, _student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    set_playlist(&mut teacher_connection, &[question.id.0]).await;
+    playlist_step(&mut teacher_connection, "NextQuestion", 10).await;
+    get_next_ws_msg(&mut teacher_connection).await;
+
+    // Act
+    let msg = playlist_step(&mut teacher_connection, "NextQuestion", 10).await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "No more questions in the playlist."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn set_playlist_rejects_repeated_questions() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, _student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+
+    // Act
+    let msg = set_playlist(&mut teacher_connection, &[question.id.0, question.id.0]).await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(
+            msg,
+            format!("Invalid playlist: question {} is repeated.", question.id.0)
+        ),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn scheduled_questions_are_published_after_the_delay() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut teacher_connection,
+        serde_json::json!({
+            "task": "ScheduleQuestion",
+            "payload": { "id": question.id.0, "secs": 10, "delay_secs": 1 }
+        }),
+    )
+    .await;
+    let student_msg = get_next_ws_msg(&mut student_connection).await;
+
+    // Assert
+    match msg {
+        ClientMessage::Playlist(playlist) => {
+            assert_eq!(playlist.scheduled.len(), 1);
+            assert_eq!(playlist.scheduled[0].id, question.id);
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    match student_msg {
+        ClientMessage::QuestionPublication(publication) => {
+            assert_eq!(publication.id, question.id);
+            assert_eq!(publication.secs, 10);
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn cancelled_schedules_are_not_published() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    send_ws_msg(
+        &mut teacher_connection,
+        serde_json::json!({
+            "task": "ScheduleQuestion",
+            "payload": { "id": question.id.0, "secs": 10, "delay_secs": 1 }
+        }),
+    )
+    .await;
+
+    // Act
+    send_ws_msg(
+        &mut teacher_connection,
+        serde_json::json!({ "task": "CancelSchedule", "payload": question.id.0 }),
+    )
+    .await;
+
+    // Assert
+    tokio::select! {
+        msg = get_next_ws_msg(&mut student_connection) => panic!("Unexpected msg: {msg:?}"),
+        _ = tokio::time::sleep(Duration::from_millis(1500)) => {}
+    }
+}
+
+#[actix_rt::test]
+async fn schedule_question_fails_with_a_too_big_delay() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut teacher_connection,
+        serde_json::json!({
+            "task": "ScheduleQuestion",
+            "payload": { "id": question.id.0, "secs": 10, "delay_secs": u64::MAX }
+        }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => {
+            assert_eq!(msg, "Invalid schedule: `delay_secs` is too big.")
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
@@ -1,9 +1,14 @@
 use crate::helpers::{
     answer_question, create_question, delete_question, get_next_ws_msg, modify_question,
-    publish_question, spawn_app,
+    publish_question, reorder_questions, send_ws_msg, spawn_app,
 };
 use futures::SinkExt;
-use interactive_class::routes::message::{ClientMessage, ConnectionType};
+use interactive_class::routes::message::{ClientMessage, ConnectionType, QuestionInfo};
+use uuid::Uuid;
+
+fn titles(questions: &[QuestionInfo]) -> Vec<&str> {
+    questions.iter().map(|q| q.title.as_str()).collect()
+}
 
 #[actix_rt::test]
 async fn create_question_works() {
@@ -126,10 +131,14 @@ async fn modify_questions_works() {
     let options = vec!["option1", "option2", "option3"];
     let test_cases = vec![
         (Some("new title"), None, "new title"),
-        (None, Some(vec!["an option"]), "new options"),
+        (
+            None,
+            Some(vec!["an option", "another option"]),
+            "new options",
+        ),
         (
             Some("new title"),
-            Some(vec!["an option"]),
+            Some(vec!["an option", "another option"]),
             "new title and options",
         ),
     ];
@@ -311,3 +320,194 @@ async fn answers_get_deleted_when_student_disconnects() {
         msg => panic!("Invalid msg: {msg:?}"),
     }
 }
+
+#[actix_rt::test]
+async fn students_cannot_create_questions() {
+    // Arrange
+    let app = spawn_app().await;
+    let room_name = "test_room";
+    app.create_cups_room(room_name).await;
+    let (mut connection, _, _) = app
+        .get_ws_room_connection(room_name, ConnectionType::Student)
+        .await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut connection,
+        serde_json::json!({
+            "task": "CreateQuestion",
+            "payload": { "title": "test question", "options": ["option1", "option2"] }
+        }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Student connections can't do this."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn teachers_can_share_results_with_students() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    answer_question(&mut student_connection, question.id.0, 1).await;
+    get_next_ws_msg(&mut teacher_connection).await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut teacher_connection,
+        serde_json::json!({
+            "task": "ShareResults",
+            "payload": { "id": question.id.0, "mode": "Both" }
+        }),
+    )
+    .await;
+
+    // Assert
+    assert!(matches!(msg, ClientMessage::Ok), "{msg:?}");
+    match get_next_ws_msg(&mut student_connection).await {
+        ClientMessage::QuestionResults(results) => {
+            assert_eq!(results.title, "question");
+            assert_eq!(results.counts, Some(vec![0, 1]));
+            assert_eq!(results.percentages, Some(vec![0.0, 100.0]));
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn results_are_shared_when_the_publication_ends() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    app.set_auto_share_results("room", "Percentages")
+        .await
+        .error_for_status()
+        .unwrap();
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+
+    // Act
+    publish_question(&mut teacher_connection, question.id.0, 1).await;
+    get_next_ws_msg(&mut student_connection).await;
+    answer_question(&mut student_connection, question.id.0, 0).await;
+
+    // Assert
+    match get_next_ws_msg(&mut student_connection).await {
+        ClientMessage::QuestionResults(results) => {
+            assert_eq!(results.counts, None);
+            assert_eq!(results.percentages, Some(vec![100.0, 0.0]));
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn students_cannot_share_results() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut student_connection,
+        serde_json::json!({
+            "task": "ShareResults",
+            "payload": { "id": question.id.0, "mode": "Counts" }
+        }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Student connections can't do this."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn questions_come_in_creation_order() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    for title in ["a", "b", "c", "d"] {
+        create_question(&mut teacher_connection, title, &["1", "2"]).await;
+    }
+
+    // Act
+    let (_, _, questions_info) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+
+    // Assert
+    match questions_info {
+        Some(ClientMessage::QuestionsInfo(questions)) => {
+            assert_eq!(titles(&questions), ["a", "b", "c", "d"]);
+            assert!(questions
+                .windows(2)
+                .all(|w| w[0].created_at <= w[1].created_at));
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn reorder_questions_moves_them_into_place() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    let a = create_question(&mut teacher_connection, "a", &["1", "2"]).await;
+    create_question(&mut teacher_connection, "b", &["1", "2"]).await;
+    let c = create_question(&mut teacher_connection, "c", &["1", "2"]).await;
+
+    // Act
+    let msg = reorder_questions(&mut teacher_connection, &[c.id.0, a.id.0]).await;
+
+    // Assert
+    match msg {
+        ClientMessage::QuestionsInfo(questions) => {
+            assert_eq!(titles(&questions), ["c", "a", "b"])
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn reorder_questions_rejects_unknown_questions() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    create_question(&mut teacher_connection, "a", &["1", "2"]).await;
+    let unknown = Uuid::new_v4();
+
+    // Act
+    let msg = reorder_questions(&mut teacher_connection, &[unknown]).await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(
+            msg,
+            format!("Invalid question order: unknown question {unknown}.")
+        ),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
@@ -0,0 +1,133 @@
+use crate::helpers::{select_cup_color, send_ws_msg, spawn_app_with};
+use awc::ws::{CloseCode, Frame, Message};
+use futures::{SinkExt, StreamExt};
+use interactive_class::{
+    routes::message::{ClientMessage, ConnectionType},
+    telemetry::MetricsInfo,
+};
+use std::time::Duration;
+
+#[actix_rt::test]
+async fn messages_over_the_session_rate_limit_are_rejected() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.websocket.session_rate_limit.per_second = 0.0;
+        c.websocket.session_rate_limit.burst = 3;
+    })
+    .await;
+    app.create_cups_room("room").await;
+    let (mut connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Student)
+        .await;
+    select_cup_color(&mut connection, "Green").await;
+    select_cup_color(&mut connection, "Yellow").await;
+
+    // Act
+    let msg = select_cup_color(&mut connection, "Red").await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Too many messages, slow down."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn messages_over_the_ip_rate_limit_are_rejected() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.websocket.ip_rate_limit.per_second = 0.0;
+        c.websocket.ip_rate_limit.burst = 3;
+    })
+    .await;
+    app.create_cups_room("room").await;
+    let (mut first_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Student)
+        .await;
+    let (mut second_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Student)
+        .await;
+    select_cup_color(&mut first_connection, "Green").await;
+
+    // Act
+    let msg = select_cup_color(&mut second_connection, "Green").await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Too many messages, slow down."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn oversized_messages_are_rejected() {
+    // Arrange
+    let app = spawn_app_with(|c| c.websocket.max_message_size = 200).await;
+    app.create_cups_room("room").await;
+    let (mut connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut connection,
+        serde_json::json!({
+            "task": "CreateQuestion",
+            "payload": { "title": "a".repeat(200), "options": ["a", "b"] }
+        }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Messages can't be larger than 200 bytes."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn clients_breaking_the_limits_are_disconnected() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.websocket.session_rate_limit.per_second = 0.0;
+        c.websocket.session_rate_limit.burst = 1;
+        c.websocket.max_rejected_messages = 1;
+    })
+    .await;
+    app.create_cups_room("room").await;
+    let (mut connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Student)
+        .await;
+    select_cup_color(&mut connection, "Green").await;
+
+    // Act
+    connection
+        .send(Message::Text("{}".into()))
+        .await
+        .expect("Failed to send message.");
+    let close_frame = tokio::time::timeout(Duration::from_millis(500), async {
+        loop {
+            match connection.next().await {
+                Some(Ok(Frame::Close(reason))) => return reason,
+                Some(_) => {}
+                None => return None,
+            }
+        }
+    })
+    .await
+    .expect("Server did not disconnect.");
+    let metrics = app
+        .get_route("metrics")
+        .await
+        .json::<MetricsInfo>()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        close_frame.map(|reason| reason.code),
+        Some(CloseCode::Policy)
+    );
+    assert_eq!(metrics.throttled_messages, 2);
+    assert_eq!(metrics.throttled_disconnections, 1);
+}
@@ -0,0 +1,162 @@
+use crate::helpers::{
+    answer_question, create_question, create_quiz_question, delete_question, get_next_ws_msg,
+    select_cup_color, send_ws_msg, spawn_app,
+};
+use interactive_class::routes::message::{ClientMessage, ConnectionType};
+
+#[actix_rt::test]
+async fn assistants_can_create_questions() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    let (mut assistant_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Assistant)
+        .await;
+
+    // Act
+    let question = create_question(&mut assistant_connection, "question", &["a", "b"]).await;
+
+    // Assert
+    assert_eq!(question.title, "question");
+    match get_next_ws_msg(&mut teacher_connection).await {
+        ClientMessage::QuestionsInfo(questions) => assert_eq!(questions.len(), 1),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn assistants_cannot_delete_questions() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut assistant_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Assistant)
+        .await;
+    let question = create_question(&mut assistant_connection, "question", &["a", "b"]).await;
+
+    // Act
+    let msg = delete_question(&mut assistant_connection, question.id.0).await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Assistant connections can't do this."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn projectors_cannot_create_questions() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut projector_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Projector)
+        .await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut projector_connection,
+        serde_json::json!({
+            "task": "CreateQuestion",
+            "payload": { "title": "question", "options": ["a", "b"] }
+        }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Projector connections can't do this."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn projectors_get_cups_updates() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut projector_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Projector)
+        .await;
+    let (mut student_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Student)
+        .await;
+    get_next_ws_msg(&mut projector_connection).await;
+
+    // Act
+    select_cup_color(&mut student_connection, "Red").await;
+    let msg = get_next_ws_msg(&mut projector_connection).await;
+
+    // Assert
+    match msg {
+        ClientMessage::RoomInfo(msg) => assert_eq!(msg.cups["Red"], 1),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn projectors_get_the_answer_counts_but_not_the_correct_options() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_quiz_question(&mut teacher_connection, "question", &["a", "b"], 1).await;
+    answer_question(&mut student_connection, question.id.0, 1).await;
+
+    // Act
+    let (mut projector_connection, _, questions_info) = app
+        .get_ws_room_connection("room", ConnectionType::Projector)
+        .await;
+    answer_question(&mut student_connection, question.id.0, 0).await;
+    let msg = get_next_ws_msg(&mut projector_connection).await;
+
+    // Assert
+    for (msg, answers) in [(questions_info.unwrap(), vec![0, 1]), (msg, vec![1, 0])] {
+        match msg {
+            ClientMessage::QuestionsInfo(questions) => {
+                assert_eq!(questions[0].title, "question");
+                assert_eq!(questions[0].correct, None);
+                assert_eq!(questions[0].answers, answers);
+            }
+            msg => panic!("Invalid msg: {msg:?}"),
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn projectors_get_the_shared_results() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_quiz_question(&mut teacher_connection, "question", &["a", "b"], 1).await;
+    answer_question(&mut student_connection, question.id.0, 1).await;
+    let (mut projector_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Projector)
+        .await;
+
+    // Act
+    send_ws_msg(
+        &mut teacher_connection,
+        serde_json::json!({
+            "task": "ShareResults",
+            "payload": { "id": question.id.0, "mode": "Counts" }
+        }),
+    )
+    .await;
+    let msg = get_next_ws_msg(&mut projector_connection).await;
+
+    // Assert
+    match msg {
+        ClientMessage::QuestionResults(results) => {
+            assert_eq!(results.counts, Some(vec![0, 1]));
+            assert_eq!(results.correct, Some(1));
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
@@ -0,0 +1,109 @@
+use crate::helpers::{answer_question, create_question, get_next_ws_msg, spawn_app_with};
+use futures::StreamExt;
+use interactive_class::{
+    configuration::PersistenceSettings,
+    persistence::StoredState,
+    routes::message::{ClientMessage, ConnectionType},
+};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+fn rooms_file() -> PathBuf {
+    std::env::temp_dir().join(format!("interactive_class-{}.json", Uuid::new_v4()))
+}
+
+#[actix_rt::test]
+async fn clients_are_notified_on_shutdown() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.application.shutdown.reconnect_after = Duration::from_millis(3000);
+    })
+    .await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+
+    // Act
+    app.shutdown.shutdown();
+    let teacher_msg = get_next_ws_msg(&mut teacher_connection).await;
+    let student_msg = get_next_ws_msg(&mut student_connection).await;
+
+    // Assert
+    for msg in [teacher_msg, student_msg] {
+        match msg {
+            ClientMessage::ServerShutdown { reconnect_after } => {
+                assert_eq!(reconnect_after, 3000)
+            }
+            msg => panic!("Invalid msg: {msg:?}"),
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn the_server_stops_after_the_drain_timeout() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.application.shutdown.drain_timeout = Duration::from_millis(300);
+    })
+    .await;
+    let mut connection = app.get_ws_connection().await;
+    let start = Instant::now();
+
+    // Act
+    app.shutdown.shutdown();
+    let closed = tokio::time::timeout(Duration::from_millis(1500), async {
+        while let Some(Ok(_)) = connection.next().await {}
+    })
+    .await;
+
+    // Assert
+    assert!(closed.is_ok(), "The connection was not closed.");
+    assert!(start.elapsed() >= Duration::from_millis(300));
+}
+
+#[actix_rt::test]
+async fn rooms_are_saved_on_shutdown_and_loaded_on_start() {
+    // Arrange
+    let path = rooms_file();
+    let persistence = PersistenceSettings {
+        path: path.to_string_lossy().into_owned(),
+    };
+    let configure = |persistence: PersistenceSettings| {
+        move |c: &mut interactive_class::configuration::Settings| {
+            c.persistence = Some(persistence);
+            c.application.shutdown.drain_timeout = Duration::from_millis(100);
+        }
+    };
+    let app = spawn_app_with(configure(persistence.clone())).await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    answer_question(&mut student_connection, question.id.0, 1).await;
+
+    // Act
+    app.shutdown.shutdown();
+    get_next_ws_msg(&mut student_connection).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let stored: StoredState = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    let new_app = spawn_app_with(configure(persistence)).await;
+    let (_connection, _, questions_info) = new_app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+
+    // Assert
+    assert_eq!(stored.rooms.len(), 1);
+    assert_eq!(stored.rooms[0].name, "room");
+    match questions_info {
+        Some(ClientMessage::QuestionsInfo(info)) => {
+            assert_eq!(info.len(), 1);
+            assert_eq!(info[0].title, "question");
+            assert_eq!(info[0].answers, vec![0, 1]);
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    std::fs::remove_file(path).unwrap();
+}
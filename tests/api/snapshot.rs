@@ -0,0 +1,235 @@
+use crate::helpers::{answer_question, create_question, get_next_ws_msg, spawn_app, TestApp};
+use interactive_class::{
+    history::RoomEvent,
+    routes::{message::ClientMessage, RestoredRoom},
+    state::RoomSnapshot,
+};
+use std::time::Duration;
+use uuid::Uuid;
+
+type Connection = actix_codec::Framed<awc::BoxedSocket, awc::ws::Codec>;
+
+/// Room with a question answered by a student, returns the question id and the
+/// connections, as the answers leave with the student
+async fn room_with_answer(app: &TestApp, room: &str) -> (Uuid, [Connection; 2]) {
+    app.create_cups_room(room).await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections(room).await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    answer_question(&mut student_connection, question.id.0, 1).await;
+    (question.id.0, [teacher_connection, student_connection])
+}
+
+#[actix_rt::test]
+async fn snapshot_contains_the_questions_and_answers() {
+    // Arrange
+    let app = spawn_app().await;
+    let (question, _connections) = room_with_answer(&app, "room").await;
+
+    // Act
+    let response = app.get_route("cups/room/snapshot").await;
+    let missing_room = app.get_route("cups/other/snapshot").await;
+
+    // Assert
+    assert!(response
+        .headers()
+        .get("content-disposition")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .contains("room.json"));
+    let snapshot = response.json::<RoomSnapshot>().await.unwrap();
+    assert_eq!(snapshot.name, "room");
+    assert_eq!(snapshot.questions[&question].title, "question");
+    assert_eq!(snapshot.questions[&question].summary(), vec![0, 1]);
+    assert_eq!(missing_room.status().as_u16(), 400);
+}
+
+#[actix_rt::test]
+async fn a_deleted_room_is_restored_from_its_snapshot() {
+    // Arrange
+    let app = spawn_app().await;
+    let (question, _connections) = room_with_answer(&app, "room").await;
+    let snapshot = app.get_room_snapshot("room").await;
+    app.delete_cups_room("room").await;
+
+    // Act
+    let response = app
+        .restore_room(serde_json::json!({ "snapshot": snapshot }))
+        .await;
+
+    // Assert
+    let restored = response.json::<RestoredRoom>().await.unwrap();
+    assert_eq!(restored.name, "room");
+    let restored_snapshot = app.get_room_snapshot("room").await;
+    assert_eq!(restored_snapshot.created_at, snapshot.created_at);
+    assert_eq!(restored_snapshot.questions[&question].summary(), vec![0, 1]);
+    let history = app.get_room_history("room").await;
+    assert!(history.state.students.is_empty());
+    assert!(history.state.teachers.is_empty());
+    assert_eq!(history.state.questions[&question].answers.len(), 1);
+}
+
+#[actix_rt::test]
+async fn restoring_an_existing_room_follows_the_conflict_policy() {
+    // Arrange
+    let app = spawn_app().await;
+    let (question, connections) = room_with_answer(&app, "room").await;
+    let snapshot = app.get_room_snapshot("room").await;
+    let [_, mut student_connection] = connections;
+
+    // Act
+    let rejected = app
+        .restore_room(serde_json::json!({ "snapshot": snapshot }))
+        .await;
+    let renamed = app
+        .restore_room(serde_json::json!({ "snapshot": snapshot, "on_conflict": "Rename" }))
+        .await
+        .json::<RestoredRoom>()
+        .await
+        .unwrap();
+    let renamed_again = app
+        .restore_room(serde_json::json!({ "snapshot": snapshot, "on_conflict": "Rename" }))
+        .await
+        .json::<RestoredRoom>()
+        .await
+        .unwrap();
+    let replaced = app
+        .restore_room(serde_json::json!({ "snapshot": snapshot, "on_conflict": "Replace" }))
+        .await;
+
+    // Assert
+    assert_eq!(rejected.status().as_u16(), 400);
+    assert_eq!(renamed.name, "room-1");
+    assert_eq!(renamed_again.name, "room-2");
+    assert!(replaced.status().is_success());
+    match get_next_ws_msg(&mut student_connection).await {
+        ClientMessage::Disconnected(msg) => {
+            assert_eq!(msg, "The room was replaced by a restored snapshot.")
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    let rooms = app
+        .get_cups_info()
+        .await
+        .rooms
+        .into_iter()
+        .map(|room| room.name)
+        .collect::<Vec<_>>();
+    assert_eq!(rooms, vec!["room", "room-1", "room-2"]);
+    let renamed_snapshot = app.get_room_snapshot("room-1").await;
+    assert_eq!(renamed_snapshot.questions[&question].summary(), vec![0, 1]);
+}
+
+#[actix_rt::test]
+async fn the_replaced_room_sessions_leave_the_restored_room_alone() {
+    // Arrange
+    let app = spawn_app().await;
+    let (question, connections) = room_with_answer(&app, "room").await;
+    let snapshot = app.get_room_snapshot("room").await;
+
+    // Act
+    app.restore_room(serde_json::json!({ "snapshot": snapshot, "on_conflict": "Replace" }))
+        .await;
+    drop(connections);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Assert
+    let restored_snapshot = app.get_room_snapshot("room").await;
+    assert_eq!(restored_snapshot.questions[&question].summary(), vec![0, 1]);
+    let history = app.get_room_history("room").await;
+    assert!(matches!(
+        history.events.last().unwrap().event,
+        RoomEvent::Restored
+    ));
+}
+
+#[actix_rt::test]
+async fn restored_rooms_are_not_linked_to_lti_contexts() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_question, _connections) = room_with_answer(&app, "room").await;
+    let snapshot = serde_json::to_value(app.get_room_snapshot("room").await).unwrap();
+    app.delete_cups_room("room").await;
+    let lti = serde_json::json!({ "context_id": "context", "lineitem": null });
+
+    // Act
+    app.restore_room(serde_json::json!({ "snapshot": modified(&snapshot, "/lti", lti) }))
+        .await;
+
+    // Assert
+    assert_eq!(app.get_room_snapshot("room").await.lti, None);
+}
+
+/// Snapshot with the value at `pointer` replaced
+fn modified(
+    snapshot: &serde_json::Value,
+    pointer: &str,
+    value: serde_json::Value,
+) -> serde_json::Value {
+    let mut snapshot = snapshot.clone();
+    *snapshot.pointer_mut(pointer).unwrap() = value;
+    snapshot
+}
+
+#[actix_rt::test]
+async fn restore_returns_a_400_when_the_snapshot_is_invalid() {
+    // Arrange
+    let app = spawn_app().await;
+    let (question, _connections) = room_with_answer(&app, "room").await;
+    let snapshot = serde_json::to_value(app.get_room_snapshot("room").await).unwrap();
+    app.delete_cups_room("room").await;
+    let test_cases = vec![
+        (
+            modified(&snapshot, "/name", serde_json::json!("")),
+            "an empty name",
+        ),
+        (
+            modified(&snapshot, "/signals", serde_json::json!([])),
+            "no signals",
+        ),
+        (
+            modified(&snapshot, "/webhooks", serde_json::json!(["not a url"])),
+            "an invalid webhook url",
+        ),
+        (
+            modified(
+                &snapshot,
+                &format!("/questions/{question}/options"),
+                serde_json::json!(["a"]),
+            ),
+            "a question with one option",
+        ),
+        (
+            modified(
+                &snapshot,
+                &format!("/questions/{question}/answers"),
+                serde_json::json!({ Uuid::new_v4().to_string(): 2 }),
+            ),
+            "an answer out of the options",
+        ),
+        (
+            modified(&snapshot, "/playlist", serde_json::json!([Uuid::new_v4()])),
+            "an unknown question in the playlist",
+        ),
+        (
+            modified(&snapshot, "/playlist_position", serde_json::json!(0)),
+            "a playlist position out of the playlist",
+        ),
+    ];
+
+    for (snapshot, error_msg) in test_cases {
+        // Act
+        let response = app
+            .restore_room(serde_json::json!({ "snapshot": snapshot }))
+            .await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 when the snapshot had {error_msg}."
+        );
+    }
+    assert!(app.get_cups_info().await.rooms.is_empty());
+}
@@ -0,0 +1,166 @@
+use crate::helpers::{answer_question, create_question, spawn_app};
+use interactive_class::routes::{
+    message::{ClientMessage, ConnectionType, ResultsMode},
+    TemplatesInfo,
+};
+
+#[actix_rt::test]
+async fn clone_room_copies_questions_answers_and_settings() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room_with_signals(
+        "room",
+        serde_json::json!([
+            { "id": "Lost", "label": "I am lost", "color": "#ef4444", "alert": true },
+            { "id": "Fine", "label": "All good", "color": "#22c55e" }
+        ]),
+    )
+    .await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    answer_question(&mut student_connection, question.id.0, 1).await;
+
+    // Act
+    let response = app
+        .clone_cups_room("room", serde_json::json!({ "new_room": "copy" }))
+        .await;
+    let (_, room_info, questions_info) = app
+        .get_ws_room_connection("copy", ConnectionType::Teacher)
+        .await;
+
+    // Assert
+    assert!(response.status().is_success());
+    match room_info {
+        ClientMessage::RoomInfo(info) => {
+            let signals = info
+                .signals
+                .iter()
+                .map(|s| s.id.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(signals, ["Lost", "Fine"]);
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+    match questions_info {
+        Some(ClientMessage::QuestionsInfo(questions)) => {
+            assert_eq!(questions.len(), 1);
+            assert_eq!(questions[0].title, "question");
+            assert_eq!(questions[0].answers, [0, 1]);
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn clone_room_can_leave_out_answers() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    answer_question(&mut student_connection, question.id.0, 1).await;
+
+    // Act
+    app.clone_cups_room(
+        "room",
+        serde_json::json!({ "new_room": "copy", "without_answers": true }),
+    )
+    .await;
+    let (_, _, questions_info) = app
+        .get_ws_room_connection("copy", ConnectionType::Teacher)
+        .await;
+
+    // Assert
+    match questions_info {
+        Some(ClientMessage::QuestionsInfo(questions)) => {
+            assert_eq!(questions[0].answers, [0, 0]);
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[tokio::test]
+async fn clone_room_fails_on_existing_room() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    app.create_cups_room("other").await;
+
+    // Act
+    let response = app
+        .clone_cups_room("room", serde_json::json!({ "new_room": "other" }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[actix_rt::test]
+async fn rooms_can_be_created_from_a_template() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    app.set_auto_share_results("room", "Percentages").await;
+    let (mut teacher_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    app.save_template("room", "course")
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = app
+        .create_cups_room_from_template("section", "course")
+        .await;
+
+    // Assert
+    assert!(response.status().is_success());
+    let cups_info = app.get_cups_info().await;
+    let section = cups_info
+        .rooms
+        .iter()
+        .find(|room| room.name == "section")
+        .unwrap();
+    assert_eq!(section.questions, 1);
+    assert_eq!(section.auto_share_results, Some(ResultsMode::Percentages));
+}
+
+#[tokio::test]
+async fn create_room_fails_with_unknown_template() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.create_cups_room_from_template("room", "missing").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "Template \"missing\" doesn't exists."
+    );
+}
+
+#[tokio::test]
+async fn saved_templates_are_listed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+
+    // Act
+    app.save_template("room", "b").await;
+    app.save_template("room", "a").await;
+    let templates: TemplatesInfo = app.get_route("cups/templates").await.json().await.unwrap();
+
+    // Assert
+    let names = templates
+        .templates
+        .iter()
+        .map(|template| template.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["a", "b"]);
+}
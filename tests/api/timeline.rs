@@ -0,0 +1,126 @@
+use crate::helpers::{
+    create_question, get_next_ws_msg, publish_question, select_cup_color, send_ws_msg, spawn_app,
+};
+use futures::SinkExt;
+use interactive_class::routes::message::{ClientMessage, ConnectionType, CupTimeline};
+
+#[actix_rt::test]
+async fn teachers_get_the_cup_timeline() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    select_cup_color(&mut student_connection, "Red").await;
+    get_next_ws_msg(&mut teacher_connection).await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+    publish_question(&mut teacher_connection, question.id.0, 10).await;
+    select_cup_color(&mut student_connection, "Green").await;
+    get_next_ws_msg(&mut teacher_connection).await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut teacher_connection,
+        serde_json::json!({ "task": "GetCupTimeline", "payload": { "bucket_secs": 60 } }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::CupTimeline(timeline) => {
+            assert_eq!(timeline.bucket_secs, 60);
+            assert_eq!(timeline.buckets.len(), 1);
+            assert_eq!(timeline.buckets[0].cups["Green"], 1);
+            assert_eq!(timeline.buckets[0].cups["Red"], 0);
+            assert_eq!(timeline.publications.len(), 1);
+            assert_eq!(timeline.publications[0].title, "question");
+        }
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn students_cannot_get_the_cup_timeline() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut student_connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Student)
+        .await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut student_connection,
+        serde_json::json!({ "task": "GetCupTimeline", "payload": { "bucket_secs": null } }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Student connections can't do this."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn cup_timeline_endpoint_drops_students_that_leave() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    select_cup_color(&mut student_connection, "Yellow").await;
+    get_next_ws_msg(&mut teacher_connection).await;
+    student_connection.close().await.unwrap();
+    get_next_ws_msg(&mut teacher_connection).await;
+
+    // Act
+    let timeline = app
+        .get_route("cups/timeline?room=room")
+        .await
+        .error_for_status()
+        .unwrap()
+        .json::<CupTimeline>()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(timeline.bucket_secs, 60);
+    assert_eq!(timeline.buckets[0].cups["Yellow"], 0);
+}
+
+#[tokio::test]
+async fn cup_timeline_endpoint_limits_the_bucket_size() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+
+    // Act
+    let timeline = app
+        .get_route(&format!(
+            "cups/timeline?room=room&bucket_secs={}",
+            usize::MAX
+        ))
+        .await
+        .error_for_status()
+        .unwrap()
+        .json::<CupTimeline>()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(timeline.bucket_secs, 24 * 60 * 60);
+    assert_eq!(timeline.buckets.len(), 1);
+}
+
+#[tokio::test]
+async fn cup_timeline_endpoint_fails_on_non_existing_room() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_route("cups/timeline?room=room").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -0,0 +1,110 @@
+use crate::helpers::spawn_app_with;
+use interactive_class::configuration::{Settings, TlsSettings};
+use reqwest::tls::TlsInfo;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
+
+const FIXTURES: &str = "tests/api/fixtures";
+
+/// Copies the fixture certificate `name` to a new directory, so it can be replaced
+fn certificate_files(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("interactive_class-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    copy_certificate(name, &dir);
+    dir
+}
+
+fn copy_certificate(name: &str, dir: &Path) {
+    for file in ["cert", "key"] {
+        std::fs::copy(
+            Path::new(FIXTURES).join(format!("{name}_{file}.pem")),
+            dir.join(format!("{file}.pem")),
+        )
+        .unwrap();
+    }
+}
+
+fn tls_settings(dir: &Path, redirect_port: Option<u16>) -> impl FnOnce(&mut Settings) {
+    let tls = TlsSettings {
+        cert_path: dir.join("cert.pem").to_string_lossy().into_owned(),
+        key_path: dir.join("key.pem").to_string_lossy().into_owned(),
+        redirect_port,
+    };
+    move |c| c.application.tls = Some(tls)
+}
+
+async fn peer_certificate(app_address: &str, client: &reqwest::Client) -> Vec<u8> {
+    let response = client
+        .get(format!("{}/health_check", app_address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    response
+        .extensions()
+        .get::<TlsInfo>()
+        .and_then(|info| info.peer_certificate())
+        .expect("No peer certificate.")
+        .to_vec()
+}
+
+fn fixture_certificate(name: &str) -> Vec<u8> {
+    let pem = std::fs::read(Path::new(FIXTURES).join(format!("{name}_cert.pem"))).unwrap();
+    rustls_pemfile::certs(&mut pem.as_slice())
+        .unwrap()
+        .remove(0)
+}
+
+#[tokio::test]
+async fn the_server_serves_https_with_the_configured_certificate() {
+    // Arrange
+    let dir = certificate_files("tls");
+    let app = spawn_app_with(tls_settings(&dir, None)).await;
+
+    // Act
+    let certificate = peer_certificate(&app.address, &app.api_client).await;
+
+    // Assert
+    assert!(app.address.starts_with("https://"));
+    assert_eq!(certificate, fixture_certificate("tls"));
+}
+
+#[tokio::test]
+async fn the_certificate_is_reloaded_on_sighup() {
+    // Arrange
+    let dir = certificate_files("tls");
+    let app = spawn_app_with(tls_settings(&dir, None)).await;
+    copy_certificate("tls_renewed", &dir);
+
+    // Act
+    unsafe { libc::kill(libc::getpid(), libc::SIGHUP) };
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let certificate = peer_certificate(&app.address, &app.api_client).await;
+
+    // Assert
+    assert_eq!(certificate, fixture_certificate("tls_renewed"));
+}
+
+#[tokio::test]
+async fn http_requests_are_redirected_to_https() {
+    // Arrange
+    let dir = certificate_files("tls");
+    let app = spawn_app_with(tls_settings(&dir, Some(0))).await;
+    let redirect_port = app.redirect_port.expect("No redirect listener.");
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("http://localhost:{}/cups?x=1", redirect_port))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 308);
+    assert_eq!(
+        response.headers()["Location"],
+        format!("{}/cups?x=1", app.address).as_str()
+    );
+}
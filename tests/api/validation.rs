@@ -0,0 +1,179 @@
+use crate::helpers::{
+    answer_question, create_question, get_next_ws_msg, modify_question, send_ws_msg, spawn_app,
+    spawn_app_with,
+};
+use interactive_class::routes::message::{ClientMessage, ConnectionType};
+
+#[tokio::test]
+async fn create_room_fails_with_invalid_names() {
+    // Arrange
+    let app = spawn_app().await;
+    let long_name = "a".repeat(51);
+    let test_cases = [
+        ("", "The name can't be empty."),
+        ("   ", "The name can't be empty."),
+        (
+            long_name.as_str(),
+            "The name can't be longer than 50 characters.",
+        ),
+        ("room/1", "Invalid character in the name: '/'."),
+    ];
+
+    for (name, error_msg) in test_cases {
+        // Act
+        let response = app.create_cups_room(name).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 400, "Room name: {name:?}.");
+        assert_eq!(response.text().await.unwrap(), error_msg);
+    }
+    assert!(app.get_cups_info().await.rooms.is_empty());
+}
+
+#[actix_rt::test]
+async fn create_question_fails_with_invalid_questions() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    let test_cases = [
+        (
+            serde_json::json!({ "title": " ", "options": ["a", "b"] }),
+            "The question title can't be empty.",
+        ),
+        (
+            serde_json::json!({ "title": "question", "options": ["a"] }),
+            "A question needs at least 2 options.",
+        ),
+        (
+            serde_json::json!({ "title": "question", "options": ["a", "b", "a"] }),
+            "Repeated option: \"a\".",
+        ),
+        (
+            serde_json::json!({ "title": "question", "options": ["a", "b"], "correct": 2 }),
+            "Invalid correct option: 2.",
+        ),
+    ];
+
+    for (question, error_msg) in test_cases {
+        // Act
+        let msg = send_ws_msg(
+            &mut connection,
+            serde_json::json!({ "task": "CreateQuestion", "payload": question }),
+        )
+        .await;
+
+        // Assert
+        match msg {
+            ClientMessage::Error(msg) => assert_eq!(msg, error_msg),
+            msg => panic!("Invalid msg: {msg:?}"),
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn create_question_fails_over_the_room_limit() {
+    // Arrange
+    let app = spawn_app_with(|c| c.limits.max_questions_per_room = 1).await;
+    app.create_cups_room("room").await;
+    let (mut connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    create_question(&mut connection, "first", &["a", "b"]).await;
+
+    // Act
+    let msg = send_ws_msg(
+        &mut connection,
+        serde_json::json!({
+            "task": "CreateQuestion",
+            "payload": { "title": "second", "options": ["a", "b"] }
+        }),
+    )
+    .await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "A room can't have more than 1 questions."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn modify_question_fails_with_invalid_options() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    let question = create_question(&mut connection, "question", &["a", "b"]).await;
+
+    // Act
+    let msg = modify_question(&mut connection, question.id.0, None, Some(vec!["a", ""])).await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "The question options can't be empty."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn answers_outside_the_options_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections("room").await;
+    let question = create_question(&mut teacher_connection, "question", &["a", "b"]).await;
+
+    // Act
+    answer_question(&mut student_connection, question.id.0, 2).await;
+    let msg = get_next_ws_msg(&mut teacher_connection).await;
+
+    // Assert
+    match msg {
+        ClientMessage::Error(msg) => assert_eq!(msg, "Invalid answer: 2."),
+        msg => panic!("Invalid msg: {msg:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn publications_longer_than_the_limit_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+    let (mut connection, _, _) = app
+        .get_ws_room_connection("room", ConnectionType::Teacher)
+        .await;
+    let question = create_question(&mut connection, "question", &["a", "b"]).await;
+    let test_cases = [
+        serde_json::json!({
+            "task": "PublishQuestion",
+            "payload": { "id": question.id, "secs": usize::MAX }
+        }),
+        serde_json::json!({ "task": "NextQuestion", "payload": { "secs": 86401 } }),
+        serde_json::json!({
+            "task": "ScheduleQuestion",
+            "payload": { "id": question.id, "secs": usize::MAX, "delay_secs": 60 }
+        }),
+    ];
+
+    for msg in test_cases {
+        // Act
+        let msg = send_ws_msg(&mut connection, msg).await;
+
+        // Assert
+        match msg {
+            ClientMessage::Error(msg) => assert_eq!(
+                msg,
+                "A question can't be published for more than 86400 seconds."
+            ),
+            msg => panic!("Invalid msg: {msg:?}"),
+        }
+    }
+    let other = create_question(&mut connection, "other", &["a", "b"]).await;
+    assert_eq!(other.title, "other");
+}
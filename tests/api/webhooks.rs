@@ -0,0 +1,280 @@
+use crate::helpers::{
+    answer_question, create_question, get_webhooks, publish_question, select_cup_color, spawn_app,
+    spawn_app_with, webhook_server,
+};
+use interactive_class::{
+    configuration::get_configuration,
+    webhooks::{sign, WebhookEvent, SIGNATURE_HEADER},
+    Application,
+};
+use std::time::Duration;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+#[tokio::test]
+async fn room_events_are_sent_to_global_webhooks() {
+    // Arrange
+    let mock_server = webhook_server().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+    let app = spawn_app_with(|c| c.webhooks.urls = vec![mock_server.uri()]).await;
+
+    // Act
+    app.create_cups_room("room").await;
+    app.delete_cups_room("room").await;
+    let webhooks = get_webhooks(&mock_server, 2).await;
+
+    // Assert
+    let events = webhooks.into_iter().map(|d| d.event).collect::<Vec<_>>();
+    assert!(events.contains(&WebhookEvent::RoomCreated {
+        room: "room".to_string()
+    }));
+    assert!(events.contains(&WebhookEvent::RoomDeleted {
+        room: "room".to_string()
+    }));
+}
+
+#[tokio::test]
+async fn webhooks_are_signed() {
+    // Arrange
+    let mock_server = webhook_server().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+    let mut secret = String::new();
+    let app = spawn_app_with(|c| {
+        c.webhooks.urls = vec![mock_server.uri()];
+        secret = secrecy::ExposeSecret::expose_secret(c.webhooks.secret.as_ref().unwrap()).clone();
+    })
+    .await;
+
+    // Act
+    app.create_cups_room("room").await;
+    get_webhooks(&mock_server, 1).await;
+
+    // Assert
+    let request = &mock_server.received_requests().await.unwrap()[0];
+    let signature = request.headers.get(SIGNATURE_HEADER).unwrap();
+    assert_eq!(signature.to_str().unwrap(), sign(&secret, &request.body));
+}
+
+#[tokio::test]
+async fn failed_webhooks_are_retried() {
+    // Arrange
+    let mock_server = webhook_server().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let app = spawn_app_with(|c| c.webhooks.urls = vec![mock_server.uri()]).await;
+
+    // Act
+    app.create_cups_room("room").await;
+    let webhooks = get_webhooks(&mock_server, 3).await;
+
+    // Assert
+    assert!(webhooks.iter().all(|d| d.id == webhooks[0].id));
+}
+
+#[tokio::test]
+async fn webhook_redirects_are_not_followed() {
+    // Arrange
+    let mock_server = webhook_server().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .respond_with(
+            ResponseTemplate::new(307)
+                .insert_header("Location", format!("{}/redirected", mock_server.uri())),
+        )
+        .mount(&mock_server)
+        .await;
+    let app =
+        spawn_app_with(|c| c.webhooks.urls = vec![format!("{}/hook", mock_server.uri())]).await;
+
+    // Act
+    app.create_cups_room("room").await;
+    get_webhooks(&mock_server, 1).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Assert
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].url.path(), "/hook");
+}
+
+#[tokio::test]
+async fn create_webhook_fails_with_invalid_url() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_cups_room("room").await;
+
+    // Act
+    let error_msg = app
+        .create_webhook("room", "not an url")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(error_msg, "Invalid webhook url: \"not an url\".");
+}
+
+#[tokio::test]
+async fn create_webhook_fails_with_local_or_private_urls() {
+    // Arrange
+    let app = spawn_app_with(|c| c.webhooks.allow_private_urls = false).await;
+    app.create_cups_room("room").await;
+    let test_cases = vec![
+        ("ftp://hooks.example.com", "a non http url"),
+        ("http://localhost:8000/hook", "localhost"),
+        ("http://127.0.0.1/hook", "a loopback address"),
+        ("http://10.0.0.5/hook", "a private address"),
+        ("http://169.254.169.254/latest", "a link local address"),
+        ("http://[::1]/hook", "a loopback ipv6 address"),
+        ("http://[fd00::1]/hook", "a unique local ipv6 address"),
+    ];
+
+    for (url, error_msg) in test_cases {
+        // Act
+        let response = app.create_webhook("room", url).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 when the url was {error_msg}."
+        );
+    }
+    let response = app
+        .create_webhook("room", "https://hooks.example.com/room")
+        .await;
+    assert!(response.status().is_success());
+}
+
+#[tokio::test]
+async fn room_webhooks_need_a_signing_secret() {
+    // Arrange
+    let app = spawn_app_with(|c| c.webhooks.secret = None).await;
+    app.create_cups_room("room").await;
+
+    // Act
+    let error_msg = app
+        .create_webhook("room", "https://hooks.example.com/room")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        error_msg,
+        "Room webhooks are disabled, the server has no signing secret."
+    );
+}
+
+#[tokio::test]
+async fn global_webhooks_without_a_secret_fail_at_startup() {
+    // Arrange
+    let mut configuration = get_configuration().unwrap();
+    configuration.application.port = 0;
+    configuration.webhooks.urls = vec!["https://hooks.example.com".to_string()];
+    configuration.webhooks.secret = None;
+
+    // Act
+    let application = Application::build(configuration).await;
+
+    // Assert
+    assert!(application.is_err());
+}
+
+#[actix_rt::test]
+async fn room_webhooks_receive_question_events() {
+    // Arrange
+    let mock_server = webhook_server().await;
+    Mock::given(method("POST"))
+        .and(path("/room"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+    let app = spawn_app().await;
+    let room_name = "test_room";
+    let options = vec!["option1", "option2"];
+    app.create_cups_room(room_name).await;
+    app.create_webhook(room_name, &format!("{}/room", mock_server.uri()))
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let (mut teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections(room_name).await;
+    let question_info = create_question(&mut teacher_connection, "question", &options).await;
+    let id = question_info.id.0;
+    publish_question(&mut teacher_connection, id, 1).await;
+    answer_question(&mut student_connection, id, 1).await;
+    // StudentJoined, QuestionPublished, QuestionClosed
+    let webhooks = get_webhooks(&mock_server, 3).await;
+
+    // Assert
+    let events = webhooks.into_iter().map(|d| d.event).collect::<Vec<_>>();
+    assert_eq!(
+        events[0],
+        WebhookEvent::StudentJoined {
+            room: room_name.to_string(),
+            connections: 1
+        }
+    );
+    assert!(matches!(events[1], WebhookEvent::QuestionPublished { .. }));
+    match &events[2] {
+        WebhookEvent::QuestionClosed { summary, .. } => assert_eq!(summary, &vec![0, 1]),
+        event => panic!("Invalid event: {event:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn webhook_is_sent_when_red_cups_cross_the_threshold() {
+    // Arrange
+    let mock_server = webhook_server().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+    let app = spawn_app_with(|c| {
+        c.webhooks.urls = vec![mock_server.uri()];
+        c.webhooks.red_cup_threshold = 0.5;
+    })
+    .await;
+    let room_name = "test_room";
+    app.create_cups_room(room_name).await;
+
+    // Act
+    let (_teacher_connection, mut student_connection) =
+        app.get_ws_teacher_student_connections(room_name).await;
+    select_cup_color(&mut student_connection, "Red").await;
+    // RoomCreated, StudentJoined, CupThresholdCrossed
+    let webhooks = get_webhooks(&mock_server, 3).await;
+
+    // Assert
+    assert_eq!(
+        webhooks[2].event,
+        WebhookEvent::CupThresholdCrossed {
+            room: room_name.to_string(),
+            connections: 1,
+            red: 1,
+            threshold: 0.5
+        }
+    );
+}